@@ -0,0 +1,318 @@
+//! Conversion of bare URLs and email addresses between forms of links.
+
+use std::ops::Range;
+
+use pulldown_cmark::{CowStr, Event, LinkType, Tag};
+
+use crate::{BareUrls, EmailLinks};
+
+type OffsetEvent<'a> = (Event<'a>, Range<usize>);
+
+/// Replaces the bare `http://` and `https://` URLs in text outside of the
+/// verbatim regions with links, written as autolinks or inline links
+/// depending on the style. Text within links, images, code blocks, and HTML
+/// anchors is kept as is.
+pub(crate) fn convert<'a>(
+    events: Vec<OffsetEvent<'a>>,
+    verbatim: &[Range<usize>],
+    style: BareUrls,
+) -> Vec<OffsetEvent<'a>> {
+    if style == BareUrls::Preserve {
+        return events;
+    }
+    let mut out = Vec::with_capacity(events.len());
+    // The depth of the links, images, code blocks and anchors that the
+    // current event is within.
+    let mut depth = 0usize;
+    let mut events = events.into_iter().peekable();
+    while let Some((event, range)) = events.next() {
+        match &event {
+            Event::Start(Tag::Link(..) | Tag::Image(..) | Tag::CodeBlock(_)) => depth += 1,
+            Event::End(Tag::Link(..) | Tag::Image(..) | Tag::CodeBlock(_)) => {
+                depth = depth.saturating_sub(1)
+            }
+            Event::Html(html) if is_anchor_start(html) => depth += 1,
+            Event::Html(html) if is_anchor_end(html) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        let Event::Text(text) = &event else {
+            out.push((event, range));
+            continue;
+        };
+        if depth > 0 || verbatim.iter().any(|v| v.contains(&range.start)) {
+            out.push((event, range));
+            continue;
+        }
+
+        // The parser splits text at characters that may start inline markup,
+        // so a URL may continue into the text events that follow.
+        let mut group = vec![(text.clone(), range)];
+        while let Some((Event::Text(text), next)) = events.peek() {
+            let end = group.last().map_or(0, |(_, r)| r.end);
+            if next.start != end {
+                break;
+            }
+            group.push((text.clone(), next.clone()));
+            events.next();
+        }
+        push_group(&mut out, group, style);
+    }
+    out
+}
+
+/// Rewrites the links to email addresses outside of the verbatim regions in
+/// the form of the style: inline `mailto:` links whose text is the address,
+/// and `mailto:` autolinks, as email autolinks, or email autolinks as inline
+/// links.
+pub(crate) fn convert_emails<'a>(
+    events: Vec<OffsetEvent<'a>>,
+    verbatim: &[Range<usize>],
+    style: EmailLinks,
+) -> Vec<OffsetEvent<'a>> {
+    if style == EmailLinks::Preserve {
+        return events;
+    }
+    let mut out = Vec::with_capacity(events.len());
+    let mut events = events.into_iter().peekable();
+    while let Some((event, range)) = events.next() {
+        let Event::Start(Tag::Link(typ, dest, title)) = &event else {
+            out.push((event, range));
+            continue;
+        };
+        if verbatim.iter().any(|v| v.contains(&range.start)) {
+            out.push((event, range));
+            continue;
+        }
+        let address = match typ {
+            LinkType::Email => Some(dest.to_string()),
+            LinkType::Inline | LinkType::Autolink if title.is_empty() => {
+                strip_mailto(dest).map(str::to_string)
+            }
+            _ => None,
+        };
+        let Some(address) = address else {
+            out.push((event, range));
+            continue;
+        };
+
+        // Links can't be nested, so the link ends at the next link end.
+        let mut inner = Vec::new();
+        while let Some((event, range)) =
+            events.next_if(|(e, _)| !matches!(e, Event::End(Tag::Link(..))))
+        {
+            inner.push((event, range));
+        }
+        let end = events.next();
+        let text = inner
+            .iter()
+            .map(|(event, _)| match event {
+                Event::Text(text) => Some(&**text),
+                _ => None,
+            })
+            .collect::<Option<String>>();
+        let is_address =
+            text.is_some_and(|text| text == address || Some(&*address) == strip_mailto(&text));
+        let tag = match (style, typ) {
+            (EmailLinks::Autolink, LinkType::Inline | LinkType::Autolink)
+                if is_address && is_email(&address) =>
+            {
+                inner = vec![(Event::Text(CowStr::from(address.clone())), range.clone())];
+                Some(Tag::Link(
+                    LinkType::Email,
+                    CowStr::from(address),
+                    CowStr::from(""),
+                ))
+            }
+            (EmailLinks::Explicit, LinkType::Email | LinkType::Autolink) => {
+                inner = split_text(&address)
+                    .into_iter()
+                    .map(|event| (event, range.clone()))
+                    .collect();
+                let dest = format!("mailto:{address}");
+                Some(Tag::Link(
+                    LinkType::Inline,
+                    CowStr::from(dest),
+                    CowStr::from(""),
+                ))
+            }
+            _ => None,
+        };
+        match tag {
+            Some(tag) => {
+                let end_range = end.as_ref().map_or(range.clone(), |(_, r)| r.clone());
+                out.push((Event::Start(tag.clone()), range));
+                out.extend(inner);
+                out.push((Event::End(tag), end_range));
+            }
+            None => {
+                out.push((event, range));
+                out.extend(inner);
+                out.extend(end);
+            }
+        }
+    }
+    out
+}
+
+// Returns the address of a `mailto:` destination, matching the scheme
+// case-insensitively.
+fn strip_mailto(dest: &str) -> Option<&str> {
+    dest.get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+        .map(|_| &dest[7..])
+}
+
+// Returns whether the address can be written as an email autolink.
+fn is_email(address: &str) -> bool {
+    let Some((local, domain)) = address.split_once('@') else {
+        return false;
+    };
+    let local_chars = |c: char| c.is_ascii_alphanumeric() || ".!#$%&'*+/=?^_`{|}~-".contains(c);
+    !local.is_empty()
+        && local.chars().all(local_chars)
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+// Pushes the events of a run of adjacent text events, with the URLs in their
+// combined text replaced by links.
+fn push_group<'a>(
+    out: &mut Vec<OffsetEvent<'a>>,
+    group: Vec<(CowStr<'a>, Range<usize>)>,
+    style: BareUrls,
+) {
+    let text = group.iter().map(|(text, _)| &**text).collect::<String>();
+    let urls = find_urls(&text);
+    if urls.is_empty() {
+        out.extend(
+            group
+                .into_iter()
+                .map(|(text, range)| (Event::Text(text), range)),
+        );
+        return;
+    }
+
+    // The offset of each text event in the combined text.
+    let mut offsets = Vec::with_capacity(group.len());
+    let mut offset = 0;
+    for (text, _) in &group {
+        offsets.push(offset);
+        offset += text.len();
+    }
+    let range_at = |i: usize| {
+        let index = offsets
+            .partition_point(|&offset| offset <= i)
+            .saturating_sub(1);
+        group[index].1.clone()
+    };
+    let push_plain = |out: &mut Vec<OffsetEvent<'a>>, span: Range<usize>| {
+        for (index, (text, range)) in group.iter().enumerate() {
+            let start = offsets[index];
+            let end = start + text.len();
+            let (from, to) = (span.start.max(start), span.end.min(end));
+            if from >= to {
+                continue;
+            }
+            let text = if from == start && to == end {
+                text.clone()
+            } else {
+                CowStr::from(text[from - start..to - start].to_string())
+            };
+            out.push((Event::Text(text), range.clone()));
+        }
+    };
+
+    let mut pos = 0;
+    for span in urls {
+        push_plain(out, pos..span.start);
+        let range = range_at(span.start).start..range_at(span.end - 1).end;
+        let url = &text[span.clone()];
+        let (typ, events) = match style {
+            BareUrls::Inline => (LinkType::Inline, split_text(url)),
+            _ => (
+                LinkType::Autolink,
+                vec![Event::Text(CowStr::from(url.to_string()))],
+            ),
+        };
+        let tag = Tag::Link(typ, CowStr::from(url.to_string()), CowStr::from(""));
+        out.push((Event::Start(tag.clone()), range.clone()));
+        out.extend(events.into_iter().map(|event| (event, range.clone())));
+        out.push((Event::End(tag), range));
+        pos = span.end;
+    }
+    push_plain(out, pos..text.len());
+}
+
+// Returns the ranges of the bare URLs in the text. A URL starts with
+// `http://` or `https://` that doesn't follow an alphanumeric character, and
+// continues until whitespace or `<`, without trailing punctuation or
+// unbalanced closing parentheses.
+fn find_urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut pos = 0;
+    while let Some(i) = text[pos..].find("http").map(|i| pos + i) {
+        pos = i + 4;
+        let scheme = ["https://", "http://"]
+            .into_iter()
+            .find(|scheme| text[i..].starts_with(scheme));
+        let Some(scheme) = scheme else {
+            continue;
+        };
+        if text[..i].ends_with(|c: char| c.is_alphanumeric()) {
+            continue;
+        }
+        let rest = &text[i..];
+        let mut end = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>'))
+            .unwrap_or(rest.len());
+        loop {
+            let url = &rest[..end];
+            let punctuation =
+                url.ends_with(['?', '!', '.', ',', ':', ';', '*', '_', '~', '\'', '"']);
+            let unbalanced =
+                url.ends_with(')') && url.matches(')').count() > url.matches('(').count();
+            if !punctuation && !unbalanced {
+                break;
+            }
+            end -= 1;
+        }
+        if !rest
+            .get(scheme.len()..end)
+            .is_some_and(|host| host.starts_with(char::is_alphanumeric))
+        {
+            continue;
+        }
+        urls.push(i..i + end);
+        pos = i + end;
+    }
+    urls
+}
+
+// Splits the text of an inline link before the characters that may need to
+// be escaped, which the formatter only checks at the start of text.
+fn split_text(text: &str) -> Vec<Event<'static>> {
+    let mut events = Vec::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if i > start && "\\<>*_`[]~|".contains(c) {
+            events.push(Event::Text(CowStr::from(text[start..i].to_string())));
+            start = i;
+        }
+    }
+    events.push(Event::Text(CowStr::from(text[start..].to_string())));
+    events
+}
+
+fn is_anchor_start(html: &str) -> bool {
+    let html = html.to_ascii_lowercase();
+    html.starts_with("<a ") || html.starts_with("<a>")
+}
+
+fn is_anchor_end(html: &str) -> bool {
+    html.trim_end().eq_ignore_ascii_case("</a>")
+}
@@ -0,0 +1,246 @@
+//! A Language Server Protocol server formatting and linting markdown documents
+//! with cmarkfmt, communicating over stdio.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::process::ExitCode;
+
+use cmarkfmt::{Config, Formatter, FormatterConfig};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{Formatting, RangeFormatting, Request as RequestTrait};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentFormattingParams, DocumentRangeFormattingParams,
+    NumberOrString, OneOf, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url,
+};
+use similar::{DiffTag, TextDiff};
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+fn main() -> ExitCode {
+    let (connection, io_threads) = Connection::stdio();
+    let result = run(&connection);
+    drop(connection);
+    match result.and_then(|()| io_threads.join().map_err(Into::into)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("cmarkfmt-lsp: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(connection: &Connection) -> Result<(), BoxError> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        document_range_formatting_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                server.handle_request(req)?;
+            }
+            Message::Notification(not) => server.handle_notification(not)?,
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    /// The text of every open document.
+    documents: HashMap<Url, String>,
+}
+
+impl Server<'_> {
+    fn handle_request(&mut self, req: Request) -> Result<(), BoxError> {
+        let resp = match req.method.as_str() {
+            Formatting::METHOD => {
+                let (id, params) = req.extract::<DocumentFormattingParams>(Formatting::METHOD)?;
+                match self.edits(&params.text_document.uri, None) {
+                    Ok(edits) => Response::new_ok(id, edits),
+                    Err(err) => Response::new_err(id, ErrorCode::RequestFailed as i32, err),
+                }
+            }
+            RangeFormatting::METHOD => {
+                let (id, params) =
+                    req.extract::<DocumentRangeFormattingParams>(RangeFormatting::METHOD)?;
+                match self.edits(&params.text_document.uri, Some(params.range)) {
+                    Ok(edits) => Response::new_ok(id, edits),
+                    Err(err) => Response::new_err(id, ErrorCode::RequestFailed as i32, err),
+                }
+            }
+            method => Response::new_err(
+                req.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported method: {method}"),
+            ),
+        };
+        self.connection.sender.send(resp.into())?;
+        Ok(())
+    }
+
+    fn handle_notification(&mut self, not: Notification) -> Result<(), BoxError> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = extract::<DidOpenTextDocument>(not)?;
+                let doc = params.text_document;
+                self.documents.insert(doc.uri.clone(), doc.text);
+                self.publish_diagnostics(doc.uri)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = extract::<DidChangeTextDocument>(not)?;
+                let uri = params.text_document.uri;
+                // With full synchronization, the last change holds the whole
+                // document.
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                self.publish_diagnostics(uri)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = extract::<DidCloseTextDocument>(not)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.send_diagnostics(uri, Vec::new())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the edits formatting the document, keeping only those touching
+    /// `range` if provided.
+    fn edits(&self, uri: &Url, range: Option<Range>) -> Result<Vec<TextEdit>, String> {
+        let input = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| format!("unknown document: {uri}"))?;
+        let config = config(uri)?;
+        let output = Formatter::from(&config)
+            .try_format_cmark(input)
+            .map_err(|err| err.to_string())?;
+
+        let diff = TextDiff::from_lines(input.as_str(), output.as_str());
+        let mut offsets = Vec::with_capacity(diff.old_slices().len() + 1);
+        let mut offset = 0;
+        offsets.push(offset);
+        for line in diff.old_slices() {
+            offset += line.len();
+            offsets.push(offset);
+        }
+
+        let edits = diff
+            .ops()
+            .iter()
+            .filter(|op| op.tag() != DiffTag::Equal)
+            .filter(|op| match &range {
+                Some(range) => overlaps(&op.old_range(), range),
+                None => true,
+            })
+            .map(|op| {
+                let old = op.old_range();
+                TextEdit {
+                    range: Range {
+                        start: position(input, offsets[old.start]),
+                        end: position(input, offsets[old.end]),
+                    },
+                    new_text: diff.new_slices()[op.new_range()].concat(),
+                }
+            })
+            .collect();
+        Ok(edits)
+    }
+
+    fn publish_diagnostics(&self, uri: Url) -> Result<(), BoxError> {
+        let Some(input) = self.documents.get(&uri) else {
+            return Ok(());
+        };
+        let Ok(config) = config(&uri) else {
+            return Ok(());
+        };
+        let diagnostics = Formatter::from(&config)
+            .lint(input)
+            .into_iter()
+            .map(|diagnostic| Diagnostic {
+                range: Range {
+                    start: position(input, diagnostic.range.start),
+                    end: position(input, diagnostic.range.end),
+                },
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(diagnostic.rule.id().to_string())),
+                source: Some("cmarkfmt".to_string()),
+                message: diagnostic.message,
+                ..Diagnostic::default()
+            })
+            .collect();
+        self.send_diagnostics(uri, diagnostics)
+    }
+
+    fn send_diagnostics(&self, uri: Url, diagnostics: Vec<Diagnostic>) -> Result<(), BoxError> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        };
+        let not = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(not.into())?;
+        Ok(())
+    }
+}
+
+fn extract<N: NotificationTrait>(not: Notification) -> Result<N::Params, BoxError> {
+    Ok(not.extract(N::METHOD)?)
+}
+
+/// Returns the formatter settings for the document: those resolved by
+/// `Config::resolve` for files, and the defaults otherwise.
+fn config(uri: &Url) -> Result<FormatterConfig, String> {
+    match uri.to_file_path() {
+        Ok(path) => {
+            Config::resolve(&path, None, &FormatterConfig::default()).map_err(|err| err.to_string())
+        }
+        Err(()) => Ok(FormatterConfig::default()),
+    }
+}
+
+/// Returns true if the lines of the input in `lines` touch `range`. Lines that
+/// are only inserted touch it if they are inserted within it.
+fn overlaps(lines: &std::ops::Range<usize>, range: &Range) -> bool {
+    let start = range.start.line as usize;
+    let mut end = range.end.line as usize;
+    if range.end.character == 0 && end > start {
+        end -= 1;
+    }
+    if lines.is_empty() {
+        (start..=end + 1).contains(&lines.start)
+    } else {
+        lines.start <= end && start < lines.end
+    }
+}
+
+/// Converts a byte offset in the input to an LSP position, in UTF-16 code
+/// units.
+fn position(input: &str, offset: usize) -> Position {
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
@@ -0,0 +1,47 @@
+//! The lines changed in files, according to `git diff`.
+
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::process::Command;
+
+/// Returns the 1-based line ranges of the file at `path` that differ from the
+/// revision `rev`. Where lines were only removed, the line following them is
+/// included.
+pub(crate) fn changed_lines(rev: &str, path: &Path) -> io::Result<Vec<RangeInclusive<usize>>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["diff", "--no-color", "--no-ext-diff", "-U0", rev, "--"])
+        .arg(name)
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!("git diff: {}", stderr.trim())));
+    }
+    Ok(parse_hunks(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Returns the new line ranges of the hunks in a unified diff, from headers
+/// like `@@ -10,2 +12,3 @@`.
+fn parse_hunks(diff: &str) -> Vec<RangeInclusive<usize>> {
+    diff.lines()
+        .filter_map(|line| line.strip_prefix("@@ "))
+        .filter_map(|header| header.split_whitespace().nth(1)?.strip_prefix('+'))
+        .filter_map(|new| {
+            let (start, count): (usize, usize) = match new.split_once(',') {
+                Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+                None => (new.parse().ok()?, 1),
+            };
+            Some(match count {
+                0 => start.max(1)..=start.max(1),
+                _ => start..=start + count - 1,
+            })
+        })
+        .collect()
+}
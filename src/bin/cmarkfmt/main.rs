@@ -0,0 +1,906 @@
+//! The `cmarkfmt` command-line tool.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+#[cfg(feature = "watch")]
+use std::{collections::BTreeSet, sync::mpsc, time::Duration};
+
+use clap::{Parser, ValueEnum};
+use cmarkfmt::{
+    BareUrls, BlockKind, BlockquoteJoin, BlockquoteNesting, BoldItalic, BulletStyle, ByteOrderMark,
+    Config, ContinuationIndent, DuplicateHeadings, EmailLinks, EmptyLinks, FenceChar, FinalNewline,
+    Flavor, FootnotePlacement, Formatter, FormatterConfig, HardBreak, HeadingAttributeOrder,
+    HeadingAttributes, HeadingStyle, Ignore, InfoString, LabelCase, LineBreakStyle, LineEnding,
+    ListMarkerAlignment, ListSpacing, OrderedListDelimiter, OrderedListNumbering, Preset,
+    RaggedRows, RefDefPolicy, ReferenceLinks, TableLineBreak, TableStyle, Tabs, TitleQuote,
+    TrailingWhitespace, WidthMode,
+};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use similar::TextDiff;
+
+use crate::report::{FileReport, OutputFormat};
+
+mod git;
+mod report;
+
+/// Format CommonMark files.
+///
+/// Formatted output is written to stdout, unless `--in-place` or `--check` is
+/// provided. When no files are provided, markdown is read from stdin.
+/// Directories are searched recursively for markdown files, skipping hidden
+/// entries.
+///
+/// Exits with status 1 if `--check` finds unformatted input, and 2 on errors.
+#[derive(Debug, Parser)]
+#[command(name = "cmarkfmt", version)]
+struct Args {
+    /// The files or directories to format.
+    files: Vec<PathBuf>,
+
+    /// Read NUL-separated paths of files or directories to format from the
+    /// file, or stdin if `-`, as written by `git ls-files -z`.
+    #[arg(long, value_name = "PATH", conflicts_with = "stdin_filepath")]
+    files_from: Option<PathBuf>,
+
+    /// Rewrite the files in place.
+    #[arg(short, long, visible_alias = "write")]
+    in_place: bool,
+
+    /// Check that the input is formatted, printing a diff of the changes
+    /// formatting would make.
+    #[arg(long, conflicts_with = "in_place")]
+    check: bool,
+
+    /// The format of the results of `--check`.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, requires = "check")]
+    output_format: OutputFormat,
+
+    /// Report relative links and images whose target file or heading doesn't
+    /// exist in the results of `--check`.
+    #[arg(long, requires = "check")]
+    check_links: bool,
+
+    /// The path of the file being read from stdin, used to pick comment
+    /// syntax and in messages.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["files", "in_place"])]
+    stdin_filepath: Option<PathBuf>,
+
+    /// Keep running, rewriting the files in place whenever they change.
+    #[cfg(feature = "watch")]
+    #[arg(long, requires = "files", conflicts_with = "check")]
+    watch: bool,
+
+    /// Format the markdown within source-code comments instead of markdown
+    /// files.
+    #[arg(long)]
+    comments: bool,
+
+    /// A comment prefix holding markdown, overriding the defaults for each
+    /// file extension. Can be repeated.
+    #[arg(long, value_name = "PREFIX", requires = "comments")]
+    comment_prefix: Vec<String>,
+
+    /// The configuration file to use in place of the nearest
+    /// `.cmarkfmt.toml`.
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// The preset to start from before applying other options.
+    #[arg(long, value_enum)]
+    preset: Option<Preset>,
+
+    /// Only format the provided kinds of top-level blocks. Can be repeated or
+    /// comma-separated.
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KIND")]
+    only: Vec<BlockArg>,
+
+    /// Only format the top-level blocks overlapping lines that differ from
+    /// the git revision, HEAD by default. Files without changes are left
+    /// untouched.
+    #[arg(
+        long,
+        value_name = "REV",
+        num_args = 0..=1,
+        default_missing_value = "HEAD",
+        requires = "files",
+        conflicts_with = "comments"
+    )]
+    git_diff: Option<String>,
+
+    /// Keep the markers of GitHub alerts, like `> [!NOTE]`, unescaped.
+    #[arg(long)]
+    alerts: bool,
+
+    /// Align table columns whose body cells are all numbers to the right.
+    #[arg(long)]
+    align_numeric_columns: bool,
+
+    /// How bare URLs in text are written.
+    #[arg(long, value_enum)]
+    bare_urls: Option<BareUrls>,
+
+    /// Don't write a blank line after items of tight lists ending in a
+    /// blockquote or code block.
+    #[arg(long)]
+    no_blank_line_after_block_items: bool,
+
+    /// The number of blank lines after headings.
+    #[arg(long, value_name = "N")]
+    blank_lines_after_headings: Option<usize>,
+
+    /// The number of blank lines before headings.
+    #[arg(long, value_name = "N")]
+    blank_lines_before_headings: Option<usize>,
+
+    /// The blockquote string.
+    #[arg(long, value_name = "STRING")]
+    blockquote: Option<String>,
+
+    /// How adjacent blockquotes are separated.
+    #[arg(long, value_enum)]
+    blockquote_join: Option<BlockquoteJoin>,
+
+    /// How the markers of nested blockquotes are written.
+    #[arg(long, value_enum)]
+    blockquote_nesting: Option<BlockquoteNesting>,
+
+    /// The number of spaces after each blockquote marker.
+    #[arg(long, value_name = "N")]
+    blockquote_spacing: Option<usize>,
+
+    /// Convert paragraphs of only strong text to headings of this level.
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(u8).range(1..=6))]
+    bold_headings: Option<u8>,
+
+    /// How text that is both strong and emphasized is written.
+    #[arg(long, value_enum)]
+    bold_italic: Option<BoldItalic>,
+
+    /// Which character the markers of unordered lists are written with.
+    #[arg(long, value_enum)]
+    bullet_style: Option<BulletStyle>,
+
+    /// Whether the output starts with a UTF-8 byte order mark.
+    #[arg(long, value_enum)]
+    byte_order_mark: Option<ByteOrderMark>,
+
+    /// The character that fenced code blocks are written with.
+    #[arg(long, value_enum)]
+    code_fence: Option<FenceChar>,
+
+    /// How the lines of list items after the first are indented.
+    #[arg(long, value_enum)]
+    continuation_indent: Option<ContinuationIndent>,
+
+    /// Convert HTML blocks holding a simple table to pipe tables.
+    #[arg(long)]
+    convert_html_tables: bool,
+
+    /// How headings with the same anchor as an earlier heading are handled.
+    #[arg(long, value_enum)]
+    duplicate_headings: Option<DuplicateHeadings>,
+
+    /// The form that links to email addresses are written in.
+    #[arg(long, value_enum)]
+    email_links: Option<EmailLinks>,
+
+    /// The emphasis string.
+    #[arg(long, value_name = "STRING")]
+    emphasis: Option<String>,
+
+    /// How links and images with empty destinations or text are handled.
+    #[arg(long, value_enum)]
+    empty_links: Option<EmptyLinks>,
+
+    /// Whether the output ends with a newline.
+    #[arg(long, value_enum)]
+    final_newline: Option<FinalNewline>,
+
+    /// The dialect of markdown that input is parsed as.
+    #[arg(long, value_enum)]
+    flavor: Option<Flavor>,
+
+    /// Where top-level footnote definitions are written.
+    #[arg(long, value_enum)]
+    footnote_placement: Option<FootnotePlacement>,
+
+    /// Rename footnote labels to sequential numbers, in the order of their
+    /// first reference.
+    #[arg(long)]
+    footnote_renumbering: bool,
+
+    /// How hard line breaks are written.
+    #[arg(long, value_enum)]
+    hard_break: Option<HardBreak>,
+
+    /// The order that the attributes of headings are written in.
+    #[arg(long, value_enum)]
+    heading_attribute_order: Option<HeadingAttributeOrder>,
+
+    /// How the attributes of headings, like `{ #id .class }`, are written.
+    #[arg(long, value_enum)]
+    heading_attributes: Option<HeadingAttributes>,
+
+    /// Shift the levels of headings by this number, e.g. 1 to write
+    /// first-level headings as second-level ones.
+    #[arg(long, value_name = "N", allow_negative_numbers = true)]
+    heading_shift: Option<i8>,
+
+    /// How first- and second-level headings are written.
+    #[arg(long, value_enum)]
+    heading_style: Option<HeadingStyle>,
+
+    /// Indent the content of list items by this width, from 2 to 4, or 0 to
+    /// indent it by the width of the marker.
+    #[arg(long, value_name = "N")]
+    indent_width: Option<usize>,
+
+    /// How the info strings of fenced code blocks are written.
+    #[arg(long, value_enum)]
+    info_string: Option<InfoString>,
+
+    /// Write reference links as inline links, removing link reference
+    /// definitions.
+    #[arg(long)]
+    inline_links: bool,
+
+    /// The case that the labels of reference links and definitions are
+    /// written in.
+    #[arg(long, value_enum)]
+    label_case: Option<LabelCase>,
+
+    /// Keep lazy continuation lines of paragraphs in blockquotes without a
+    /// `>` marker.
+    #[arg(long)]
+    lazy_continuation: bool,
+
+    /// How soft line breaks within paragraphs and list items are written.
+    #[arg(long, value_enum)]
+    line_break_style: Option<LineBreakStyle>,
+
+    /// The line ending that output lines are written with.
+    #[arg(long, value_enum)]
+    line_ending: Option<LineEnding>,
+
+    /// Reflow paragraphs and list items to this maximum line width, or 0 to
+    /// keep their line breaks.
+    #[arg(long, value_name = "N")]
+    line_width: Option<usize>,
+
+    /// How the markers of ordered list items are aligned.
+    #[arg(long, value_enum)]
+    list_marker_alignment: Option<ListMarkerAlignment>,
+
+    /// Whether list items are separated by blank lines.
+    #[arg(long, value_enum)]
+    list_spacing: Option<ListSpacing>,
+
+    /// The maximum number of consecutive blank lines kept between top-level
+    /// blocks.
+    #[arg(long, value_name = "N")]
+    max_blank_lines: Option<usize>,
+
+    /// Fail on blocks nested deeper than this depth, or 0 to not limit it.
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Write inline links with longer destinations than this as reference
+    /// links, or 0 to not limit their length.
+    #[arg(long, value_name = "N")]
+    max_inline_link_length: Option<usize>,
+
+    /// The delimiter written after the numbers of ordered list items.
+    #[arg(long, value_enum)]
+    ordered_list_delimiter: Option<OrderedListDelimiter>,
+
+    /// How the items of ordered lists are numbered.
+    #[arg(long, value_enum)]
+    ordered_list_numbering: Option<OrderedListNumbering>,
+
+    /// Keep tables as they are in the input, unless a row has more or fewer
+    /// cells than the header.
+    #[arg(long)]
+    preserve_tables: bool,
+
+    /// Match the output of Prettier's markdown formatter.
+    #[arg(long)]
+    prettier: bool,
+
+    /// Remove unused and duplicate link reference definitions.
+    #[arg(long)]
+    prune_refdefs: bool,
+
+    /// How body rows of tables with more cells than the header are written.
+    #[arg(long, value_enum)]
+    ragged_rows: Option<RaggedRows>,
+
+    /// Where and in which order link reference definitions are written.
+    #[arg(long, value_enum)]
+    refdef_policy: Option<RefDefPolicy>,
+
+    /// The form that reference links are written in.
+    #[arg(long, value_enum)]
+    reference_links: Option<ReferenceLinks>,
+
+    /// Report images without alt text.
+    #[arg(long)]
+    require_alt_text: bool,
+
+    /// Write emphasis within words with `*` when the emphasis or strong
+    /// string uses underscores.
+    #[arg(long)]
+    smart_emphasis: bool,
+
+    /// The strong emphasis string.
+    #[arg(long, value_name = "STRING")]
+    strong: Option<String>,
+
+    /// The width of tab stops that tabs are expanded to with `--tabs expand`.
+    #[arg(long, value_name = "N")]
+    tab_width: Option<usize>,
+
+    /// The maximum width that table columns are padded to, or 0 to pad every
+    /// cell to the widest in its column.
+    #[arg(long, value_name = "N")]
+    table_cell_width: Option<usize>,
+
+    /// Wrap table cells wider than `--table-cell-width` with `<br>` tags.
+    #[arg(long)]
+    table_cell_wrap: bool,
+
+    /// The tag that line breaks within table cells are written with.
+    #[arg(long, value_enum)]
+    table_line_break: Option<TableLineBreak>,
+
+    /// Pad table columns by the width of the text of their cells as rendered,
+    /// without inline markup.
+    #[arg(long)]
+    table_rendered_width: bool,
+
+    /// Write the rows of tables as they're formatted, padding columns to the
+    /// width of their header cell or `--table-cell-width`, if wider.
+    #[arg(long)]
+    table_streaming: bool,
+
+    /// Whether the rows of tables are written with leading and trailing pipes.
+    #[arg(long, value_enum)]
+    table_style: Option<TableStyle>,
+
+    /// How the width of table cells is measured when padding their columns.
+    #[arg(long, value_enum)]
+    table_width_mode: Option<WidthMode>,
+
+    /// How tab characters in text outside of code are handled.
+    #[arg(long, value_enum)]
+    tabs: Option<Tabs>,
+
+    /// The character in the markers of checked task list items, or an empty
+    /// string to keep the character of the input.
+    #[arg(long, value_name = "CHAR")]
+    task_checked: Option<String>,
+
+    /// The delimiters that link titles are written with.
+    #[arg(long, value_enum)]
+    title_quote: Option<TitleQuote>,
+
+    /// Where trailing whitespace is trimmed from the ends of lines.
+    #[arg(long, value_enum)]
+    trailing_whitespace: Option<TrailingWhitespace>,
+
+    /// The unordered list string.
+    #[arg(long, value_name = "STRING")]
+    unordered_list: Option<String>,
+
+    /// Copy README decorations, like centered HTML headers and badges,
+    /// through verbatim.
+    #[arg(long)]
+    verbatim_badges: bool,
+
+    /// Keep the brackets of wikilinks, like `[[Page Name]]`, unescaped.
+    #[arg(long)]
+    wikilinks: bool,
+}
+
+/// A kind of top-level block accepted by `--only`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum BlockArg {
+    Paragraph,
+    Heading,
+    Blockquote,
+    Code,
+    List,
+    Table,
+    Html,
+    Rule,
+    Footnote,
+}
+
+impl BlockArg {
+    fn matches(self, kind: BlockKind) -> bool {
+        matches!(
+            (self, kind),
+            (BlockArg::Paragraph, BlockKind::Paragraph)
+                | (BlockArg::Heading, BlockKind::Heading(_))
+                | (BlockArg::Blockquote, BlockKind::BlockQuote)
+                | (BlockArg::Code, BlockKind::CodeBlock)
+                | (BlockArg::List, BlockKind::List)
+                | (BlockArg::Table, BlockKind::Table)
+                | (BlockArg::Html, BlockKind::Html)
+                | (BlockArg::Rule, BlockKind::Rule)
+                | (BlockArg::Footnote, BlockKind::FootnoteDefinition)
+        )
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = Args::parse();
+
+    let read_stdin = args.files.is_empty() && args.files_from.is_none();
+    if let Some(list) = &args.files_from {
+        match read_files_from(list) {
+            Ok(files) => args.files.extend(files),
+            Err(err) => {
+                eprintln!("cmarkfmt: {}: {err}", list.display());
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    if read_stdin && args.in_place {
+        eprintln!("cmarkfmt: --in-place requires at least one file");
+        return ExitCode::from(2);
+    }
+
+    #[cfg(feature = "watch")]
+    if args.watch {
+        args.in_place = true;
+        return match watch(&args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("cmarkfmt: {err}");
+                ExitCode::from(2)
+            }
+        };
+    }
+
+    let mut results = Vec::new();
+    if read_stdin {
+        let path = args
+            .stdin_filepath
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("<stdin>"));
+        let result = run_stdin(&args, &path);
+        results.push((path, result));
+    }
+
+    let mut failed = false;
+    let mut files = Vec::new();
+    for path in &args.files {
+        if let Err(err) = collect_files(&args, path, &mut files) {
+            eprintln!("cmarkfmt: {}: {err}", path.display());
+            failed = true;
+        }
+    }
+
+    let run = |path: PathBuf| {
+        let result = run_file(&args, &path);
+        (path, result)
+    };
+    #[cfg(feature = "rayon")]
+    results.par_extend(files.into_par_iter().map(run));
+    #[cfg(not(feature = "rayon"))]
+    results.extend(files.into_iter().map(run));
+
+    let mut unformatted = false;
+    let mut reports = Vec::new();
+    let mut stdout = io::stdout().lock();
+    for (path, result) in results {
+        match result.and_then(|outcome| {
+            stdout.write_all(outcome.stdout.as_bytes())?;
+            Ok(outcome)
+        }) {
+            Ok(outcome) => {
+                unformatted |= outcome.changed;
+                reports.extend(outcome.report);
+            }
+            Err(err) => {
+                eprintln!("cmarkfmt: {}: {err}", path.display());
+                failed = true;
+            }
+        }
+    }
+    if args.check {
+        let result = match args.output_format {
+            OutputFormat::Text => Ok(()),
+            OutputFormat::Json => writeln!(stdout, "{}", report::json(&reports)),
+            OutputFormat::Github => write!(stdout, "{}", report::github(&reports)),
+            #[cfg(feature = "sarif")]
+            OutputFormat::Sarif => writeln!(stdout, "{}", report::sarif(&reports)),
+        };
+        if let Err(err) = result {
+            eprintln!("cmarkfmt: {err}");
+            failed = true;
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if args.check && unformatted {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Formats the files in place, then watches them for changes, formatting each
+/// file again once its changes have settled.
+#[cfg(feature = "watch")]
+fn watch(args: &Args) -> notify::Result<()> {
+    use notify::{RecursiveMode, Watcher};
+
+    const DEBOUNCE: Duration = Duration::from_millis(100);
+
+    let mut files = Vec::new();
+    for path in &args.files {
+        collect_files(args, path, &mut files)?;
+    }
+    files.iter().for_each(|path| rewrite(args, path));
+
+    // Events report absolute paths, so compare and display them accordingly.
+    let cwd = std::env::current_dir()?;
+    let explicit = args
+        .files
+        .iter()
+        .filter_map(|path| fs::canonicalize(path).ok())
+        .collect::<BTreeSet<_>>();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    for path in &args.files {
+        watcher.watch(path, RecursiveMode::Recursive)?;
+    }
+    eprintln!("cmarkfmt: watching for changes");
+
+    while let Ok(event) = rx.recv() {
+        let mut changed = BTreeSet::new();
+        let mut event = Some(event);
+        while let Some(result) = event {
+            changed.extend(result?.paths);
+            event = rx.recv_timeout(DEBOUNCE).ok();
+        }
+        for path in changed {
+            if path.is_file()
+                && (explicit.contains(&path) || is_candidate(args, &path)?)
+                && !is_ignored(&ancestor_ignores(&path)?, &path, false)
+            {
+                rewrite(args, path.strip_prefix(&cwd).unwrap_or(&path));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Formats the file at `path` in place, printing a summary if it changed.
+#[cfg(feature = "watch")]
+fn rewrite(args: &Args, path: &Path) {
+    match run_file(args, path) {
+        Ok(outcome) if outcome.changed => println!("formatted {}", path.display()),
+        Ok(_) => {}
+        Err(err) => eprintln!("cmarkfmt: {}: {err}", path.display()),
+    }
+}
+
+/// Appends `path` to `files`, or the files to format within it if it's a
+/// directory. Files found while searching a directory are only included if
+/// they look like markdown, or source code when formatting comments. Paths
+/// excluded by a `.cmarkfmtignore` file are skipped.
+fn collect_files(args: &Args, path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let abs = fs::canonicalize(path)?;
+    let mut ignores = ancestor_ignores(&abs)?;
+    let is_dir = abs.is_dir();
+    if is_ignored(&ignores, &abs, is_dir) {
+        return Ok(());
+    }
+    if !is_dir {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    walk(args, path, &abs, &mut ignores, files)
+}
+
+/// Recursively appends the files to format within the directory at `path`,
+/// whose absolute path is `abs`.
+fn walk(
+    args: &Args,
+    path: &Path,
+    abs: &Path,
+    ignores: &mut Vec<(PathBuf, Ignore)>,
+    files: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let len = ignores.len();
+    load_ignore(abs, ignores)?;
+
+    let mut entries = fs::read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let abs = abs.join(&name);
+        let is_dir = path.is_dir();
+        if is_ignored(ignores, &abs, is_dir) {
+            continue;
+        }
+        if is_dir {
+            walk(args, &path, &abs, ignores, files)?;
+        } else if is_candidate(args, &path)? {
+            files.push(path);
+        }
+    }
+
+    ignores.truncate(len);
+    Ok(())
+}
+
+/// Loads the ignore files in the directories above the absolute path.
+fn ancestor_ignores(abs: &Path) -> io::Result<Vec<(PathBuf, Ignore)>> {
+    let mut ignores = Vec::new();
+    let mut dirs = abs.ancestors().skip(1).collect::<Vec<_>>();
+    dirs.reverse();
+    for dir in dirs {
+        load_ignore(dir, &mut ignores)?;
+    }
+    Ok(ignores)
+}
+
+/// Loads the ignore file in the directory, if it has one.
+fn load_ignore(dir: &Path, ignores: &mut Vec<(PathBuf, Ignore)>) -> io::Result<()> {
+    match fs::read_to_string(dir.join(Ignore::FILE_NAME)) {
+        Ok(contents) => ignores.push((dir.to_path_buf(), Ignore::new(&contents))),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    Ok(())
+}
+
+/// Returns true if any of the ignore files exclude the absolute path.
+fn is_ignored(ignores: &[(PathBuf, Ignore)], abs: &Path, is_dir: bool) -> bool {
+    ignores.iter().any(|(root, ignore)| {
+        abs.strip_prefix(root)
+            .is_ok_and(|rel| ignore.is_ignored(rel, is_dir))
+    })
+}
+
+/// Returns true if the file at `path` should be formatted when found in a
+/// directory: if it's source code with markdown comments when formatting
+/// comments, and otherwise if its name matches the `presets` of its
+/// configuration.
+fn is_candidate(args: &Args, path: &Path) -> io::Result<bool> {
+    if args.comments {
+        return Ok(path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(cmarkfmt::comment_prefixes)
+            .is_some());
+    }
+    let abs = std::env::current_dir()?.join(path);
+    let config = Config::find(&abs, args.config.as_deref()).map_err(io::Error::other)?;
+    Ok(config.preset_map().resolve(path).is_some())
+}
+
+/// Returns the formatter settings for the file at `path`, with the
+/// command-line arguments taking precedence over the configuration sources
+/// described by `Config::resolve`.
+fn config(args: &Args, path: &Path) -> io::Result<FormatterConfig> {
+    let abs = std::env::current_dir()?.join(path);
+    let overrides = FormatterConfig {
+        preset: args.preset,
+        alerts: args.alerts.then_some(true),
+        align_numeric_columns: args.align_numeric_columns.then_some(true),
+        bare_urls: args.bare_urls,
+        blank_line_after_block_items: args.no_blank_line_after_block_items.then_some(false),
+        blank_lines_after_headings: args.blank_lines_after_headings,
+        blank_lines_before_headings: args.blank_lines_before_headings,
+        blockquote: args.blockquote.clone(),
+        blockquote_join: args.blockquote_join,
+        blockquote_nesting: args.blockquote_nesting,
+        blockquote_spacing: args.blockquote_spacing,
+        bold_headings: args.bold_headings,
+        bold_italic: args.bold_italic,
+        bullet_style: args.bullet_style,
+        byte_order_mark: args.byte_order_mark,
+        code_fence: args.code_fence,
+        continuation_indent: args.continuation_indent,
+        convert_html_tables: args.convert_html_tables.then_some(true),
+        duplicate_headings: args.duplicate_headings,
+        email_links: args.email_links,
+        emphasis: args.emphasis.clone(),
+        empty_links: args.empty_links,
+        final_newline: args.final_newline,
+        flavor: args.flavor,
+        footnote_placement: args.footnote_placement,
+        footnote_renumbering: args.footnote_renumbering.then_some(true),
+        hard_break: args.hard_break,
+        heading_attribute_order: args.heading_attribute_order,
+        heading_attributes: args.heading_attributes,
+        heading_shift: args.heading_shift,
+        heading_style: args.heading_style,
+        indent_width: args.indent_width,
+        info_string: args.info_string,
+        inline_links: args.inline_links.then_some(true),
+        label_case: args.label_case,
+        lazy_continuation: args.lazy_continuation.then_some(true),
+        line_break_style: args.line_break_style,
+        line_ending: args.line_ending,
+        line_width: args.line_width,
+        list_marker_alignment: args.list_marker_alignment,
+        list_spacing: args.list_spacing,
+        max_blank_lines: args.max_blank_lines,
+        max_depth: args.max_depth,
+        max_inline_link_length: args.max_inline_link_length,
+        ordered_list_delimiter: args.ordered_list_delimiter,
+        ordered_list_numbering: args.ordered_list_numbering,
+        preserve_tables: args.preserve_tables.then_some(true),
+        prettier: args.prettier.then_some(true),
+        prune_refdefs: args.prune_refdefs.then_some(true),
+        ragged_rows: args.ragged_rows,
+        refdef_policy: args.refdef_policy,
+        reference_links: args.reference_links,
+        require_alt_text: args.require_alt_text.then_some(true),
+        smart_emphasis: args.smart_emphasis.then_some(true),
+        strong: args.strong.clone(),
+        tab_width: args.tab_width,
+        table_cell_width: args.table_cell_width,
+        table_cell_wrap: args.table_cell_wrap.then_some(true),
+        table_line_break: args.table_line_break,
+        table_rendered_width: args.table_rendered_width.then_some(true),
+        table_streaming: args.table_streaming.then_some(true),
+        table_style: args.table_style,
+        table_width_mode: args.table_width_mode,
+        tabs: args.tabs,
+        task_checked: args.task_checked.clone(),
+        title_quote: args.title_quote,
+        trailing_whitespace: args.trailing_whitespace,
+        unordered_list: args.unordered_list.clone(),
+        verbatim_badges: args.verbatim_badges.then_some(true),
+        wikilinks: args.wikilinks.then_some(true),
+    };
+    Config::resolve(&abs, args.config.as_deref(), &overrides).map_err(io::Error::other)
+}
+
+/// Returns the NUL-separated paths in the file at `list`, or stdin if `-`.
+fn read_files_from(list: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut buf = Vec::new();
+    if list == Path::new("-") {
+        io::stdin().read_to_end(&mut buf)?;
+    } else {
+        buf = fs::read(list)?;
+    }
+    let paths =
+        String::from_utf8(buf).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(paths
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Formats stdin, named by `path`.
+fn run_stdin(args: &Args, path: &Path) -> io::Result<Outcome> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    process(args, path, input)
+}
+
+/// Formats the file at `path`.
+fn run_file(args: &Args, path: &Path) -> io::Result<Outcome> {
+    let input = fs::read_to_string(path)?;
+    process(args, path, input)
+}
+
+/// The result of formatting a single input.
+struct Outcome {
+    /// Whether the formatted output differs from the input.
+    changed: bool,
+    /// The text to write to stdout for the input.
+    stdout: String,
+    /// The structured results of `--check`, if requested.
+    report: Option<FileReport>,
+}
+
+/// Formats the input read from `path`, rewriting the file if `--in-place` is
+/// provided. Nothing is written to stdout directly, so that files can be
+/// formatted concurrently.
+fn process(args: &Args, path: &Path, input: String) -> io::Result<Outcome> {
+    let config = config(args, path)?;
+    let only = |kind| args.only.iter().any(|v| v.matches(kind));
+    let lines = match &args.git_diff {
+        Some(rev) => Some(git::changed_lines(rev, path)?),
+        None => None,
+    };
+    let mut cmfmt = Formatter::from(&config);
+    if !args.only.is_empty() {
+        cmfmt = cmfmt.with_block_filter(Some(&only));
+    }
+    if let Some(lines) = &lines {
+        cmfmt = cmfmt.with_lines(Some(lines));
+    }
+    if args.check_links {
+        cmfmt = cmfmt.with_link_base(path.parent());
+    }
+
+    let output = format(args, &cmfmt, path, &input)?;
+    let mut outcome = Outcome {
+        changed: input != output,
+        stdout: String::new(),
+        report: None,
+    };
+    if args.check {
+        match args.output_format {
+            OutputFormat::Text => outcome.stdout = diff(path, &input, &output),
+            _ => {
+                let diagnostics = if args.comments {
+                    Vec::new()
+                } else {
+                    cmfmt.lint(&input)
+                };
+                outcome.report = Some(FileReport::new(path, &input, &output, diagnostics));
+            }
+        }
+    } else if args.in_place {
+        if outcome.changed {
+            fs::write(path, output)?;
+        }
+    } else {
+        outcome.stdout = output;
+    }
+    Ok(outcome)
+}
+
+/// Returns a unified diff from the input to the formatted output, or an empty
+/// string if they're equal.
+fn diff(path: &Path, input: &str, output: &str) -> String {
+    if input == output {
+        return String::new();
+    }
+    let name = path.display().to_string();
+    TextDiff::from_lines(input, output)
+        .unified_diff()
+        .header(&name, &format!("{name} (formatted)"))
+        .to_string()
+}
+
+/// Formats the input read from `path`, either as markdown or as source code
+/// containing markdown comments.
+fn format(args: &Args, cmfmt: &Formatter, path: &Path, input: &str) -> io::Result<String> {
+    if !args.comments {
+        return cmfmt
+            .try_format_cmark(input)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+    }
+
+    if !args.comment_prefix.is_empty() {
+        let prefixes = args
+            .comment_prefix
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        return Ok(cmfmt.format_comments(input, &prefixes));
+    }
+    let prefixes = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(cmarkfmt::comment_prefixes)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unknown comment syntax, use --comment-prefix",
+            )
+        })?;
+    Ok(cmfmt.format_comments(input, prefixes))
+}
@@ -0,0 +1,208 @@
+//! Machine-readable reports of the results of `--check`.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use cmarkfmt::Diagnostic;
+use serde_json::{json, Value};
+use similar::{DiffTag, TextDiff};
+
+/// How the results of `--check` are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// A unified diff of each unformatted input.
+    Text,
+    /// A JSON report of the changes and diagnostics for every input.
+    Json,
+    /// GitHub Actions workflow commands annotating the changes and
+    /// diagnostics inline.
+    Github,
+    /// A SARIF log of the changes and diagnostics for every input.
+    #[cfg(feature = "sarif")]
+    Sarif,
+}
+
+/// The results of checking a single input.
+pub(crate) struct FileReport {
+    pub(crate) path: PathBuf,
+    pub(crate) changes: Vec<Change>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+/// A run of lines that formatting would replace.
+pub(crate) struct Change {
+    /// The byte range of the replaced lines in the input.
+    pub(crate) range: Range<usize>,
+    /// The 1-based line of the start of `range`.
+    pub(crate) line: usize,
+    /// The 1-based line of the end of `range`, inclusive. Equal to
+    /// `line - 1` when lines are only inserted.
+    pub(crate) end_line: usize,
+    /// The formatted lines replacing `range`.
+    pub(crate) replacement: String,
+}
+
+impl Change {
+    /// Returns the 1-based line to report the change at. For inserted lines,
+    /// this is the line they follow.
+    fn first_line(&self) -> usize {
+        if self.end_line < self.line {
+            (self.line - 1).max(1)
+        } else {
+            self.line
+        }
+    }
+}
+
+impl FileReport {
+    pub(crate) fn new(
+        path: &Path,
+        input: &str,
+        output: &str,
+        diagnostics: Vec<Diagnostic>,
+    ) -> Self {
+        FileReport {
+            path: path.to_path_buf(),
+            changes: changes(input, output),
+            diagnostics,
+        }
+    }
+}
+
+/// Returns the line-based changes from the input to the formatted output.
+fn changes(input: &str, output: &str) -> Vec<Change> {
+    let diff = TextDiff::from_lines(input, output);
+    let mut offsets = Vec::with_capacity(diff.old_slices().len() + 1);
+    let mut offset = 0;
+    offsets.push(offset);
+    for line in diff.old_slices() {
+        offset += line.len();
+        offsets.push(offset);
+    }
+
+    diff.ops()
+        .iter()
+        .filter(|op| op.tag() != DiffTag::Equal)
+        .map(|op| {
+            let old = op.old_range();
+            Change {
+                range: offsets[old.start]..offsets[old.end],
+                line: old.start + 1,
+                end_line: old.end,
+                replacement: diff.new_slices()[op.new_range()].concat(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the JSON report of the results for every input.
+pub(crate) fn json(reports: &[FileReport]) -> Value {
+    let files = reports
+        .iter()
+        .map(|report| {
+            let changes = report
+                .changes
+                .iter()
+                .map(|change| {
+                    json!({
+                        "start": change.range.start,
+                        "end": change.range.end,
+                        "line": change.line,
+                        "end_line": change.end_line,
+                        "replacement": change.replacement,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let diagnostics = report
+                .diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    json!({
+                        "rule": diagnostic.rule.id(),
+                        "message": diagnostic.message,
+                        "start": diagnostic.range.start,
+                        "end": diagnostic.range.end,
+                        "line": diagnostic.line,
+                        "column": diagnostic.column,
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "path": report.path.display().to_string(),
+                "formatted": report.changes.is_empty(),
+                "changes": changes,
+                "diagnostics": diagnostics,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({ "files": files })
+}
+
+/// Returns the GitHub Actions `::error` commands annotating the results for
+/// every input.
+pub(crate) fn github(reports: &[FileReport]) -> String {
+    let mut out = String::new();
+    for report in reports {
+        let file = escape_property(&report.path.display().to_string());
+        for change in &report.changes {
+            let line = change.first_line();
+            let end_line = change.end_line.max(line);
+            out.push_str(&format!(
+                "::error file={file},line={line},endLine={end_line},title=cmarkfmt::File is not formatted\n",
+            ));
+        }
+        for diagnostic in &report.diagnostics {
+            out.push_str(&format!(
+                "::error file={file},line={},col={},title={}::{}\n",
+                diagnostic.line,
+                diagnostic.column,
+                escape_property(diagnostic.rule.id()),
+                escape_data(&diagnostic.message),
+            ));
+        }
+    }
+    out
+}
+
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Returns the SARIF log of the results for every input, with each change
+/// reported as an `unformatted` diagnostic.
+#[cfg(feature = "sarif")]
+pub(crate) fn sarif(reports: &[FileReport]) -> String {
+    use cmarkfmt::Rule;
+
+    let files = reports
+        .iter()
+        .map(|report| {
+            let uri = report.path.to_string_lossy().replace('\\', "/");
+            let mut diagnostics = report
+                .changes
+                .iter()
+                .map(|change| Diagnostic {
+                    rule: Rule::Unformatted,
+                    message: "File is not formatted".to_string(),
+                    range: change.range.clone(),
+                    line: change.first_line(),
+                    column: 1,
+                })
+                .collect::<Vec<_>>();
+            diagnostics.extend(report.diagnostics.iter().cloned());
+            (uri, diagnostics)
+        })
+        .collect::<Vec<_>>();
+    cmarkfmt::sarif_log(
+        files
+            .iter()
+            .map(|(uri, diagnostics)| (uri.as_str(), &diagnostics[..])),
+    )
+}
@@ -0,0 +1,129 @@
+//! Segmentation of markdown into its top-level blocks.
+
+use std::iter::Peekable;
+use std::ops::Range;
+
+use pulldown_cmark::{Event, HeadingLevel, OffsetIter, Parser, Tag};
+
+use crate::parser_options;
+
+/// The kind of a top-level block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BlockKind {
+    /// A paragraph.
+    Paragraph,
+    /// An ATX or setext heading.
+    Heading(HeadingLevel),
+    /// A blockquote.
+    BlockQuote,
+    /// A fenced or indented code block.
+    CodeBlock,
+    /// An ordered or unordered list.
+    List,
+    /// A table.
+    Table,
+    /// An HTML block.
+    Html,
+    /// A thematic break.
+    Rule,
+    /// A footnote definition.
+    FootnoteDefinition,
+}
+
+/// A top-level block, along with its location in the input. The range covers
+/// whole lines, including the final line ending but no trailing blank lines.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// The kind of the block.
+    pub kind: BlockKind,
+    /// The byte range of the block in the input.
+    pub range: Range<usize>,
+}
+
+/// Returns an iterator over the top-level blocks of the markdown input, using
+/// the same parsing options as the `Formatter`. Link reference definitions are
+/// not blocks, and are skipped.
+///
+/// Example:
+///
+/// ```
+/// use cmarkfmt::BlockKind;
+///
+/// let input = "# Title\n\nSome text.\n";
+/// let kinds = cmarkfmt::blocks(input).map(|b| b.kind).collect::<Vec<_>>();
+/// assert_eq!(
+///     kinds,
+///     vec![BlockKind::Heading(cmarkfmt::HeadingLevel::H1), BlockKind::Paragraph]
+/// );
+/// ```
+pub fn blocks(input: &str) -> Blocks<'_> {
+    Blocks {
+        input,
+        events: Parser::new_ext(input, parser_options())
+            .into_offset_iter()
+            .peekable(),
+    }
+}
+
+/// An iterator over the top-level blocks of markdown. See `blocks`.
+pub struct Blocks<'a> {
+    input: &'a str,
+    events: Peekable<OffsetIter<'a, 'a>>,
+}
+
+impl Iterator for Blocks<'_> {
+    type Item = BlockInfo;
+
+    fn next(&mut self) -> Option<BlockInfo> {
+        loop {
+            let (event, mut range) = self.events.next()?;
+            let kind = match event {
+                Event::Start(tag) => match tag {
+                    Tag::Paragraph => BlockKind::Paragraph,
+                    Tag::Heading(level, ..) => BlockKind::Heading(level),
+                    Tag::BlockQuote => BlockKind::BlockQuote,
+                    Tag::CodeBlock(_) => BlockKind::CodeBlock,
+                    Tag::List(_) => BlockKind::List,
+                    Tag::Table(_) => BlockKind::Table,
+                    Tag::FootnoteDefinition(_) => BlockKind::FootnoteDefinition,
+                    _ => continue,
+                },
+                Event::Html(_) => {
+                    // Each line of an HTML block is a separate event.
+                    while let Some((_, next)) = self.events.next_if(|(event, next)| {
+                        matches!(event, Event::Html(_)) && next.start == range.end
+                    }) {
+                        range.end = next.end;
+                    }
+                    BlockKind::Html
+                }
+                Event::Rule => BlockKind::Rule,
+                _ => continue,
+            };
+
+            // Skip the events nested within the block.
+            while self
+                .events
+                .next_if(|(_, next)| next.start < range.end)
+                .is_some()
+            {}
+            return Some(BlockInfo {
+                kind,
+                range: line_range(self.input, range),
+            });
+        }
+    }
+}
+
+// Adjusts a block range reported by the parser to end just after the line
+// ending of its last non-blank line.
+pub(crate) fn line_range(input: &str, range: Range<usize>) -> Range<usize> {
+    let content = input[range.clone()].trim_end();
+    let end = range.start + content.len();
+    let end = match input[end..].find('\n') {
+        Some(i) => end + i + 1,
+        None => input.len(),
+    };
+    range.start..end
+}
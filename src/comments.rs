@@ -0,0 +1,161 @@
+//! Formatting of markdown embedded in source-code line comments.
+
+use std::fmt;
+
+use crate::Formatter;
+
+/// Returns the default comment prefixes holding markdown for files with the
+/// provided extension, or `None` if the extension isn't recognized.
+///
+/// Only doc comments are assumed to hold markdown, as ordinary comments are
+/// often plain text or commented-out code. Other prefixes, like `//` or `#`,
+/// must be provided explicitly.
+///
+/// Example:
+///
+/// ```
+/// assert_eq!(cmarkfmt::comment_prefixes("rs"), Some(&["///", "//!"][..]));
+/// assert_eq!(cmarkfmt::comment_prefixes("py"), Some(&["##"][..]));
+/// assert_eq!(cmarkfmt::comment_prefixes("go"), None);
+/// assert_eq!(cmarkfmt::comment_prefixes("md"), None);
+/// ```
+pub fn comment_prefixes(extension: &str) -> Option<&'static [&'static str]> {
+    match extension {
+        "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "swift" => Some(&["///", "//!"]),
+        "py" | "rb" | "sh" => Some(&["##"]),
+        _ => None,
+    }
+}
+
+impl<'a> Formatter<'a> {
+    /// Formats the markdown held in line comments of source code, returning
+    /// the source with each comment block rewritten.
+    ///
+    /// A comment block is a run of consecutive lines that start with the same
+    /// prefix at the same indentation. A prefix only matches when followed by
+    /// whitespace or the end of the line, so `//` doesn't match `///`. The
+    /// indentation and prefix of each block are preserved.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "fn main() {\n    // Some *markdown*\n    // text.\n}\n";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default();
+    /// let output = cmfmt.format_comments(input, &["//"]);
+    /// assert_eq!(output, "fn main() {\n    // Some _markdown_\n    // text.\n}\n");
+    /// ```
+    pub fn format_comments(&self, source: &str, prefixes: &[&str]) -> String {
+        let mut out = String::with_capacity(source.len() + 128);
+        self.format_comments_writer(source, prefixes, &mut out)
+            .unwrap();
+        out
+    }
+
+    /// Formats the markdown held in line comments of source code, writing the
+    /// result to the provided Writer. See `format_comments`.
+    pub fn format_comments_writer<W: fmt::Write>(
+        &self,
+        source: &str,
+        prefixes: &[&str],
+        mut w: W,
+    ) -> fmt::Result {
+        let mut block: Option<CommentBlock> = None;
+        for line in source.split_inclusive('\n') {
+            let comment = comment_line(line, prefixes);
+            if let Some(b) = block.as_mut() {
+                if comment.is_some_and(|(indent, prefix)| b.indent == indent && b.prefix == prefix)
+                {
+                    b.lines.push(line);
+                    continue;
+                }
+                self.write_comment_block(b, &mut w)?;
+                block = None;
+            }
+            match comment {
+                Some((indent, prefix)) => {
+                    block = Some(CommentBlock {
+                        indent,
+                        prefix,
+                        lines: vec![line],
+                    })
+                }
+                None => w.write_str(line)?,
+            }
+        }
+        if let Some(b) = block {
+            self.write_comment_block(&b, &mut w)?;
+        }
+        Ok(())
+    }
+
+    fn write_comment_block<W: fmt::Write>(&self, block: &CommentBlock, w: &mut W) -> fmt::Result {
+        let prefix_len = block.indent.len() + block.prefix.len();
+        let bodies = block
+            .lines
+            .iter()
+            .map(|line| line[prefix_len..].trim_end_matches(['\r', '\n']))
+            .collect::<Vec<_>>();
+
+        // Strip the single space conventionally following the prefix.
+        let spaced = bodies.iter().all(|v| v.is_empty() || v.starts_with(' '));
+        let mut input = String::new();
+        for body in &bodies {
+            input.push_str(if spaced && !body.is_empty() {
+                &body[1..]
+            } else {
+                body
+            });
+            input.push('\n');
+        }
+
+        let output = self.format_cmark(&input);
+        if output.trim().is_empty() {
+            return block.lines.iter().try_for_each(|line| w.write_str(line));
+        }
+
+        let newline = if block.lines[0].ends_with("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let last_newline = block.lines[block.lines.len() - 1].ends_with('\n');
+        let mut lines = output.lines().peekable();
+        while let Some(line) = lines.next() {
+            w.write_str(block.indent)?;
+            w.write_str(block.prefix)?;
+            if !line.is_empty() {
+                w.write_char(' ')?;
+                w.write_str(line)?;
+            }
+            if lines.peek().is_some() || last_newline {
+                w.write_str(newline)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct CommentBlock<'s> {
+    indent: &'s str,
+    prefix: &'s str,
+    lines: Vec<&'s str>,
+}
+
+/// Splits a comment line into its indentation and the longest matching prefix.
+fn comment_line<'s>(line: &'s str, prefixes: &[&str]) -> Option<(&'s str, &'s str)> {
+    let trimmed = line.trim_start_matches([' ', '\t']);
+    let indent = &line[..line.len() - trimmed.len()];
+    prefixes
+        .iter()
+        .filter_map(|prefix| {
+            let body = trimmed.strip_prefix(prefix)?;
+            match body.chars().next() {
+                None => {}
+                Some(c) if c.is_whitespace() => {}
+                Some(_) => return None,
+            }
+            Some((indent, &trimmed[..prefix.len()]))
+        })
+        .max_by_key(|(_, prefix)| prefix.len())
+}
@@ -0,0 +1,513 @@
+//! Plain-data `Formatter` settings, for embedding in configuration files.
+
+#[cfg(feature = "config")]
+mod file;
+
+#[cfg(feature = "config")]
+pub use file::{Config, ConfigError, PresetPattern};
+
+use crate::{
+    BareUrls, BlockquoteJoin, BlockquoteNesting, BoldItalic, BulletStyle, ByteOrderMark,
+    ContinuationIndent, DuplicateHeadings, EmailLinks, EmptyLinks, FenceChar, FinalNewline, Flavor,
+    FootnotePlacement, Formatter, HardBreak, HeadingAttributeOrder, HeadingAttributes,
+    HeadingLevel, HeadingStyle, InfoString, LabelCase, LineBreakStyle, LineEnding,
+    ListMarkerAlignment, ListSpacing, OrderedListDelimiter, OrderedListNumbering, Preset,
+    RaggedRows, RefDefPolicy, ReferenceLinks, TableLineBreak, TableStyle, Tabs, TitleQuote,
+    TrailingWhitespace, WidthMode,
+};
+
+/// The settings of a `Formatter` as plain data, so they can be embedded in
+/// the configuration of other tools. With the `serde` feature, it implements
+/// `Serialize` and `Deserialize`, with keys in `snake_case` and enum values in
+/// `kebab-case`.
+///
+/// Every field is optional, leaving the setting of the `Preset` in place when
+/// unset.
+///
+/// Example:
+///
+/// ```
+/// use cmarkfmt::{Formatter, FormatterConfig};
+///
+/// let config = FormatterConfig {
+///     emphasis: Some("*".to_string()),
+///     ..FormatterConfig::default()
+/// };
+/// let cmfmt = Formatter::from(&config);
+/// assert_eq!(cmfmt.format_cmark("_text_"), "*text*\n");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct FormatterConfig {
+    /// The preset to start from.
+    pub preset: Option<Preset>,
+    /// See `Formatter::with_alerts`.
+    pub alerts: Option<bool>,
+    /// See `Formatter::with_align_numeric_columns`.
+    pub align_numeric_columns: Option<bool>,
+    /// See `Formatter::with_bare_urls`.
+    pub bare_urls: Option<BareUrls>,
+    /// See `Formatter::with_blank_line_after_block_items`.
+    pub blank_line_after_block_items: Option<bool>,
+    /// See `Formatter::with_blank_lines_after_headings`.
+    pub blank_lines_after_headings: Option<usize>,
+    /// See `Formatter::with_blank_lines_before_headings`.
+    pub blank_lines_before_headings: Option<usize>,
+    /// See `Formatter::with_blockquote`.
+    pub blockquote: Option<String>,
+    /// See `Formatter::with_blockquote_join`.
+    pub blockquote_join: Option<BlockquoteJoin>,
+    /// See `Formatter::with_blockquote_nesting`.
+    pub blockquote_nesting: Option<BlockquoteNesting>,
+    /// See `Formatter::with_blockquote_spacing`.
+    pub blockquote_spacing: Option<usize>,
+    /// The level, from 1 to 6, passed to `Formatter::with_bold_headings`.
+    pub bold_headings: Option<u8>,
+    /// See `Formatter::with_bold_italic`.
+    pub bold_italic: Option<BoldItalic>,
+    /// See `Formatter::with_bullet_style`.
+    pub bullet_style: Option<BulletStyle>,
+    /// See `Formatter::with_byte_order_mark`.
+    pub byte_order_mark: Option<ByteOrderMark>,
+    /// See `Formatter::with_code_fence`.
+    pub code_fence: Option<FenceChar>,
+    /// See `Formatter::with_continuation_indent`.
+    pub continuation_indent: Option<ContinuationIndent>,
+    /// See `Formatter::with_convert_html_tables`.
+    pub convert_html_tables: Option<bool>,
+    /// See `Formatter::with_duplicate_headings`.
+    pub duplicate_headings: Option<DuplicateHeadings>,
+    /// See `Formatter::with_email_links`.
+    pub email_links: Option<EmailLinks>,
+    /// See `Formatter::with_emphasis`.
+    pub emphasis: Option<String>,
+    /// See `Formatter::with_empty_links`.
+    pub empty_links: Option<EmptyLinks>,
+    /// See `Formatter::with_final_newline`.
+    pub final_newline: Option<FinalNewline>,
+    /// See `Formatter::with_flavor`.
+    pub flavor: Option<Flavor>,
+    /// See `Formatter::with_footnote_placement`.
+    pub footnote_placement: Option<FootnotePlacement>,
+    /// See `Formatter::with_footnote_renumbering`.
+    pub footnote_renumbering: Option<bool>,
+    /// See `Formatter::with_hard_break`.
+    pub hard_break: Option<HardBreak>,
+    /// See `Formatter::with_heading_attribute_order`.
+    pub heading_attribute_order: Option<HeadingAttributeOrder>,
+    /// See `Formatter::with_heading_attributes`.
+    pub heading_attributes: Option<HeadingAttributes>,
+    /// See `Formatter::with_heading_shift`.
+    pub heading_shift: Option<i8>,
+    /// See `Formatter::with_heading_style`.
+    pub heading_style: Option<HeadingStyle>,
+    /// See `Formatter::with_indent_width`, where 0 indents by the width of the
+    /// marker.
+    pub indent_width: Option<usize>,
+    /// See `Formatter::with_info_string`.
+    pub info_string: Option<InfoString>,
+    /// See `Formatter::with_inline_links`.
+    pub inline_links: Option<bool>,
+    /// See `Formatter::with_label_case`.
+    pub label_case: Option<LabelCase>,
+    /// See `Formatter::with_lazy_continuation`.
+    pub lazy_continuation: Option<bool>,
+    /// See `Formatter::with_line_break_style`.
+    pub line_break_style: Option<LineBreakStyle>,
+    /// See `Formatter::with_line_ending`.
+    pub line_ending: Option<LineEnding>,
+    /// See `Formatter::with_line_width`, where 0 keeps the line breaks of the
+    /// input.
+    pub line_width: Option<usize>,
+    /// See `Formatter::with_list_marker_alignment`.
+    pub list_marker_alignment: Option<ListMarkerAlignment>,
+    /// See `Formatter::with_list_spacing`.
+    pub list_spacing: Option<ListSpacing>,
+    /// See `Formatter::with_max_blank_lines`.
+    pub max_blank_lines: Option<usize>,
+    /// See `Formatter::with_max_depth`, where 0 doesn't limit the depth.
+    pub max_depth: Option<usize>,
+    /// See `Formatter::with_max_inline_link_length`, where 0 doesn't limit the
+    /// length.
+    pub max_inline_link_length: Option<usize>,
+    /// See `Formatter::with_ordered_list_delimiter`.
+    pub ordered_list_delimiter: Option<OrderedListDelimiter>,
+    /// See `Formatter::with_ordered_list_numbering`.
+    pub ordered_list_numbering: Option<OrderedListNumbering>,
+    /// See `Formatter::with_preserve_tables`.
+    pub preserve_tables: Option<bool>,
+    /// See `Formatter::with_prettier`.
+    pub prettier: Option<bool>,
+    /// See `Formatter::with_prune_refdefs`.
+    pub prune_refdefs: Option<bool>,
+    /// See `Formatter::with_ragged_rows`.
+    pub ragged_rows: Option<RaggedRows>,
+    /// See `Formatter::with_refdef_policy`.
+    pub refdef_policy: Option<RefDefPolicy>,
+    /// See `Formatter::with_reference_links`.
+    pub reference_links: Option<ReferenceLinks>,
+    /// See `Formatter::with_require_alt_text`.
+    pub require_alt_text: Option<bool>,
+    /// See `Formatter::with_smart_emphasis`.
+    pub smart_emphasis: Option<bool>,
+    /// See `Formatter::with_strong`.
+    pub strong: Option<String>,
+    /// See `Formatter::with_tab_width`.
+    pub tab_width: Option<usize>,
+    /// See `Formatter::with_table_cell_width`, where 0 doesn't limit the
+    /// width.
+    pub table_cell_width: Option<usize>,
+    /// See `Formatter::with_table_cell_wrap`.
+    pub table_cell_wrap: Option<bool>,
+    /// See `Formatter::with_table_line_break`.
+    pub table_line_break: Option<TableLineBreak>,
+    /// See `Formatter::with_table_rendered_width`.
+    pub table_rendered_width: Option<bool>,
+    /// See `Formatter::with_table_streaming`.
+    pub table_streaming: Option<bool>,
+    /// See `Formatter::with_table_style`.
+    pub table_style: Option<TableStyle>,
+    /// See `Formatter::with_table_width_mode`.
+    pub table_width_mode: Option<WidthMode>,
+    /// See `Formatter::with_tabs`.
+    pub tabs: Option<Tabs>,
+    /// See `Formatter::with_task_checked`, where an empty string keeps the
+    /// character of the input.
+    pub task_checked: Option<String>,
+    /// See `Formatter::with_title_quote`.
+    pub title_quote: Option<TitleQuote>,
+    /// See `Formatter::with_trailing_whitespace`.
+    pub trailing_whitespace: Option<TrailingWhitespace>,
+    /// See `Formatter::with_unordered_list`.
+    pub unordered_list: Option<String>,
+    /// See `Formatter::with_verbatim_badges`.
+    pub verbatim_badges: Option<bool>,
+    /// See `Formatter::with_wikilinks`.
+    pub wikilinks: Option<bool>,
+}
+
+impl FormatterConfig {
+    /// Overrides the settings with every setting of `other` that is set.
+    pub fn merge(&mut self, other: &FormatterConfig) {
+        fn set<T: Clone>(setting: &mut Option<T>, other: &Option<T>) {
+            if other.is_some() {
+                setting.clone_from(other);
+            }
+        }
+        set(&mut self.preset, &other.preset);
+        set(&mut self.alerts, &other.alerts);
+        set(
+            &mut self.align_numeric_columns,
+            &other.align_numeric_columns,
+        );
+        set(&mut self.bare_urls, &other.bare_urls);
+        set(
+            &mut self.blank_line_after_block_items,
+            &other.blank_line_after_block_items,
+        );
+        set(
+            &mut self.blank_lines_after_headings,
+            &other.blank_lines_after_headings,
+        );
+        set(
+            &mut self.blank_lines_before_headings,
+            &other.blank_lines_before_headings,
+        );
+        set(&mut self.blockquote, &other.blockquote);
+        set(&mut self.blockquote_join, &other.blockquote_join);
+        set(&mut self.blockquote_nesting, &other.blockquote_nesting);
+        set(&mut self.blockquote_spacing, &other.blockquote_spacing);
+        set(&mut self.bold_headings, &other.bold_headings);
+        set(&mut self.bold_italic, &other.bold_italic);
+        set(&mut self.bullet_style, &other.bullet_style);
+        set(&mut self.byte_order_mark, &other.byte_order_mark);
+        set(&mut self.code_fence, &other.code_fence);
+        set(&mut self.continuation_indent, &other.continuation_indent);
+        set(&mut self.convert_html_tables, &other.convert_html_tables);
+        set(&mut self.duplicate_headings, &other.duplicate_headings);
+        set(&mut self.email_links, &other.email_links);
+        set(&mut self.emphasis, &other.emphasis);
+        set(&mut self.empty_links, &other.empty_links);
+        set(&mut self.final_newline, &other.final_newline);
+        set(&mut self.flavor, &other.flavor);
+        set(&mut self.footnote_placement, &other.footnote_placement);
+        set(&mut self.footnote_renumbering, &other.footnote_renumbering);
+        set(&mut self.hard_break, &other.hard_break);
+        set(
+            &mut self.heading_attribute_order,
+            &other.heading_attribute_order,
+        );
+        set(&mut self.heading_attributes, &other.heading_attributes);
+        set(&mut self.heading_shift, &other.heading_shift);
+        set(&mut self.heading_style, &other.heading_style);
+        set(&mut self.indent_width, &other.indent_width);
+        set(&mut self.info_string, &other.info_string);
+        set(&mut self.inline_links, &other.inline_links);
+        set(&mut self.label_case, &other.label_case);
+        set(&mut self.lazy_continuation, &other.lazy_continuation);
+        set(&mut self.line_break_style, &other.line_break_style);
+        set(&mut self.line_ending, &other.line_ending);
+        set(&mut self.line_width, &other.line_width);
+        set(
+            &mut self.list_marker_alignment,
+            &other.list_marker_alignment,
+        );
+        set(&mut self.list_spacing, &other.list_spacing);
+        set(&mut self.max_blank_lines, &other.max_blank_lines);
+        set(&mut self.max_depth, &other.max_depth);
+        set(
+            &mut self.max_inline_link_length,
+            &other.max_inline_link_length,
+        );
+        set(
+            &mut self.ordered_list_delimiter,
+            &other.ordered_list_delimiter,
+        );
+        set(
+            &mut self.ordered_list_numbering,
+            &other.ordered_list_numbering,
+        );
+        set(&mut self.preserve_tables, &other.preserve_tables);
+        set(&mut self.prettier, &other.prettier);
+        set(&mut self.prune_refdefs, &other.prune_refdefs);
+        set(&mut self.ragged_rows, &other.ragged_rows);
+        set(&mut self.refdef_policy, &other.refdef_policy);
+        set(&mut self.reference_links, &other.reference_links);
+        set(&mut self.require_alt_text, &other.require_alt_text);
+        set(&mut self.smart_emphasis, &other.smart_emphasis);
+        set(&mut self.strong, &other.strong);
+        set(&mut self.tab_width, &other.tab_width);
+        set(&mut self.table_cell_width, &other.table_cell_width);
+        set(&mut self.table_cell_wrap, &other.table_cell_wrap);
+        set(&mut self.table_line_break, &other.table_line_break);
+        set(&mut self.table_rendered_width, &other.table_rendered_width);
+        set(&mut self.table_streaming, &other.table_streaming);
+        set(&mut self.table_style, &other.table_style);
+        set(&mut self.table_width_mode, &other.table_width_mode);
+        set(&mut self.tabs, &other.tabs);
+        set(&mut self.task_checked, &other.task_checked);
+        set(&mut self.title_quote, &other.title_quote);
+        set(&mut self.trailing_whitespace, &other.trailing_whitespace);
+        set(&mut self.unordered_list, &other.unordered_list);
+        set(&mut self.verbatim_badges, &other.verbatim_badges);
+        set(&mut self.wikilinks, &other.wikilinks);
+    }
+}
+
+impl<'a> From<&'a FormatterConfig> for Formatter<'a> {
+    fn from(config: &'a FormatterConfig) -> Self {
+        let mut cmfmt = Formatter::preset(config.preset.unwrap_or_default());
+        if let Some(alerts) = config.alerts {
+            cmfmt = cmfmt.with_alerts(alerts);
+        }
+        if let Some(align) = config.align_numeric_columns {
+            cmfmt = cmfmt.with_align_numeric_columns(align);
+        }
+        if let Some(bare_urls) = config.bare_urls {
+            cmfmt = cmfmt.with_bare_urls(bare_urls);
+        }
+        if let Some(blank_line) = config.blank_line_after_block_items {
+            cmfmt = cmfmt.with_blank_line_after_block_items(blank_line);
+        }
+        if let Some(blank_lines) = config.blank_lines_after_headings {
+            cmfmt = cmfmt.with_blank_lines_after_headings(blank_lines);
+        }
+        if let Some(blank_lines) = config.blank_lines_before_headings {
+            cmfmt = cmfmt.with_blank_lines_before_headings(blank_lines);
+        }
+        if let Some(blockquote) = &config.blockquote {
+            cmfmt = cmfmt.with_blockquote(blockquote);
+        }
+        if let Some(join) = config.blockquote_join {
+            cmfmt = cmfmt.with_blockquote_join(join);
+        }
+        if let Some(nesting) = config.blockquote_nesting {
+            cmfmt = cmfmt.with_blockquote_nesting(nesting);
+        }
+        if let Some(spacing) = config.blockquote_spacing {
+            cmfmt = cmfmt.with_blockquote_spacing(spacing);
+        }
+        if let Some(level) = config.bold_headings {
+            cmfmt = cmfmt.with_bold_headings(HeadingLevel::try_from(level as usize).ok());
+        }
+        if let Some(bold_italic) = config.bold_italic {
+            cmfmt = cmfmt.with_bold_italic(bold_italic);
+        }
+        if let Some(style) = config.bullet_style {
+            cmfmt = cmfmt.with_bullet_style(style);
+        }
+        if let Some(bom) = config.byte_order_mark {
+            cmfmt = cmfmt.with_byte_order_mark(bom);
+        }
+        if let Some(code_fence) = config.code_fence {
+            cmfmt = cmfmt.with_code_fence(code_fence);
+        }
+        if let Some(indent) = config.continuation_indent {
+            cmfmt = cmfmt.with_continuation_indent(indent);
+        }
+        if let Some(convert) = config.convert_html_tables {
+            cmfmt = cmfmt.with_convert_html_tables(convert);
+        }
+        if let Some(duplicates) = config.duplicate_headings {
+            cmfmt = cmfmt.with_duplicate_headings(duplicates);
+        }
+        if let Some(email_links) = config.email_links {
+            cmfmt = cmfmt.with_email_links(email_links);
+        }
+        if let Some(emphasis) = &config.emphasis {
+            cmfmt = cmfmt.with_emphasis(emphasis);
+        }
+        if let Some(empty_links) = config.empty_links {
+            cmfmt = cmfmt.with_empty_links(empty_links);
+        }
+        if let Some(final_newline) = config.final_newline {
+            cmfmt = cmfmt.with_final_newline(final_newline);
+        }
+        if let Some(flavor) = config.flavor {
+            cmfmt = cmfmt.with_flavor(flavor);
+        }
+        if let Some(placement) = config.footnote_placement {
+            cmfmt = cmfmt.with_footnote_placement(placement);
+        }
+        if let Some(renumbering) = config.footnote_renumbering {
+            cmfmt = cmfmt.with_footnote_renumbering(renumbering);
+        }
+        if let Some(hard_break) = config.hard_break {
+            cmfmt = cmfmt.with_hard_break(hard_break);
+        }
+        if let Some(order) = config.heading_attribute_order {
+            cmfmt = cmfmt.with_heading_attribute_order(order);
+        }
+        if let Some(attributes) = config.heading_attributes {
+            cmfmt = cmfmt.with_heading_attributes(attributes);
+        }
+        if let Some(shift) = config.heading_shift {
+            cmfmt = cmfmt.with_heading_shift(shift);
+        }
+        if let Some(style) = config.heading_style {
+            cmfmt = cmfmt.with_heading_style(style);
+        }
+        if let Some(width) = config.indent_width {
+            cmfmt = cmfmt.with_indent_width(Some(width).filter(|&width| width > 0));
+        }
+        if let Some(info_string) = config.info_string {
+            cmfmt = cmfmt.with_info_string(info_string);
+        }
+        if let Some(inline_links) = config.inline_links {
+            cmfmt = cmfmt.with_inline_links(inline_links);
+        }
+        if let Some(label_case) = config.label_case {
+            cmfmt = cmfmt.with_label_case(label_case);
+        }
+        if let Some(lazy_continuation) = config.lazy_continuation {
+            cmfmt = cmfmt.with_lazy_continuation(lazy_continuation);
+        }
+        if let Some(style) = config.line_break_style {
+            cmfmt = cmfmt.with_line_break_style(style);
+        }
+        if let Some(line_ending) = config.line_ending {
+            cmfmt = cmfmt.with_line_ending(line_ending);
+        }
+        if let Some(width) = config.line_width {
+            cmfmt = cmfmt.with_line_width(Some(width).filter(|&width| width > 0));
+        }
+        if let Some(alignment) = config.list_marker_alignment {
+            cmfmt = cmfmt.with_list_marker_alignment(alignment);
+        }
+        if let Some(spacing) = config.list_spacing {
+            cmfmt = cmfmt.with_list_spacing(spacing);
+        }
+        if let Some(max_blank_lines) = config.max_blank_lines {
+            cmfmt = cmfmt.with_max_blank_lines(max_blank_lines);
+        }
+        if let Some(depth) = config.max_depth {
+            cmfmt = cmfmt.with_max_depth(Some(depth).filter(|&depth| depth > 0));
+        }
+        if let Some(length) = config.max_inline_link_length {
+            cmfmt = cmfmt.with_max_inline_link_length(Some(length).filter(|&length| length > 0));
+        }
+        if let Some(delimiter) = config.ordered_list_delimiter {
+            cmfmt = cmfmt.with_ordered_list_delimiter(delimiter);
+        }
+        if let Some(numbering) = config.ordered_list_numbering {
+            cmfmt = cmfmt.with_ordered_list_numbering(numbering);
+        }
+        if let Some(preserve) = config.preserve_tables {
+            cmfmt = cmfmt.with_preserve_tables(preserve);
+        }
+        if let Some(prettier) = config.prettier {
+            cmfmt = cmfmt.with_prettier(prettier);
+        }
+        if let Some(prune) = config.prune_refdefs {
+            cmfmt = cmfmt.with_prune_refdefs(prune);
+        }
+        if let Some(ragged_rows) = config.ragged_rows {
+            cmfmt = cmfmt.with_ragged_rows(ragged_rows);
+        }
+        if let Some(policy) = config.refdef_policy {
+            cmfmt = cmfmt.with_refdef_policy(policy);
+        }
+        if let Some(reference_links) = config.reference_links {
+            cmfmt = cmfmt.with_reference_links(reference_links);
+        }
+        if let Some(require) = config.require_alt_text {
+            cmfmt = cmfmt.with_require_alt_text(require);
+        }
+        if let Some(smart_emphasis) = config.smart_emphasis {
+            cmfmt = cmfmt.with_smart_emphasis(smart_emphasis);
+        }
+        if let Some(strong) = &config.strong {
+            cmfmt = cmfmt.with_strong(strong);
+        }
+        if let Some(width) = config.tab_width {
+            cmfmt = cmfmt.with_tab_width(width);
+        }
+        if let Some(width) = config.table_cell_width {
+            cmfmt = cmfmt.with_table_cell_width(Some(width).filter(|&width| width > 0));
+        }
+        if let Some(wrap) = config.table_cell_wrap {
+            cmfmt = cmfmt.with_table_cell_wrap(wrap);
+        }
+        if let Some(line_break) = config.table_line_break {
+            cmfmt = cmfmt.with_table_line_break(line_break);
+        }
+        if let Some(rendered) = config.table_rendered_width {
+            cmfmt = cmfmt.with_table_rendered_width(rendered);
+        }
+        if let Some(streaming) = config.table_streaming {
+            cmfmt = cmfmt.with_table_streaming(streaming);
+        }
+        if let Some(style) = config.table_style {
+            cmfmt = cmfmt.with_table_style(style);
+        }
+        if let Some(mode) = config.table_width_mode {
+            cmfmt = cmfmt.with_table_width_mode(mode);
+        }
+        if let Some(tabs) = config.tabs {
+            cmfmt = cmfmt.with_tabs(tabs);
+        }
+        if let Some(task_checked) = &config.task_checked {
+            cmfmt = cmfmt.with_task_checked(task_checked.chars().next());
+        }
+        if let Some(title_quote) = config.title_quote {
+            cmfmt = cmfmt.with_title_quote(title_quote);
+        }
+        if let Some(trailing_whitespace) = config.trailing_whitespace {
+            cmfmt = cmfmt.with_trailing_whitespace(trailing_whitespace);
+        }
+        if let Some(unordered_list) = &config.unordered_list {
+            cmfmt = cmfmt.with_unordered_list(unordered_list);
+        }
+        if let Some(verbatim_badges) = config.verbatim_badges {
+            cmfmt = cmfmt.with_verbatim_badges(verbatim_badges);
+        }
+        if let Some(wikilinks) = config.wikilinks {
+            cmfmt = cmfmt.with_wikilinks(wikilinks);
+        }
+        cmfmt
+    }
+}
@@ -0,0 +1,300 @@
+//! Loading of `Formatter` settings from `.cmarkfmt.toml` files.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use std::collections::BTreeMap;
+
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+use crate::{Formatter, FormatterConfig, Preset, PresetMap};
+
+/// Formatting settings, as read from a `.cmarkfmt.toml` file.
+///
+/// The settings of `FormatterConfig` are written at the top level, alongside
+/// the `presets` table. Keys are written in `snake_case`, and enum values in
+/// `kebab-case`.
+///
+/// Example:
+///
+/// ```
+/// use cmarkfmt::{Config, Formatter};
+///
+/// let config = Config::from_toml(r#"
+/// emphasis = "*"
+/// unordered_list = "*"
+///
+/// [[presets]]
+/// pattern = "README.md"
+/// preset = "readme"
+/// "#).unwrap();
+///
+/// let cmfmt = Formatter::from_config(&config);
+/// assert_eq!(cmfmt.format_cmark("- _text_"), "* *text*\n");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawConfig")]
+pub struct Config {
+    /// The formatter settings. When `preset` is unset, the preset is chosen
+    /// from `presets` for each file.
+    pub formatter: FormatterConfig,
+    /// Patterns of file names mapped to the preset used to format them. See
+    /// `PresetMap`.
+    pub presets: Vec<PresetPattern>,
+}
+
+/// A file name pattern and the preset used for matching files.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresetPattern {
+    /// The file name pattern, supporting `*` and `?`.
+    pub pattern: String,
+    /// The preset for files matching `pattern`.
+    pub preset: Preset,
+}
+
+/// The layout of the configuration file. Unknown keys are collected so they
+/// can be rejected, as `deny_unknown_fields` doesn't apply to flattened
+/// fields.
+#[derive(Deserialize)]
+#[serde(default)]
+#[derive(Default)]
+struct RawConfig {
+    #[serde(flatten)]
+    formatter: FormatterConfig,
+    presets: Vec<PresetPattern>,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, IgnoredAny>,
+}
+
+/// A single setting read from an environment variable, collecting the name
+/// if it isn't a setting.
+#[derive(Deserialize)]
+struct EnvSetting {
+    // Only read to check the value of a known setting.
+    #[serde(flatten)]
+    _formatter: FormatterConfig,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, IgnoredAny>,
+}
+
+impl TryFrom<RawConfig> for Config {
+    type Error = String;
+
+    fn try_from(raw: RawConfig) -> Result<Self, Self::Error> {
+        if let Some(key) = raw.unknown.keys().next() {
+            return Err(format!("unknown field `{key}`"));
+        }
+        Ok(Config {
+            formatter: raw.formatter,
+            presets: raw.presets,
+        })
+    }
+}
+
+impl Config {
+    /// The name of the configuration file, `.cmarkfmt.toml`.
+    pub const FILE_NAME: &'static str = ".cmarkfmt.toml";
+
+    /// The environment variable naming a configuration file to use in place
+    /// of the nearest `.cmarkfmt.toml`.
+    pub const ENV_FILE: &'static str = "CMARKFMT_CONFIG";
+
+    /// The prefix of environment variables holding settings.
+    pub const ENV_PREFIX: &'static str = "CMARKFMT_";
+
+    /// Parses a configuration from TOML.
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        toml::from_str(s).map_err(|err| ConfigError::Parse(None, err))
+    }
+
+    /// Loads the configuration file at `path`.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents =
+            fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        toml::from_str(&contents).map_err(|err| ConfigError::Parse(Some(path.to_path_buf()), err))
+    }
+
+    /// Finds and loads the configuration for the file at `path`, from the
+    /// nearest `.cmarkfmt.toml` in its directory or any parent directory.
+    /// Returns `None` if there isn't one.
+    pub fn discover(path: &Path) -> Result<Option<Self>, ConfigError> {
+        for dir in path.ancestors().skip(1) {
+            let file = dir.join(Self::FILE_NAME);
+            if file.is_file() {
+                return Self::load(&file).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the settings for the file at the absolute `path`, from these
+    /// sources in increasing order of precedence:
+    ///
+    /// 1. The preset, if not set by any other source, chosen for the file by
+    ///    the `presets` of the configuration file.
+    /// 2. Settings from the properties of the `.editorconfig` files applying
+    ///    to the file, where otherwise unset, as set by `EditorConfig::apply`.
+    /// 3. With the `markdownlint` feature, settings satisfying the rules of
+    ///    the nearest markdownlint configuration, where otherwise unset.
+    /// 4. The configuration file: `config_file` if provided, then the file
+    ///    named by the `CMARKFMT_CONFIG` environment variable, and otherwise
+    ///    the nearest `.cmarkfmt.toml`.
+    /// 5. The `CMARKFMT_*` environment variables, as read by `from_env_vars`.
+    /// 6. The `overrides`, like command-line arguments.
+    pub fn resolve(
+        path: &Path,
+        config_file: Option<&Path>,
+        overrides: &FormatterConfig,
+    ) -> Result<FormatterConfig, ConfigError> {
+        let file = Self::find(path, config_file)?;
+        let vars = std::env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)));
+
+        let mut config = file.formatter.clone();
+        config.merge(&Self::from_env_vars(vars)?);
+        config.merge(overrides);
+        #[cfg(feature = "markdownlint")]
+        if let Some(markdownlint) = crate::Markdownlint::discover(path)? {
+            markdownlint.apply(&mut config);
+        }
+        crate::EditorConfig::discover(path)
+            .map_err(|err| ConfigError::Io(path.to_path_buf(), err))?
+            .apply(&mut config);
+        if config.preset.is_none() {
+            config.preset = file.preset_map().resolve(path);
+        }
+        Ok(config)
+    }
+
+    /// Returns the configuration for the file at the absolute `path`:
+    /// `config_file` if provided, then the file named by the `CMARKFMT_CONFIG`
+    /// environment variable, and otherwise the nearest `.cmarkfmt.toml`, or
+    /// the default configuration if there isn't one.
+    pub fn find(path: &Path, config_file: Option<&Path>) -> Result<Self, ConfigError> {
+        let env_file = std::env::var_os(Self::ENV_FILE).map(PathBuf::from);
+        match config_file.or(env_file.as_deref()) {
+            Some(config_file) => Self::load(config_file),
+            None => Ok(Self::discover(path)?.unwrap_or_default()),
+        }
+    }
+
+    /// Returns the settings of the `CMARKFMT_*` environment variables among
+    /// `vars`. Each is named after a setting of `FormatterConfig` in upper
+    /// case, like `CMARKFMT_UNORDERED_LIST`, and holds a TOML value, or a
+    /// string if it isn't one. Variables that aren't named after a setting
+    /// are ignored.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{BoldItalic, Config};
+    ///
+    /// let vars = [
+    ///     ("CMARKFMT_EMPHASIS", "*"),
+    ///     ("CMARKFMT_BOLD_ITALIC", "nested"),
+    ///     ("CMARKFMT_BLOCKQUOTE_SPACING", "2"),
+    ///     ("CMARKFMT_LOG", "debug"),
+    ///     ("HOME", "/home/user"),
+    /// ];
+    /// let config = Config::from_env_vars(vars.map(|(k, v)| (k.to_string(), v.to_string()))).unwrap();
+    /// assert_eq!(config.emphasis.as_deref(), Some("*"));
+    /// assert_eq!(config.bold_italic, Some(BoldItalic::Nested));
+    /// assert_eq!(config.blockquote_spacing, Some(2));
+    /// ```
+    pub fn from_env_vars<I>(vars: I) -> Result<FormatterConfig, ConfigError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut table = toml::Table::new();
+        for (key, value) in vars {
+            let Some(name) = key.strip_prefix(Self::ENV_PREFIX) else {
+                continue;
+            };
+            if key == Self::ENV_FILE {
+                continue;
+            }
+            let value = format!("value = {value}")
+                .parse::<toml::Table>()
+                .ok()
+                .and_then(|mut table| table.remove("value"))
+                .unwrap_or(toml::Value::String(value));
+            let mut setting = toml::Table::new();
+            setting.insert(name.to_ascii_lowercase(), value);
+            // Each variable is checked alone, so errors can name it.
+            let parsed = EnvSetting::deserialize(toml::Value::Table(setting.clone()))
+                .map_err(|err| ConfigError::Env(key.clone(), err))?;
+            if parsed.unknown.is_empty() {
+                table.extend(setting);
+            }
+        }
+        FormatterConfig::deserialize(toml::Value::Table(table))
+            .map_err(|err| ConfigError::Parse(None, err))
+    }
+
+    /// Returns the `PresetMap` made from the default map and `presets`.
+    pub fn preset_map(&self) -> PresetMap {
+        self.presets.iter().fold(PresetMap::default(), |map, p| {
+            map.with(&p.pattern, p.preset)
+        })
+    }
+}
+
+impl<'a> Formatter<'a> {
+    /// Creates a `Formatter` from a `Config`, starting from its preset and
+    /// applying every setting it provides.
+    pub fn from_config(config: &'a Config) -> Self {
+        Formatter::from(&config.formatter)
+    }
+}
+
+/// The error returned when a configuration can't be loaded.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The configuration file at the path couldn't be read.
+    Io(PathBuf, io::Error),
+    /// The configuration, from the file at the path if any, is invalid.
+    Parse(Option<PathBuf>, toml::de::Error),
+    /// The setting in the environment variable is invalid.
+    Env(String, toml::de::Error),
+    /// The markdownlint configuration couldn't be loaded.
+    #[cfg(feature = "markdownlint")]
+    Markdownlint(crate::MarkdownlintError),
+}
+
+#[cfg(feature = "markdownlint")]
+impl From<crate::MarkdownlintError> for ConfigError {
+    fn from(err: crate::MarkdownlintError) -> Self {
+        ConfigError::Markdownlint(err)
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            ConfigError::Parse(Some(path), err) => write!(f, "{}: {err}", path.display()),
+            ConfigError::Parse(None, err) => write!(f, "{err}"),
+            ConfigError::Env(key, err) => write!(f, "{key}: {err}"),
+            #[cfg(feature = "markdownlint")]
+            ConfigError::Markdownlint(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(_, err) => Some(err),
+            ConfigError::Parse(_, err) => Some(err),
+            ConfigError::Env(_, err) => Some(err),
+            #[cfg(feature = "markdownlint")]
+            ConfigError::Markdownlint(err) => Some(err),
+        }
+    }
+}
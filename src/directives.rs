@@ -0,0 +1,71 @@
+//! Parsing of formatting directives held in HTML comments.
+
+/// The settings of a `<!-- cmarkfmt: ... -->` directive before a table.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct TableDirective {
+    /// The 0-based column that body rows are sorted by, from the 1-based
+    /// column of `sort-by=N`.
+    pub(crate) sort_by: Option<usize>,
+    /// How the table is laid out, from `table=compact`, `table=padded`, or
+    /// `table=preserve`.
+    pub(crate) layout: Option<TableLayout>,
+    /// Whether cells are padded on the right, rather than by the alignment
+    /// of their column, from `no-align`.
+    pub(crate) no_align: bool,
+}
+
+/// How a table is laid out, overriding the formatter's settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TableLayout {
+    /// Cells aren't padded to the width of their column.
+    Compact,
+    /// Cells are padded to the width of their column.
+    Padded,
+    /// The table is written as it appears in the input.
+    Preserve,
+}
+
+impl TableDirective {
+    /// Parses the directive in the HTML, returning `None` if it isn't one.
+    /// Unknown settings are ignored.
+    pub(crate) fn parse(html: &str) -> Option<Self> {
+        let mut directive = TableDirective::default();
+        for (name, value) in settings(html)? {
+            match (name, value) {
+                ("sort-by", Some(value)) => {
+                    directive.sort_by = value.parse::<usize>().ok().and_then(|v| v.checked_sub(1));
+                }
+                ("table", Some(value)) => {
+                    directive.layout = match value {
+                        "compact" => Some(TableLayout::Compact),
+                        "padded" => Some(TableLayout::Padded),
+                        "preserve" => Some(TableLayout::Preserve),
+                        _ => directive.layout,
+                    };
+                }
+                ("no-align", None) => directive.no_align = true,
+                _ => {}
+            }
+        }
+        Some(directive)
+    }
+}
+
+/// Returns the `name` or `name=value` settings of a directive, separated by
+/// commas or whitespace, or `None` if the HTML isn't a directive.
+fn settings(html: &str) -> Option<impl Iterator<Item = (&str, Option<&str>)>> {
+    let body = html
+        .trim()
+        .strip_prefix("<!--")?
+        .strip_suffix("-->")?
+        .trim()
+        .strip_prefix("cmarkfmt:")?;
+    let settings = body
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|setting| !setting.is_empty())
+        .map(|setting| match setting.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (setting, None),
+        });
+    Some(settings)
+}
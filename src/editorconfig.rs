@@ -0,0 +1,231 @@
+//! Resolution of `.editorconfig` properties for markdown files.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+
+use crate::glob::glob_match;
+use crate::{FinalNewline, FormatterConfig, LineEnding};
+
+/// The `.editorconfig` properties relevant to formatting, as they apply to a
+/// single file.
+///
+/// Example:
+///
+/// ```
+/// use std::path::Path;
+/// use cmarkfmt::{EditorConfig, EndOfLine};
+///
+/// let editorconfig = EditorConfig::parse(r#"
+/// root = true
+///
+/// [*]
+/// end_of_line = lf
+/// indent_size = 4
+///
+/// [*.{md,markdown}]
+/// indent_size = 2
+/// max_line_length = 80
+/// "#, Path::new("docs/guide.md"));
+///
+/// assert_eq!(editorconfig.end_of_line, Some(EndOfLine::Lf));
+/// assert_eq!(editorconfig.indent_size, Some(2));
+/// assert_eq!(editorconfig.max_line_length, Some(80));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EditorConfig {
+    /// The `indent_size` property, resolving `tab` to `tab_width`.
+    pub indent_size: Option<usize>,
+    /// The `end_of_line` property.
+    pub end_of_line: Option<EndOfLine>,
+    /// The `insert_final_newline` property.
+    pub insert_final_newline: Option<bool>,
+    /// The `max_line_length` property, unset when `off`.
+    pub max_line_length: Option<usize>,
+}
+
+/// A value of the `end_of_line` property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndOfLine {
+    /// Lines end with `\n`.
+    Lf,
+    /// Lines end with `\r\n`.
+    Crlf,
+    /// Lines end with `\r`.
+    Cr,
+}
+
+impl EditorConfig {
+    /// The name of the file holding properties, `.editorconfig`.
+    pub const FILE_NAME: &'static str = ".editorconfig";
+
+    /// Returns the properties in the contents of a single `.editorconfig` file
+    /// that apply to `path`, relative to the directory holding the file.
+    pub fn parse(contents: &str, path: &Path) -> Self {
+        let mut properties = HashMap::new();
+        apply(&mut properties, contents, &slash_path(path));
+        Self::from_properties(&properties)
+    }
+
+    /// Returns the properties that apply to the file at the absolute `path`,
+    /// from the `.editorconfig` files in its directory and every parent
+    /// directory up to, and including, the one declaring `root = true`.
+    /// Properties in nearer files take precedence.
+    pub fn discover(path: &Path) -> io::Result<Self> {
+        let mut files = Vec::new();
+        for dir in path.ancestors().skip(1) {
+            let contents = match fs::read_to_string(dir.join(Self::FILE_NAME)) {
+                Ok(contents) => contents,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            let root = is_root(&contents);
+            files.push((dir, contents));
+            if root {
+                break;
+            }
+        }
+
+        let mut properties = HashMap::new();
+        for (dir, contents) in files.iter().rev() {
+            if let Ok(rel) = path.strip_prefix(dir) {
+                apply(&mut properties, contents, &slash_path(rel));
+            }
+        }
+        Ok(Self::from_properties(&properties))
+    }
+
+    /// Sets every setting of `config` that is unset to the corresponding
+    /// property, if set. Settings that are already set are left alone. The
+    /// properties mapped onto settings are:
+    ///
+    /// - `end_of_line`: the line ending, unless `cr`, which isn't supported.
+    /// - `indent_size`: the indent width of list items, if from 2 to 4.
+    /// - `insert_final_newline`: `FinalNewline::Ensure` if true, and
+    ///   `FinalNewline::Strip` if false.
+    /// - `max_line_length`: the line width.
+    pub fn apply(&self, config: &mut FormatterConfig) {
+        let line_ending = match self.end_of_line {
+            Some(EndOfLine::Lf) => Some(LineEnding::Lf),
+            Some(EndOfLine::Crlf) => Some(LineEnding::CrLf),
+            Some(EndOfLine::Cr) | None => None,
+        };
+        if let Some(line_ending) = line_ending {
+            config.line_ending.get_or_insert(line_ending);
+        }
+        if let Some(size) = self.indent_size.filter(|size| (2..=4).contains(size)) {
+            config.indent_width.get_or_insert(size);
+        }
+        if let Some(insert) = self.insert_final_newline {
+            config.final_newline.get_or_insert(if insert {
+                FinalNewline::Ensure
+            } else {
+                FinalNewline::Strip
+            });
+        }
+        if let Some(width) = self.max_line_length {
+            config.line_width.get_or_insert(width);
+        }
+    }
+
+    fn from_properties(properties: &HashMap<String, String>) -> Self {
+        let get = |key: &str| properties.get(key).map(String::as_str);
+        let indent_size = match get("indent_size") {
+            Some("tab") => get("tab_width"),
+            value => value,
+        };
+        EditorConfig {
+            indent_size: indent_size.and_then(|v| v.parse().ok()),
+            end_of_line: match get("end_of_line") {
+                Some("lf") => Some(EndOfLine::Lf),
+                Some("crlf") => Some(EndOfLine::Crlf),
+                Some("cr") => Some(EndOfLine::Cr),
+                _ => None,
+            },
+            insert_final_newline: get("insert_final_newline").and_then(|v| v.parse().ok()),
+            max_line_length: get("max_line_length").and_then(|v| v.parse().ok()),
+        }
+    }
+}
+
+/// Returns true if the preamble of the file declares `root = true`.
+fn is_root(contents: &str) -> bool {
+    contents
+        .lines()
+        .map(str::trim)
+        .take_while(|line| !line.starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .any(|(key, value)| {
+            key.trim().eq_ignore_ascii_case("root") && value.trim().eq_ignore_ascii_case("true")
+        })
+}
+
+/// Sets the properties of every section of the file matching `path`, with
+/// later sections taking precedence. Properties set to `unset` are removed.
+fn apply(properties: &mut HashMap<String, String>, contents: &str, path: &str) {
+    let mut matched = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            matched = section_matches(section, path);
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if !matched {
+            continue;
+        }
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim().to_ascii_lowercase();
+        if value == "unset" {
+            properties.remove(&key);
+        } else {
+            properties.insert(key, value);
+        }
+    }
+}
+
+/// Matches a section glob. Globs without a `/` match the file name at any
+/// depth, and others match the path relative to the `.editorconfig` file.
+fn section_matches(section: &str, path: &str) -> bool {
+    let glob = match section.strip_prefix('/') {
+        Some(glob) => glob.to_string(),
+        None if section.contains('/') => section.to_string(),
+        None => format!("**/{section}"),
+    };
+    expand_braces(&glob)
+        .iter()
+        .any(|glob| glob_match(glob, path))
+}
+
+/// Expands `{a,b}` alternatives into every glob they describe.
+fn expand_braces(glob: &str) -> Vec<String> {
+    let Some(open) = glob.find('{') else {
+        return vec![glob.to_string()];
+    };
+    let Some(len) = glob[open..].find('}') else {
+        return vec![glob.to_string()];
+    };
+    let close = open + len;
+    let (prefix, suffix) = (&glob[..open], &glob[close + 1..]);
+    glob[open + 1..close]
+        .split(',')
+        .flat_map(|alt| expand_braces(&format!("{prefix}{alt}{suffix}")))
+        .collect()
+}
+
+fn slash_path(path: &Path) -> String {
+    let parts = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    parts.join("/")
+}
@@ -0,0 +1,128 @@
+//! Placement and numbering of footnote definitions within the event stream.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use pulldown_cmark::{CowStr, Event, Tag};
+
+use crate::FootnotePlacement;
+
+type OffsetEvent<'a> = (Event<'a>, Range<usize>);
+
+/// Moves the top-level footnote definitions outside of the verbatim regions
+/// according to the placement: after every other block, or after the
+/// top-level block holding the first reference to them. Definitions that are
+/// never referenced are moved after every other block.
+pub(crate) fn place<'a>(
+    events: Vec<OffsetEvent<'a>>,
+    placement: FootnotePlacement,
+    verbatim: &[Range<usize>],
+) -> Vec<OffsetEvent<'a>> {
+    if placement == FootnotePlacement::Preserve {
+        return events;
+    }
+
+    let mut blocks = Vec::new();
+    let mut definitions = Vec::new();
+    let mut block = Vec::new();
+    let mut depth = 0usize;
+    for (event, range) in events {
+        match &event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        block.push((event, range));
+        if depth > 0 {
+            continue;
+        }
+        let block = std::mem::take(&mut block);
+        match &block[0] {
+            (Event::Start(Tag::FootnoteDefinition(label)), range)
+                if !verbatim.iter().any(|v| v.contains(&range.start)) =>
+            {
+                definitions.push((label.to_lowercase(), block));
+            }
+            _ => blocks.push(block),
+        }
+    }
+
+    let mut out = Vec::new();
+    for block in blocks {
+        let mut pending = vec![block];
+        while let Some(block) = pending.pop() {
+            let start = out.len();
+            out.extend(block);
+            if placement != FootnotePlacement::AfterFirstReference {
+                continue;
+            }
+            // Definitions are placed in the order of their first reference,
+            // including references from other definitions.
+            let mut referenced = Vec::new();
+            for (event, _) in &out[start..] {
+                if let Event::FootnoteReference(label) = event {
+                    let label = label.to_lowercase();
+                    if let Some(i) = definitions.iter().position(|(v, _)| *v == label) {
+                        referenced.push(definitions.remove(i).1);
+                    }
+                }
+            }
+            pending.extend(referenced.into_iter().rev());
+        }
+    }
+    for (_, definition) in definitions {
+        out.extend(definition);
+    }
+    out
+}
+
+/// Renames the footnote labels outside of the verbatim regions to sequential
+/// numbers, in the order of their first reference. Definitions that are never
+/// referenced are numbered after the others, in order. Labels within the
+/// verbatim regions are kept, and their numbers skipped.
+pub(crate) fn renumber(events: &mut [OffsetEvent], verbatim: &[Range<usize>]) {
+    let is_verbatim = |range: &Range<usize>| verbatim.iter().any(|v| v.contains(&range.start));
+    let mut kept = HashSet::new();
+    let mut references = Vec::new();
+    let mut definitions = Vec::new();
+    for (event, range) in events.iter() {
+        let (labels, label) = match event {
+            Event::FootnoteReference(label) => (&mut references, label),
+            Event::Start(Tag::FootnoteDefinition(label)) => (&mut definitions, label),
+            _ => continue,
+        };
+        let label = label.to_lowercase();
+        if is_verbatim(range) {
+            kept.insert(label);
+        } else if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+
+    let mut numbers = HashMap::new();
+    let mut next = 1;
+    for label in references.into_iter().chain(definitions) {
+        if kept.contains(&label) || numbers.contains_key(&label) {
+            continue;
+        }
+        while kept.contains(&next.to_string()) {
+            next += 1;
+        }
+        numbers.insert(label, CowStr::from(next.to_string()));
+        next += 1;
+    }
+
+    for (event, range) in events.iter_mut() {
+        if is_verbatim(range) {
+            continue;
+        }
+        let (Event::FootnoteReference(label) | Event::Start(Tag::FootnoteDefinition(label))) =
+            event
+        else {
+            continue;
+        };
+        if let Some(number) = numbers.get(&label.to_lowercase()) {
+            *label = number.clone();
+        }
+    }
+}
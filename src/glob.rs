@@ -0,0 +1,132 @@
+//! Matching of paths against glob patterns, shared by ignore files, preset
+//! patterns, and `.editorconfig` sections.
+
+/// Returns true if the glob matches the whole path. `*` matches any sequence
+/// of characters other than `/`, `**` any sequence including `/`, and `**/`
+/// any number of directories. `?` matches a single character other than `/`,
+/// `[...]` a single character in the class, which may hold ranges and start
+/// with `!` or `^` to negate it, and `\` escapes the character after it.
+///
+/// The match is found in time proportional to the length of the glob times
+/// the length of the path.
+pub(crate) fn glob_match(glob: &str, path: &str) -> bool {
+    let tokens = parse(glob);
+    let path = path.chars().collect::<Vec<_>>();
+
+    // `next[j]` is whether the tokens after the current one match the path
+    // from its `j`th character.
+    let mut next = vec![false; path.len() + 1];
+    next[path.len()] = true;
+    let mut current = vec![false; path.len() + 1];
+    for token in tokens.iter().rev() {
+        // Whether the tokens after this one match the path after a `/` at or
+        // after the `j`th character.
+        let mut after_dir = false;
+        for j in (0..=path.len()).rev() {
+            let c = path.get(j).copied();
+            current[j] = match token {
+                Token::Char(expected) => c == Some(*expected) && next[j + 1],
+                Token::Any => c.is_some_and(|c| c != '/') && next[j + 1],
+                Token::Class(class) => {
+                    c.is_some_and(|c| c != '/' && class.matches(c)) && next[j + 1]
+                }
+                Token::Star => next[j] || (c.is_some_and(|c| c != '/') && current[j + 1]),
+                Token::DoubleStar => next[j] || (c.is_some() && current[j + 1]),
+                Token::Dirs => {
+                    after_dir |= c == Some('/') && next[j + 1];
+                    next[j] || after_dir
+                }
+            };
+        }
+        std::mem::swap(&mut next, &mut current);
+    }
+    next[0]
+}
+
+enum Token {
+    Char(char),
+    Any,
+    Class(Class),
+    Star,
+    DoubleStar,
+    // `**/`, matching any number of directories.
+    Dirs,
+}
+
+struct Class {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        let found = self.ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c));
+        found != self.negated
+    }
+}
+
+fn parse(glob: &str) -> Vec<Token> {
+    let chars = glob.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    tokens.push(Token::Dirs);
+                    i += 3;
+                } else {
+                    tokens.push(Token::DoubleStar);
+                    i += 2;
+                }
+            }
+            '*' => {
+                tokens.push(Token::Star);
+                i += 1;
+            }
+            '?' => {
+                tokens.push(Token::Any);
+                i += 1;
+            }
+            // A class holds at least one character, so `[]]` matches `]`.
+            '[' => match chars[i + 1..].iter().skip(1).position(|&c| c == ']') {
+                Some(len) => {
+                    tokens.push(Token::Class(parse_class(&chars[i + 1..i + 2 + len])));
+                    i += len + 3;
+                }
+                None => {
+                    tokens.push(Token::Char('['));
+                    i += 1;
+                }
+            },
+            '\\' if i + 1 < chars.len() => {
+                tokens.push(Token::Char(chars[i + 1]));
+                i += 2;
+            }
+            c => {
+                tokens.push(Token::Char(c));
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+fn parse_class(class: &[char]) -> Class {
+    let (negated, class) = match class.split_first() {
+        Some(('!' | '^', rest)) if !rest.is_empty() => (true, rest),
+        _ => (false, class),
+    };
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            ranges.push((class[i], class[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((class[i], class[i]));
+            i += 1;
+        }
+    }
+    Class { negated, ranges }
+}
@@ -0,0 +1,384 @@
+//! Conversion of HTML blocks holding simple tables into pipe tables.
+
+use std::ops::Range;
+
+use pulldown_cmark::{Alignment, CowStr, Event, Parser, Tag};
+
+type OffsetEvent<'a> = (Event<'a>, Range<usize>);
+
+// The tags that can't be kept within the inline text of a pipe table cell.
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "blockquote",
+    "caption",
+    "col",
+    "colgroup",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "ul",
+];
+
+/// Replaces the HTML blocks outside of the verbatim regions that hold a
+/// simple table with the events of a pipe table. Tables are only converted if
+/// their first row is a header, every row has as many cells as the header,
+/// and no cell spans rows or columns or holds block content.
+pub(crate) fn convert<'a>(
+    events: Vec<OffsetEvent<'a>>,
+    verbatim: &[Range<usize>],
+    input: &str,
+) -> Vec<OffsetEvent<'a>> {
+    let mut out = Vec::with_capacity(events.len());
+    // Whether HTML within each open block is an HTML block.
+    let mut stack = Vec::new();
+    let mut events = events.into_iter().peekable();
+    while let Some((event, range)) = events.next() {
+        let is_block = stack.last().copied().unwrap_or(true);
+        match &event {
+            Event::Start(tag) => stack.push(matches!(
+                tag,
+                Tag::BlockQuote | Tag::Item | Tag::FootnoteDefinition(_)
+            )),
+            Event::End(_) => {
+                stack.pop();
+            }
+            _ => {}
+        }
+        let Event::Html(html) = &event else {
+            out.push((event, range));
+            continue;
+        };
+        if !is_block || verbatim.iter().any(|v| v.contains(&range.start)) {
+            out.push((event, range));
+            continue;
+        }
+
+        // The lines of an HTML block are separate events, with only the
+        // markers of containers between them.
+        let mut block = vec![(html.clone(), range.clone())];
+        while let Some((Event::Html(html), next)) = events.peek() {
+            let end = block.last().map_or(range.end, |(_, r)| r.end);
+            if next.start < end || input[end..next.start].contains('\n') {
+                break;
+            }
+            block.push((html.clone(), next.clone()));
+            events.next();
+        }
+        let html = block.iter().map(|(html, _)| &**html).collect::<String>();
+        let range = range.start..block.last().map_or(range.end, |(_, r)| r.end);
+        match parse(&html) {
+            Some(table) => out.extend(table_events(table, range)),
+            None => out.extend(block.into_iter().map(|(html, r)| (Event::Html(html), r))),
+        }
+    }
+    out
+}
+
+struct HtmlTable {
+    alignments: Vec<Alignment>,
+    // The rows of cells, starting with the header, holding inline events.
+    rows: Vec<Vec<Vec<Event<'static>>>>,
+}
+
+// Returns the events of the pipe table. The table and its cells span the
+// range of the HTML block, while rows are empty at its end, so that the
+// source of their cells isn't taken for missing or extra cells.
+fn table_events<'a>(table: HtmlTable, range: Range<usize>) -> Vec<OffsetEvent<'a>> {
+    let end = range.end..range.end;
+    let mut out = vec![(
+        Event::Start(Tag::Table(table.alignments.clone())),
+        range.clone(),
+    )];
+    for (i, row) in table.rows.into_iter().enumerate() {
+        let tag = if i == 0 {
+            Tag::TableHead
+        } else {
+            Tag::TableRow
+        };
+        out.push((Event::Start(tag.clone()), end.clone()));
+        for cell in row {
+            out.push((Event::Start(Tag::TableCell), range.clone()));
+            out.extend(cell.into_iter().map(|event| (event, range.clone())));
+            out.push((Event::End(Tag::TableCell), range.clone()));
+        }
+        out.push((Event::End(tag), end.clone()));
+    }
+    out.push((Event::End(Tag::Table(table.alignments)), range));
+    out
+}
+
+// Parses the HTML as a single simple table, returning `None` if it isn't one.
+fn parse(html: &str) -> Option<HtmlTable> {
+    let mut rows: Vec<(bool, Vec<Vec<Event<'static>>>)> = Vec::new();
+    let mut alignments = Vec::new();
+    let mut cell: Option<(bool, Vec<Event<'static>>)> = None;
+    let (mut opened, mut closed, mut in_head) = (false, false, false);
+    for token in tokens(html) {
+        // Comments and declarations aren't kept.
+        if token.starts_with("<!") || (closed && !token.trim().is_empty()) {
+            return None;
+        }
+        let Some(name) = tag_name(token) else {
+            match cell.as_mut() {
+                Some((_, events)) => push_text(events, token),
+                None if token.trim().is_empty() => {}
+                None => return None,
+            }
+            continue;
+        };
+        let closing = token.starts_with("</");
+        let structural = matches!(
+            name.as_str(),
+            "table" | "thead" | "tbody" | "tfoot" | "tr" | "th" | "td"
+        );
+        if !structural {
+            if BLOCK_TAGS.contains(&name.as_str()) {
+                return None;
+            }
+            let (_, events) = cell.as_mut()?;
+            events.push(Event::Html(CowStr::from(token.replace('|', "\\|"))));
+            continue;
+        }
+
+        // Structural tags end the open cell.
+        if let Some((th, events)) = cell.take() {
+            let row = rows.last_mut()?;
+            row.0 &= th;
+            row.1.push(trim_cell(events));
+        }
+        match (name.as_str(), closing) {
+            ("table", false) if !opened => opened = true,
+            ("table", true) => closed = true,
+            ("thead", _) => in_head = !closing,
+            ("tbody" | "tfoot", _) => in_head = false,
+            ("tr", false) => rows.push((true, Vec::new())),
+            ("tr", true) => {}
+            ("th" | "td", false) => {
+                let spans = ["rowspan", "colspan"]
+                    .iter()
+                    .any(|span| attribute(token, span).is_some_and(|v| v.trim() != "1"));
+                if spans || !opened || rows.is_empty() {
+                    return None;
+                }
+                if rows.len() == 1 {
+                    alignments.push(alignment(token));
+                }
+                cell = Some((name == "th" || in_head, Vec::new()));
+            }
+            ("th" | "td", true) => {}
+            _ => return None,
+        }
+    }
+
+    let (header, _) = rows.first()?;
+    let columns = alignments.len();
+    if !closed || !header || columns == 0 || rows.iter().any(|(_, row)| row.len() != columns) {
+        return None;
+    }
+    Some(HtmlTable {
+        alignments,
+        rows: rows.into_iter().map(|(_, row)| row).collect(),
+    })
+}
+
+// Splits the HTML into tags, comments, and the text between them.
+fn tokens(html: &str) -> impl Iterator<Item = &str> {
+    let mut rest = html;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map_or(rest.len(), |i| i + 3)
+        } else if tag_name(rest).is_some() || rest.starts_with("<!") {
+            tag_end(rest)
+        } else {
+            // Text continues until the next tag.
+            let first = rest.chars().next().map_or(0, char::len_utf8);
+            rest[first..].find('<').map_or(rest.len(), |i| i + first)
+        };
+        let (token, tail) = rest.split_at(end);
+        rest = tail;
+        Some(token)
+    })
+}
+
+// Returns the end of the tag at the start of the HTML, after its `>`, ignoring
+// any within quoted attribute values.
+fn tag_end(html: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in html.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+// Returns the lowercase name of the opening or closing tag at the start of the
+// HTML.
+fn tag_name(html: &str) -> Option<String> {
+    let name = html.strip_prefix("</").or_else(|| html.strip_prefix('<'))?;
+    let len = name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(name.len());
+    if len == 0 || !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(name[..len].to_ascii_lowercase())
+}
+
+// Returns the value of the attribute of the tag, or an empty string if it has
+// no value.
+fn attribute<'h>(tag: &'h str, name: &str) -> Option<&'h str> {
+    let mut rest = tag
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .trim_end_matches('/');
+    rest = rest.trim_start_matches(|c: char| c.is_ascii_alphanumeric());
+    loop {
+        rest = rest.trim_start();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return None;
+        }
+        let (attr, tail) = rest.split_at(len);
+        let tail = tail.trim_start();
+        let (value, tail) = match tail.strip_prefix('=') {
+            Some(tail) => {
+                let tail = tail.trim_start();
+                match tail.chars().next() {
+                    Some(q @ ('"' | '\'')) => {
+                        let end = tail[1..].find(q).map_or(tail.len(), |i| i + 1);
+                        (&tail[1..end], tail.get(end + 1..).unwrap_or_default())
+                    }
+                    _ => {
+                        let end = tail.find(char::is_whitespace).unwrap_or(tail.len());
+                        tail.split_at(end)
+                    }
+                }
+            }
+            None => ("", tail),
+        };
+        if attr.eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+        rest = tail;
+    }
+}
+
+// Returns the alignment of a header cell from its `align` attribute, or the
+// `text-align` property of its style.
+fn alignment(tag: &str) -> Alignment {
+    let style = attribute(tag, "style")
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let value = attribute(tag, "align")
+        .map(str::to_ascii_lowercase)
+        .or_else(|| {
+            style.split(';').find_map(|declaration| {
+                let (property, value) = declaration.split_once(':')?;
+                (property.trim() == "text-align").then(|| value.trim().to_string())
+            })
+        });
+    match value.as_deref() {
+        Some("left") => Alignment::Left,
+        Some("center") => Alignment::Center,
+        Some("right") => Alignment::Right,
+        _ => Alignment::None,
+    }
+}
+
+// Pushes the text of a cell with its whitespace collapsed and its character
+// references decoded. As the parser does, the text is split before the
+// characters that may need to be escaped, which the formatter only checks at
+// the start of text.
+fn push_text(events: &mut Vec<Event<'static>>, text: &str) {
+    let mut collapsed = String::with_capacity(text.len());
+    for c in decode_references(text).chars() {
+        if !c.is_whitespace() {
+            collapsed.push(c);
+        } else if !collapsed.ends_with(' ') {
+            collapsed.push(' ');
+        }
+    }
+    let text = collapsed;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if i > start && "\\<>*_`[]~|#-+".contains(c) {
+            events.push(Event::Text(CowStr::from(text[start..i].to_string())));
+            start = i;
+        }
+    }
+    events.push(Event::Text(CowStr::from(text[start..].to_string())));
+}
+
+// Decodes the character references in the text, like `&amp;`, as the parser
+// would in the text of a pipe table.
+fn decode_references(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let len = rest[1..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '#')
+            .filter(|&i| rest[1 + i..].starts_with(';'))
+            .map_or(1, |i| i + 2);
+        let (reference, tail) = rest.split_at(len);
+        let decoded = Parser::new(reference)
+            .filter_map(|event| match event {
+                Event::Text(text) => Some(text.into_string()),
+                _ => None,
+            })
+            .collect::<String>();
+        out.push_str(if len > 1 && !decoded.is_empty() {
+            &decoded
+        } else {
+            reference
+        });
+        rest = tail;
+    }
+    out.push_str(rest);
+    out
+}
+
+// Trims the whitespace at the start and end of a cell's text.
+fn trim_cell(mut events: Vec<Event<'static>>) -> Vec<Event<'static>> {
+    while let Some(Event::Text(text)) = events.first() {
+        let trimmed = text.trim_start();
+        if !trimmed.is_empty() {
+            events[0] = Event::Text(CowStr::from(trimmed.to_string()));
+            break;
+        }
+        events.remove(0);
+    }
+    while let Some(Event::Text(text)) = events.last() {
+        let trimmed = text.trim_end();
+        if !trimmed.is_empty() {
+            let last = events.len() - 1;
+            events[last] = Event::Text(CowStr::from(trimmed.to_string()));
+            break;
+        }
+        events.pop();
+    }
+    events
+}
@@ -0,0 +1,123 @@
+//! Matching of paths against gitignore-style patterns.
+
+use std::path::{Component, Path};
+
+use crate::glob::glob_match;
+
+/// A set of gitignore-style patterns, as read from a `.cmarkfmtignore` file,
+/// for skipping files like generated changelogs or vendored docs.
+///
+/// Each line holds a pattern. Blank lines and lines starting with `#` are
+/// ignored, and a leading `!` re-includes paths excluded by an earlier
+/// pattern. A pattern ending with `/` only matches directories. A pattern
+/// containing any other `/` is matched against the whole path relative to the
+/// ignore file, while other patterns match the name at any depth. Patterns
+/// support `*`, `?`, `[...]`, and `**` for any number of directories.
+///
+/// Example:
+///
+/// ```
+/// use std::path::Path;
+/// use cmarkfmt::Ignore;
+///
+/// let ignore = Ignore::new("CHANGELOG.md\nvendor/\n*.gen.md\n!keep.gen.md\n");
+/// assert!(ignore.is_ignored(Path::new("CHANGELOG.md"), false));
+/// assert!(ignore.is_ignored(Path::new("docs/api.gen.md"), false));
+/// assert!(ignore.is_ignored(Path::new("vendor/lib/README.md"), false));
+/// assert!(!ignore.is_ignored(Path::new("docs/keep.gen.md"), false));
+/// assert!(!ignore.is_ignored(Path::new("README.md"), false));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Ignore {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Pattern {
+    glob: String,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl Ignore {
+    /// The name of the file holding ignore patterns, `.cmarkfmtignore`.
+    pub const FILE_NAME: &'static str = ".cmarkfmtignore";
+
+    /// Parses the contents of an ignore file.
+    pub fn new(contents: &str) -> Self {
+        let patterns = contents.lines().filter_map(Pattern::parse).collect();
+        Ignore { patterns }
+    }
+
+    /// Returns true if the path, relative to the directory holding the ignore
+    /// file, is excluded by the patterns. A path is also excluded when any of
+    /// its parent directories are.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut parts = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(part) => parts.push(part.to_string_lossy()),
+                Component::ParentDir => return false,
+                _ => {}
+            }
+        }
+
+        let mut rel = String::new();
+        for (i, part) in parts.iter().enumerate() {
+            if i > 0 {
+                rel.push('/');
+            }
+            rel.push_str(part);
+            let last = i + 1 == parts.len();
+            if self.matches(&rel, part, !last || is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns true if the last pattern matching the path excludes it.
+    fn matches(&self, path: &str, name: &str, is_dir: bool) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|p| {
+                (is_dir || !p.dir_only)
+                    && if p.anchored {
+                        glob_match(&p.glob, path)
+                    } else {
+                        glob_match(&p.glob, name)
+                    }
+            })
+            .is_some_and(|p| !p.negated)
+    }
+}
+
+impl Pattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let glob = line.strip_prefix('/').unwrap_or(line);
+        if glob.is_empty() {
+            return None;
+        }
+        Some(Pattern {
+            glob: glob.to_string(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+}
@@ -13,11 +13,20 @@
 //! println!("{output}");
 //! ```
 
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
 use std::fmt::{self, Debug, Write};
+use std::ops::{Range, RangeInclusive};
+use std::path::Path;
 
-use pulldown_cmark::{
-    Alignment, CodeBlockKind, Event, HeadingLevel, LinkType, Options as POptions, Parser, Tag,
-};
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, Options as POptions, Parser, Tag};
+
+use crate::directives::{TableDirective, TableLayout};
+use crate::refdefs::{link_label, normalize_label, strip_markers};
+
+pub use pulldown_cmark::{HeadingLevel, LinkType};
 
 /// Function for formatting code blocks within markdown.
 ///
@@ -48,6 +57,1023 @@ use pulldown_cmark::{
 /// ```
 pub type CodeFormatFn<'a> = &'a dyn Fn(&str, &str) -> Option<String>;
 
+/// Function for selecting the top-level blocks to format.
+///
+/// The parameter is the kind of a top-level block. Blocks for which the
+/// function returns false are copied through verbatim, along with everything
+/// else outside the selected blocks, like link reference definitions.
+///
+/// Example:
+///
+/// ```
+/// use cmarkfmt::BlockKind;
+///
+/// let input = "# Title\n\n|a|b|\n|-|-|\n|1|2|\n\n* list\n";
+///
+/// let cmfmt = cmarkfmt::Formatter::default()
+///     .with_block_filter(Some(&|kind| kind == BlockKind::Table));
+///
+/// let output = cmfmt.format_cmark(input);
+/// assert_eq!(output, "# Title\n\n| a   | b   |\n| --- | --- |\n| 1   | 2   |\n\n* list\n");
+/// ```
+pub type BlockFilterFn<'a> = &'a dyn Fn(BlockKind) -> bool;
+
+/// Function for transforming the text of headings.
+///
+/// The first parameter is the level that the heading is written at, and the
+/// second parameter is its formatted text, without the heading markers. If
+/// transformed, returns `Some(String)` with the text to use.
+///
+/// Example:
+///
+/// ```
+/// use cmarkfmt::HeadingLevel;
+///
+/// let input = "# Getting started\n\n## Next steps";
+///
+/// let cmfmt = cmarkfmt::Formatter::default().with_heading_transform(Some(&|level, text| {
+///     if level == HeadingLevel::H1 {
+///         Some(text.to_uppercase())
+///     } else {
+///         None
+///     }
+/// }));
+///
+/// let output = cmfmt.format_cmark(input);
+/// assert_eq!(output, "# GETTING STARTED\n\n## Next steps\n");
+/// ```
+pub type HeadingTransformFn<'a> = &'a dyn Fn(HeadingLevel, &str) -> Option<String>;
+
+/// Function for formatting the text of table cells.
+///
+/// The first parameter is the 0-based column of a cell, and the second
+/// parameter is its formatted text. It's called for the cells of the header
+/// and the body, before the widths of the columns are measured. If formatted,
+/// returns `Some(String)` with the text to use.
+///
+/// Example:
+///
+/// ```
+/// let input = "|Item|Price|\n|-|-|\n|Tea|1.5|";
+///
+/// let cmfmt = cmarkfmt::Formatter::default().with_table_cell_fmt(Some(&|column, text| {
+///     match text.parse::<f64>() {
+///         Ok(price) if column == 1 => Some(format!("{price:.2}")),
+///         _ => None,
+///     }
+/// }));
+///
+/// let output = cmfmt.format_cmark(input);
+/// assert_eq!(output, "| Item | Price |\n| ---- | ----- |\n| Tea  | 1.50  |\n");
+/// ```
+pub type TableCellFormatFn<'a> = &'a dyn Fn(usize, &str) -> Option<String>;
+
+/// Function for choosing the column that the body rows of a table are sorted
+/// by.
+///
+/// The parameter is the formatted text of the header cells of a table. Returns
+/// the 0-based column to sort by, or `None` to keep the order of the rows.
+/// Cells are sorted as numbers if both are numbers, and as case-insensitive
+/// text otherwise.
+///
+/// A `<!-- cmarkfmt: sort-by=N -->` comment before a table sorts its rows by
+/// the 1-based column `N` instead.
+///
+/// Example:
+///
+/// ```
+/// let input = "|Name|Size|\n|-|-|\n|b|10|\n|a|9|\n\n<!-- cmarkfmt: sort-by=2 -->\n|Name|Size|\n|-|-|\n|b|10|\n|a|9|\n";
+///
+/// let cmfmt = cmarkfmt::Formatter::default()
+///     .with_table_sort(Some(&|head| head.iter().position(|cell| cell == "Name")));
+///
+/// let output = cmfmt.format_cmark(input);
+/// assert_eq!(
+///     output,
+///     "| Name | Size |\n| ---- | ---- |\n| a    | 9    |\n| b    | 10   |\n\n\
+///      <!-- cmarkfmt: sort-by=2 -->\n\n\
+///      | Name | Size |\n| ---- | ---- |\n| a    | 9    |\n| b    | 10   |\n"
+/// );
+/// ```
+pub type TableSortFn<'a> = &'a dyn Fn(&[String]) -> Option<usize>;
+
+/// Function for rewriting the destinations of links.
+///
+/// The first parameter is the type of a link, and the second parameter is its
+/// destination. Link reference definitions are passed as
+/// `LinkType::Reference`. If rewritten, returns `Some(String)` with the
+/// destination to use.
+///
+/// Example:
+///
+/// ```
+/// let input = "See [the guide](https://github.com/o/r/blob/main/docs/guide.md).\n\n[home]: https://github.com/o/r/blob/main/README.md";
+///
+/// let cmfmt = cmarkfmt::Formatter::default().with_link_rewriter(Some(&|_, dest| {
+///     dest.strip_prefix("https://github.com/o/r/blob/main/")
+///         .map(|path| format!("/{path}"))
+/// }));
+///
+/// let output = cmfmt.format_cmark(input);
+/// assert_eq!(output, "See [the guide](/docs/guide.md).\n\n[home]: /README.md\n");
+/// ```
+pub type LinkRewriteFn<'a> = &'a dyn Fn(LinkType, &str) -> Option<String>;
+
+/// Function for rewriting the sources of images.
+///
+/// The first parameter is the formatted alt text of an image, and the second
+/// parameter is its source. If rewritten, returns `Some(String)` with the
+/// source to use. Images are written as inline images, so this also applies
+/// to those that use a link reference definition.
+///
+/// Example:
+///
+/// ```
+/// let input = "![Logo](images/logo.png) and ![Badge](https://example.com/badge.svg)";
+///
+/// let cmfmt = cmarkfmt::Formatter::default().with_image_rewriter(Some(&|_, src| {
+///     src.strip_prefix("images/")
+///         .map(|path| format!("https://cdn.example.com/{path}"))
+/// }));
+///
+/// let output = cmfmt.format_cmark(input);
+/// assert_eq!(
+///     output,
+///     "![Logo](https://cdn.example.com/logo.png) and ![Badge](https://example.com/badge.svg)\n"
+/// );
+/// ```
+pub type ImageRewriteFn<'a> = &'a dyn Fn(&str, &str) -> Option<String>;
+
+/// Function for supplying the alt text of images that have none.
+///
+/// The parameter is the source of an image without alt text. Returns
+/// `Some(String)` with the alt text to write, or `None` to leave it empty.
+///
+/// Example:
+///
+/// ```
+/// let input = "![](images/logo.png) and ![](photo.jpg)";
+///
+/// let cmfmt = cmarkfmt::Formatter::default().with_alt_text(Some(&|src| {
+///     (src == "images/logo.png").then(|| "The logo".to_string())
+/// }));
+///
+/// let output = cmfmt.format_cmark(input);
+/// assert_eq!(output, "![The logo](images/logo.png) and ![](photo.jpg)\n");
+/// ```
+pub type AltTextFn<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+mod autolinks;
+mod blocks;
+mod comments;
+mod config;
+mod directives;
+#[cfg(feature = "config")]
+mod editorconfig;
+mod footnotes;
+mod glob;
+mod html_tables;
+mod ignore;
+mod links;
+mod lint;
+#[cfg(feature = "markdownlint")]
+mod markdownlint;
+mod preset;
+mod refdefs;
+#[cfg(feature = "sarif")]
+mod sarif;
+mod verbatim;
+mod wrap;
+
+pub use blocks::{blocks, BlockInfo, BlockKind, Blocks};
+pub use comments::comment_prefixes;
+pub use config::FormatterConfig;
+#[cfg(feature = "config")]
+pub use config::{Config, ConfigError, PresetPattern};
+#[cfg(feature = "config")]
+pub use editorconfig::{EditorConfig, EndOfLine};
+pub use ignore::Ignore;
+pub use links::{extract_images, extract_links, ImageInfo, LinkInfo};
+pub use lint::{Diagnostic, Rule};
+#[cfg(feature = "markdownlint")]
+pub use markdownlint::{Markdownlint, MarkdownlintError};
+pub use preset::{ParsePresetError, Preset, PresetMap};
+#[cfg(feature = "sarif")]
+pub use sarif::sarif_log;
+
+/// Controls how adjacent blockquotes, and the blocks within them, are
+/// separated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum BlockquoteJoin {
+    /// Blockquotes are kept as they appear in the input.
+    #[default]
+    Preserve,
+    /// Adjacent blockquotes separated only by blank lines are merged into a
+    /// single blockquote, separated by a `>`-only line.
+    Merge,
+    /// Blocks within a blockquote are separated by an unquoted blank line,
+    /// splitting them into distinct blockquotes.
+    Split,
+}
+
+/// Controls how the markers of nested blockquotes are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum BlockquoteNesting {
+    /// Markers are separated by the blockquote spacing, e.g. `> >`.
+    #[default]
+    Spaced,
+    /// Markers are written without spaces between them, e.g. `>>`.
+    Compact,
+    /// Each nested blockquote keeps the spacing of the input.
+    Preserve,
+}
+
+/// Controls where top-level footnote definitions are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum FootnotePlacement {
+    /// Definitions are kept where they appear in the input.
+    #[default]
+    Preserve,
+    /// Definitions are collected at the end of the document.
+    EndOfDocument,
+    /// Definitions follow the top-level block that first references them.
+    AfterFirstReference,
+}
+
+/// Controls where and in which order link reference definitions are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum RefDefPolicy {
+    /// Definitions are collected at the end of the document, sorted by label.
+    #[default]
+    SortedAtEnd,
+    /// Definitions are collected at the end of the document, in the order
+    /// they appear in the input.
+    PreserveOrderAtEnd,
+    /// Definitions are kept where they appear in the input.
+    KeepInPlace,
+    /// Definitions follow the top-level block that first uses them, in the
+    /// order of their first use. Definitions that are never used are
+    /// collected at the end of the document, in the order they appear in the
+    /// input.
+    AfterFirstUse,
+}
+
+/// Controls how bare URLs in text are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum BareUrls {
+    /// Bare URLs are written as they appear in the input.
+    #[default]
+    Preserve,
+    /// Bare URLs are written as autolinks, like `<https://example.com>`.
+    Autolink,
+    /// Bare URLs are written as inline links, like
+    /// `[https://example.com](https://example.com)`.
+    Inline,
+}
+
+/// The delimiters that link titles are written with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum TitleQuote {
+    /// Titles are written in double quotes: `"title"`.
+    #[default]
+    Double,
+    /// Titles are written in single quotes: `'title'`.
+    Single,
+    /// Titles are written in parentheses: `(title)`.
+    Parentheses,
+}
+
+impl TitleQuote {
+    // Returns the title within the delimiters, escaping the delimiters and
+    // the backslashes that would otherwise escape the character after them.
+    fn apply(self, title: &str) -> String {
+        let (open, close) = match self {
+            TitleQuote::Double => ('"', '"'),
+            TitleQuote::Single => ('\'', '\''),
+            TitleQuote::Parentheses => ('(', ')'),
+        };
+        let mut out = String::with_capacity(title.len() + 2);
+        out.push(open);
+        let mut chars = title.chars().peekable();
+        while let Some(c) = chars.next() {
+            let escaped = match c {
+                '\\' => chars
+                    .peek()
+                    .copied()
+                    .unwrap_or(close)
+                    .is_ascii_punctuation(),
+                c => c == open || c == close,
+            };
+            if escaped {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push(close);
+        out
+    }
+}
+
+/// The form that reference links are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum ReferenceLinks {
+    /// Reference links are written in the form they appear in the input.
+    #[default]
+    Preserve,
+    /// Reference links are written in full: `[text][label]`.
+    Full,
+    /// Reference links are written collapsed where possible: `[label][]`.
+    Collapsed,
+    /// Reference links are written as shortcuts where possible: `[label]`.
+    Shortcut,
+}
+
+/// The case that the labels of reference links and definitions are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum LabelCase {
+    /// Labels are written in the case they appear in the input.
+    #[default]
+    Preserve,
+    /// Labels are written in lowercase.
+    Lowercase,
+}
+
+impl LabelCase {
+    // Returns the label in the case. Labels match case-insensitively, so this
+    // doesn't change which definition a reference link uses.
+    fn apply(self, label: &str) -> String {
+        match self {
+            LabelCase::Preserve => label.to_string(),
+            LabelCase::Lowercase => label.to_lowercase(),
+        }
+    }
+}
+
+/// The form that links to email addresses are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum EmailLinks {
+    /// Email links are written in the form they appear in the input.
+    #[default]
+    Preserve,
+    /// Email links are written as autolinks, like `<user@example.com>`, where
+    /// their text is the address.
+    Autolink,
+    /// Email links are written as inline links, like
+    /// `[user@example.com](mailto:user@example.com)`.
+    Explicit,
+}
+
+/// Controls how text that is both strong and emphasized is written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum BoldItalic {
+    /// The nesting order of the input is kept.
+    #[default]
+    Preserve,
+    /// A single run of three strong delimiter characters, e.g. `***text***`.
+    Triple,
+    /// Emphasis nested within strong text, e.g. `**_text_**`.
+    Nested,
+}
+
+/// Controls which character the markers of unordered lists are written with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum BulletStyle {
+    /// Every list uses the unordered list string.
+    #[default]
+    Consistent,
+    /// Each list keeps the marker of the input.
+    Preserve,
+    /// Nested lists cycle through `-`, `*`, and `+` by depth, starting from
+    /// the unordered list string at the top level.
+    Depth,
+}
+
+/// Whether the output starts with a UTF-8 byte order mark. The byte order
+/// mark of the input is never parsed as text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum ByteOrderMark {
+    /// The output starts with a byte order mark only if the input does.
+    #[default]
+    Preserve,
+    /// The output always starts with a byte order mark.
+    Ensure,
+    /// The output never starts with a byte order mark.
+    Strip,
+}
+
+/// Controls how headings with the same anchor as an earlier heading are
+/// handled. The anchor of a heading is its `{#id}` attribute, or the slug of
+/// its text, like `getting-started` for "Getting Started".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum DuplicateHeadings {
+    /// Headings are written as they appear in the input.
+    #[default]
+    Preserve,
+    /// Duplicate `{#id}` attributes get a `-1`, `-2`, etc. suffix, the first
+    /// making the anchor unique.
+    Deduplicate,
+    /// Headings are written as they appear in the input, and duplicates are
+    /// reported as diagnostics by `Formatter::lint`.
+    Diagnose,
+}
+
+/// Controls how links and images with an empty destination, and links with
+/// empty text, are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum EmptyLinks {
+    /// Empty links and images are written as they appear in the input.
+    #[default]
+    Preserve,
+    /// Empty links and images are converted to plain text: the text of a link
+    /// or image without a destination is kept, a link without text is
+    /// replaced by its destination, and a link with neither is dropped.
+    PlainText,
+    /// Empty links and images are written as they appear in the input, and
+    /// reported as diagnostics by `Formatter::lint`.
+    Diagnose,
+}
+
+/// Controls whether the output ends with a newline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum FinalNewline {
+    /// The output ends with exactly one newline.
+    #[default]
+    Ensure,
+    /// The output ends with a newline only if the input does.
+    Preserve,
+    /// The output doesn't end with a newline.
+    Strip,
+}
+
+/// The character that fenced code blocks are written with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum FenceChar {
+    /// Code blocks are fenced by backticks, e.g. ```` ``` ````.
+    #[default]
+    Backtick,
+    /// Code blocks are fenced by tildes, e.g. `~~~`.
+    Tilde,
+}
+
+impl FenceChar {
+    fn char(self) -> char {
+        match self {
+            FenceChar::Backtick => '`',
+            FenceChar::Tilde => '~',
+        }
+    }
+}
+
+/// The dialect of markdown that input is parsed as.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Flavor {
+    /// CommonMark with the GitHub Flavored Markdown extensions (tables,
+    /// strikethrough, and task lists), footnotes, and heading attributes.
+    #[default]
+    Gfm,
+    /// CommonMark without any extensions.
+    #[cfg_attr(feature = "cli", value(name = "commonmark"))]
+    #[cfg_attr(feature = "serde", serde(rename = "commonmark"))]
+    CommonMark,
+}
+
+/// Controls how hard line breaks are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum HardBreak {
+    /// A backslash at the end of the line.
+    #[default]
+    Backslash,
+    /// Two spaces at the end of the line. Hard breaks within links are
+    /// written with a backslash, as trailing spaces aren't kept there.
+    TwoSpaces,
+}
+
+/// Controls how the attributes of headings, like `{ #id .class }`, are
+/// written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum HeadingAttributes {
+    /// Attributes are written with spaces inside the braces, e.g.
+    /// `{ #id .class }`.
+    #[default]
+    Spaced,
+    /// Attributes are written without spaces inside the braces, e.g.
+    /// `{#id .class}`.
+    Compact,
+    /// Attributes are removed, for renderers that don't support them.
+    Remove,
+}
+
+/// Controls the order that the attributes of headings are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum HeadingAttributeOrder {
+    /// The ID is written before the classes.
+    #[default]
+    IdFirst,
+    /// The ID is written in its position among the classes in the input.
+    Preserve,
+}
+
+/// Controls how headings are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum HeadingStyle {
+    /// Headings are prefixed by `#` characters, e.g. `## Heading`.
+    #[default]
+    Atx,
+    /// First- and second-level headings are underlined by `=` and `-`
+    /// characters. Deeper headings, and those that can't be underlined, like
+    /// empty headings, are prefixed by `#` characters.
+    Setext,
+}
+
+/// Controls how the info strings of fenced code blocks, like `rust` in
+/// ```` ```rust ````, are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum InfoString {
+    /// Info strings are written as they appear in the input.
+    #[default]
+    Preserve,
+    /// Info strings are trimmed, and their language, the first word, is
+    /// lowercased. The attributes following it are kept.
+    Normalize,
+    /// Info strings are reduced to their lowercased language.
+    Language,
+}
+
+impl InfoString {
+    fn apply(self, info: &str) -> String {
+        let info = info.trim();
+        let (lang, attrs) = match info.split_once(char::is_whitespace) {
+            Some((lang, attrs)) => (lang, attrs.trim_start()),
+            None => (info, ""),
+        };
+        match self {
+            InfoString::Preserve => info.to_string(),
+            InfoString::Normalize if !attrs.is_empty() => {
+                format!("{} {attrs}", lang.to_lowercase())
+            }
+            InfoString::Normalize | InfoString::Language => lang.to_lowercase(),
+        }
+    }
+}
+
+/// Controls how soft line breaks within paragraphs and list items are
+/// written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum LineBreakStyle {
+    /// Soft line breaks are kept as they appear in the input.
+    #[default]
+    Preserve,
+    /// Soft line breaks are replaced by spaces, writing each paragraph on a
+    /// single line. Hard line breaks are kept.
+    Join,
+}
+
+/// The line ending that output lines are written with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum LineEnding {
+    /// Lines end with `\n`.
+    #[default]
+    Lf,
+    /// Lines end with `\r\n`.
+    #[cfg_attr(feature = "cli", value(name = "crlf"))]
+    #[cfg_attr(feature = "serde", serde(rename = "crlf"))]
+    CrLf,
+    /// Lines end with `\r\n` on Windows, and `\n` elsewhere.
+    Native,
+    /// Lines end like the first line of the input, or with `\n` if it has a
+    /// single line.
+    Preserve,
+}
+
+impl LineEnding {
+    fn as_str(self, input: &str) -> &'static str {
+        let crlf = match self {
+            LineEnding::Lf => false,
+            LineEnding::CrLf => true,
+            LineEnding::Native => cfg!(windows),
+            LineEnding::Preserve => input.find('\n').is_some_and(|i| input[..i].ends_with('\r')),
+        };
+        if crlf {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+}
+
+/// Controls how the markers of ordered list items with numbers of different
+/// widths are aligned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum ListMarkerAlignment {
+    /// Markers aren't padded, so the text of items starts after their own
+    /// marker.
+    #[default]
+    None,
+    /// Markers are padded with spaces after them, e.g. `9.  ` and `10. `.
+    Left,
+    /// Markers are padded with spaces before them, e.g. ` 9. ` and `10. `.
+    Right,
+}
+
+/// Controls how the lines of list items after the first are indented.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum ContinuationIndent {
+    /// Lines are aligned with the text after the marker.
+    #[default]
+    Marker,
+    /// Lines are indented by 4 spaces, or more for wider markers.
+    Four,
+}
+
+/// Controls whether list items are separated by blank lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum ListSpacing {
+    /// Items aren't separated by blank lines.
+    #[default]
+    Tight,
+    /// Items are always separated by blank lines.
+    Loose,
+    /// Items are separated by blank lines if their list is loose in the
+    /// input.
+    Preserve,
+}
+
+/// The delimiter written after the numbers of ordered list items.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum OrderedListDelimiter {
+    /// A period, e.g. `1.`.
+    #[default]
+    Period,
+    /// A closing parenthesis, e.g. `1)`.
+    Paren,
+    /// The delimiter each list has in the input.
+    Preserve,
+}
+
+/// Controls how the items of ordered lists are numbered. The first item keeps
+/// the start number of the list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum OrderedListNumbering {
+    /// Every item is numbered with the start number of the list.
+    #[default]
+    Start,
+    /// Items are numbered incrementally from the start number, e.g. 1, 2, 3.
+    Sequential,
+    /// Every item after the first is numbered 1, so that inserting or removing
+    /// items doesn't renumber the others.
+    AllOnes,
+    /// Items keep the numbers they have in the input.
+    Preserve,
+}
+
+impl Flavor {
+    fn parser_options(self) -> POptions {
+        match self {
+            Flavor::Gfm => parser_options(),
+            Flavor::CommonMark => POptions::empty(),
+        }
+    }
+}
+
+/// How body rows of tables with more or fewer cells than the header are
+/// written. Rows with fewer cells are always padded with empty cells.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum RaggedRows {
+    /// Cells beyond the number of header cells are dropped.
+    #[default]
+    Truncate,
+    /// Columns with empty header cells are added for cells beyond the number
+    /// of header cells.
+    Extend,
+}
+
+/// Controls how tab characters in text outside of code are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Tabs {
+    /// Tabs are written as they appear in the input.
+    #[default]
+    Preserve,
+    /// Tabs are expanded to spaces, up to the next multiple of the tab width.
+    Expand,
+    /// Tabs are written as they appear in the input, and reported as
+    /// diagnostics by `Formatter::lint`.
+    Diagnose,
+}
+
+/// Whether the rows of tables are written with leading and trailing pipes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum TableStyle {
+    /// Rows are written with outer pipes, e.g. `| a | b |`.
+    #[default]
+    Piped,
+    /// Rows are written without outer pipes, e.g. `a | b`.
+    Unpiped,
+    /// Rows are written with outer pipes only if the header row of the input
+    /// starts with a pipe.
+    Preserve,
+}
+
+/// The tag that line breaks within table cells are written with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum TableLineBreak {
+    /// Line breaks are written as they appear in the input. Cells wrapped by
+    /// `Formatter::with_table_cell_wrap` are broken with `<br>`.
+    #[default]
+    Preserve,
+    /// Line breaks are written as `<br>`.
+    Br,
+    /// Line breaks are written as `<br/>`.
+    SelfClosing,
+}
+
+impl TableLineBreak {
+    // Returns the tag that line breaks are written with, or `None` if they're
+    // preserved.
+    fn tag(self) -> Option<&'static str> {
+        match self {
+            TableLineBreak::Preserve => None,
+            TableLineBreak::Br => Some("<br>"),
+            TableLineBreak::SelfClosing => Some("<br/>"),
+        }
+    }
+}
+
+/// How the width of table cells is measured when padding their columns.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum WidthMode {
+    /// Each character is one column wide.
+    #[default]
+    Chars,
+    /// Characters are as wide as they're displayed in a terminal, so that
+    /// wide characters, like CJK text and emoji, are two columns wide. Without
+    /// the `unicode-width` feature, this is the same as `WidthMode::Chars`.
+    Display,
+}
+
+/// Controls where trailing whitespace is trimmed from the ends of lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum TrailingWhitespace {
+    /// Trailing whitespace is trimmed, except for hard line breaks written
+    /// with two spaces.
+    #[default]
+    Trim,
+    /// Trailing whitespace is also kept in HTML and code blocks.
+    KeepInBlocks,
+    /// Trailing whitespace is trimmed from every line, so hard line breaks
+    /// are written with a backslash.
+    TrimAll,
+}
+
+/// The error returned when markdown can't be formatted.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FormatError {
+    /// Writing the output failed.
+    Fmt(fmt::Error),
+    /// Blocks are nested deeper than the maximum depth, from the 1-based line
+    /// of the input.
+    MaxDepth(usize),
+}
+
+impl From<fmt::Error> for FormatError {
+    fn from(err: fmt::Error) -> Self {
+        FormatError::Fmt(err)
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Fmt(err) => write!(f, "{err}"),
+            FormatError::MaxDepth(line) => {
+                write!(
+                    f,
+                    "line {line}: blocks are nested deeper than the maximum depth"
+                )
+            }
+        }
+    }
+}
+
+impl Error for FormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FormatError::Fmt(err) => Some(err),
+            FormatError::MaxDepth(_) => None,
+        }
+    }
+}
+
 /// A `Formatter` is needed to format markdown. It is created and customized as
 /// needed using the `with_*` methods.
 ///
@@ -56,18 +1082,168 @@ pub type CodeFormatFn<'a> = &'a dyn Fn(&str, &str) -> Option<String>;
 #[derive(Clone)]
 pub struct Formatter<'a> {
     code_fmt: Option<CodeFormatFn<'a>>,
+    block_filter: Option<BlockFilterFn<'a>>,
+    heading_transform: Option<HeadingTransformFn<'a>>,
+    table_cell_fmt: Option<TableCellFormatFn<'a>>,
+    table_sort: Option<TableSortFn<'a>>,
+    link_rewriter: Option<LinkRewriteFn<'a>>,
+    image_rewriter: Option<ImageRewriteFn<'a>>,
+    alt_text: Option<AltTextFn<'a>>,
+    alerts: bool,
+    align_numeric_columns: bool,
+    bare_urls: BareUrls,
+    blank_line_after_block_items: bool,
+    blank_lines_after_headings: usize,
+    blank_lines_before_headings: usize,
     blockquote: &'a str,
+    blockquote_join: BlockquoteJoin,
+    blockquote_nesting: BlockquoteNesting,
+    blockquote_spacing: usize,
+    bold_headings: Option<HeadingLevel>,
+    bold_italic: BoldItalic,
+    bullet_style: BulletStyle,
+    byte_order_mark: ByteOrderMark,
+    code_fence: FenceChar,
+    continuation_indent: ContinuationIndent,
+    convert_html_tables: bool,
+    duplicate_headings: DuplicateHeadings,
+    email_links: EmailLinks,
     emphasis: &'a str,
+    empty_links: EmptyLinks,
+    final_newline: FinalNewline,
+    flavor: Flavor,
+    footnote_placement: FootnotePlacement,
+    footnote_renumbering: bool,
+    hard_break: HardBreak,
+    heading_attribute_order: HeadingAttributeOrder,
+    heading_attributes: HeadingAttributes,
+    heading_shift: i8,
+    heading_style: HeadingStyle,
+    indent_width: Option<usize>,
+    info_string: InfoString,
+    inline_links: bool,
+    label_case: LabelCase,
+    lazy_continuation: bool,
+    line_break_style: LineBreakStyle,
+    line_ending: LineEnding,
+    line_width: Option<usize>,
+    lines: Option<&'a [RangeInclusive<usize>]>,
+    link_base: Option<&'a Path>,
+    list_marker_alignment: ListMarkerAlignment,
+    list_spacing: ListSpacing,
+    max_blank_lines: usize,
+    max_depth: Option<usize>,
+    max_inline_link_length: Option<usize>,
+    ordered_list_delimiter: OrderedListDelimiter,
+    ordered_list_numbering: OrderedListNumbering,
+    preserve_tables: bool,
+    prettier: bool,
+    prune_refdefs: bool,
+    ragged_rows: RaggedRows,
+    refdef_policy: RefDefPolicy,
+    reference_links: ReferenceLinks,
+    require_alt_text: bool,
+    smart_emphasis: bool,
+    strong: &'a str,
+    tab_width: usize,
+    table_cell_width: Option<usize>,
+    table_cell_wrap: bool,
+    table_line_break: TableLineBreak,
+    table_rendered_width: bool,
+    table_streaming: bool,
+    table_style: TableStyle,
+    table_width_mode: WidthMode,
+    tabs: Tabs,
+    task_checked: Option<char>,
+    title_quote: TitleQuote,
+    trailing_whitespace: TrailingWhitespace,
     unordered_list: &'a str,
+    verbatim_badges: bool,
+    wikilinks: bool,
 }
 
 impl Default for Formatter<'_> {
     fn default() -> Self {
         Self {
             code_fmt: None,
+            block_filter: None,
+            heading_transform: None,
+            table_cell_fmt: None,
+            table_sort: None,
+            link_rewriter: None,
+            image_rewriter: None,
+            alt_text: None,
+            alerts: false,
+            align_numeric_columns: false,
+            bare_urls: BareUrls::Preserve,
+            blank_line_after_block_items: true,
+            blank_lines_after_headings: 1,
+            blank_lines_before_headings: 1,
             blockquote: ">",
+            blockquote_join: BlockquoteJoin::Preserve,
+            blockquote_nesting: BlockquoteNesting::Spaced,
+            blockquote_spacing: 1,
+            bold_headings: None,
+            bold_italic: BoldItalic::Preserve,
+            bullet_style: BulletStyle::Consistent,
+            byte_order_mark: ByteOrderMark::Preserve,
+            code_fence: FenceChar::Backtick,
+            continuation_indent: ContinuationIndent::Marker,
+            convert_html_tables: false,
+            duplicate_headings: DuplicateHeadings::Preserve,
+            email_links: EmailLinks::Preserve,
             emphasis: "_",
+            empty_links: EmptyLinks::Preserve,
+            final_newline: FinalNewline::Ensure,
+            flavor: Flavor::Gfm,
+            footnote_placement: FootnotePlacement::Preserve,
+            footnote_renumbering: false,
+            hard_break: HardBreak::Backslash,
+            heading_attribute_order: HeadingAttributeOrder::IdFirst,
+            heading_attributes: HeadingAttributes::Spaced,
+            heading_shift: 0,
+            heading_style: HeadingStyle::Atx,
+            indent_width: None,
+            info_string: InfoString::Preserve,
+            inline_links: false,
+            label_case: LabelCase::Preserve,
+            lazy_continuation: false,
+            line_break_style: LineBreakStyle::Preserve,
+            line_ending: LineEnding::Lf,
+            line_width: None,
+            lines: None,
+            link_base: None,
+            list_marker_alignment: ListMarkerAlignment::None,
+            list_spacing: ListSpacing::Tight,
+            max_blank_lines: 1,
+            max_depth: None,
+            max_inline_link_length: None,
+            ordered_list_delimiter: OrderedListDelimiter::Period,
+            ordered_list_numbering: OrderedListNumbering::Start,
+            preserve_tables: false,
+            prettier: false,
+            prune_refdefs: false,
+            ragged_rows: RaggedRows::Truncate,
+            refdef_policy: RefDefPolicy::SortedAtEnd,
+            reference_links: ReferenceLinks::Preserve,
+            require_alt_text: false,
+            smart_emphasis: false,
+            strong: "**",
+            tab_width: 4,
+            table_cell_width: None,
+            table_cell_wrap: false,
+            table_line_break: TableLineBreak::Preserve,
+            table_rendered_width: false,
+            table_streaming: false,
+            table_style: TableStyle::Piped,
+            table_width_mode: WidthMode::Chars,
+            tabs: Tabs::Preserve,
+            task_checked: Some('x'),
+            title_quote: TitleQuote::Double,
+            trailing_whitespace: TrailingWhitespace::Trim,
             unordered_list: "-",
+            verbatim_badges: false,
+            wikilinks: false,
         }
     }
 }
@@ -76,26 +1252,185 @@ impl Debug for Formatter<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FormatBuilder")
             .field("code_fmt", &self.code_fmt.map(|_| ()))
+            .field("block_filter", &self.block_filter.map(|_| ()))
+            .field("heading_transform", &self.heading_transform.map(|_| ()))
+            .field("table_cell_fmt", &self.table_cell_fmt.map(|_| ()))
+            .field("table_sort", &self.table_sort.map(|_| ()))
+            .field("link_rewriter", &self.link_rewriter.map(|_| ()))
+            .field("image_rewriter", &self.image_rewriter.map(|_| ()))
+            .field("alt_text", &self.alt_text.map(|_| ()))
+            .field("alerts", &self.alerts)
+            .field("align_numeric_columns", &self.align_numeric_columns)
+            .field("bare_urls", &self.bare_urls)
+            .field(
+                "blank_line_after_block_items",
+                &self.blank_line_after_block_items,
+            )
+            .field(
+                "blank_lines_after_headings",
+                &self.blank_lines_after_headings,
+            )
+            .field(
+                "blank_lines_before_headings",
+                &self.blank_lines_before_headings,
+            )
             .field("blockquote", &self.blockquote)
+            .field("blockquote_join", &self.blockquote_join)
+            .field("blockquote_nesting", &self.blockquote_nesting)
+            .field("blockquote_spacing", &self.blockquote_spacing)
+            .field("bold_headings", &self.bold_headings)
+            .field("bold_italic", &self.bold_italic)
+            .field("bullet_style", &self.bullet_style)
+            .field("byte_order_mark", &self.byte_order_mark)
+            .field("code_fence", &self.code_fence)
+            .field("continuation_indent", &self.continuation_indent)
+            .field("convert_html_tables", &self.convert_html_tables)
+            .field("duplicate_headings", &self.duplicate_headings)
+            .field("email_links", &self.email_links)
             .field("emphasis", &self.emphasis)
+            .field("empty_links", &self.empty_links)
+            .field("final_newline", &self.final_newline)
+            .field("flavor", &self.flavor)
+            .field("footnote_placement", &self.footnote_placement)
+            .field("footnote_renumbering", &self.footnote_renumbering)
+            .field("hard_break", &self.hard_break)
+            .field("heading_attribute_order", &self.heading_attribute_order)
+            .field("heading_attributes", &self.heading_attributes)
+            .field("heading_shift", &self.heading_shift)
+            .field("heading_style", &self.heading_style)
+            .field("indent_width", &self.indent_width)
+            .field("info_string", &self.info_string)
+            .field("inline_links", &self.inline_links)
+            .field("label_case", &self.label_case)
+            .field("lazy_continuation", &self.lazy_continuation)
+            .field("line_break_style", &self.line_break_style)
+            .field("line_ending", &self.line_ending)
+            .field("line_width", &self.line_width)
+            .field("lines", &self.lines)
+            .field("link_base", &self.link_base)
+            .field("list_marker_alignment", &self.list_marker_alignment)
+            .field("list_spacing", &self.list_spacing)
+            .field("max_blank_lines", &self.max_blank_lines)
+            .field("max_depth", &self.max_depth)
+            .field("max_inline_link_length", &self.max_inline_link_length)
+            .field("ordered_list_delimiter", &self.ordered_list_delimiter)
+            .field("ordered_list_numbering", &self.ordered_list_numbering)
+            .field("preserve_tables", &self.preserve_tables)
+            .field("prettier", &self.prettier)
+            .field("prune_refdefs", &self.prune_refdefs)
+            .field("ragged_rows", &self.ragged_rows)
+            .field("refdef_policy", &self.refdef_policy)
+            .field("reference_links", &self.reference_links)
+            .field("require_alt_text", &self.require_alt_text)
+            .field("smart_emphasis", &self.smart_emphasis)
+            .field("strong", &self.strong)
+            .field("tab_width", &self.tab_width)
+            .field("table_cell_width", &self.table_cell_width)
+            .field("table_cell_wrap", &self.table_cell_wrap)
+            .field("table_line_break", &self.table_line_break)
+            .field("table_rendered_width", &self.table_rendered_width)
+            .field("table_streaming", &self.table_streaming)
+            .field("table_style", &self.table_style)
+            .field("table_width_mode", &self.table_width_mode)
+            .field("tabs", &self.tabs)
+            .field("task_checked", &self.task_checked)
+            .field("title_quote", &self.title_quote)
+            .field("trailing_whitespace", &self.trailing_whitespace)
             .field("unordered_list", &self.unordered_list)
+            .field("verbatim_badges", &self.verbatim_badges)
+            .field("wikilinks", &self.wikilinks)
             .finish()
     }
 }
 
 impl<'a> Formatter<'a> {
-    /// Format markdown, returning the formatted result as a String.
+    /// Creates a `Formatter` configured with the provided `Preset`. The
+    /// result can be customized further using the `with_*` methods.
+    pub fn preset(preset: Preset) -> Self {
+        match preset {
+            Preset::Default => Self::default(),
+            Preset::Github => Self::default().with_alerts(true),
+            Preset::Readme => Self::default().with_verbatim_badges(true),
+            Preset::Mdbook => Self::default().with_emphasis("*"),
+            Preset::CommonmarkStrict => Self::default()
+                .with_flavor(Flavor::CommonMark)
+                .with_emphasis("*")
+                .with_unordered_list("*"),
+            Preset::Prettier => Self::default()
+                .with_bold_italic(BoldItalic::Nested)
+                .with_prettier(true),
+        }
+    }
+
+    /// Format markdown, returning the formatted result as a String. If blocks
+    /// are nested deeper than the maximum depth, the input is returned as is;
+    /// use `try_format_cmark` to handle the error instead, or `lint` to report
+    /// it as a diagnostic.
     pub fn format_cmark(&self, input: &str) -> String {
+        self.try_format_cmark(input)
+            .unwrap_or_else(|_| input.to_string())
+    }
+
+    /// Format markdown, returning the formatted result as a String, or an
+    /// error if blocks are nested deeper than the maximum depth.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{FormatError, Formatter};
+    ///
+    /// let cmfmt = Formatter::default().with_max_depth(Some(2));
+    /// assert_eq!(cmfmt.try_format_cmark("> * item").unwrap(), "> - item\n");
+    ///
+    /// let err = cmfmt.try_format_cmark("> * item\n>   > quote").unwrap_err();
+    /// assert!(matches!(err, FormatError::MaxDepth(2)));
+    /// ```
+    pub fn try_format_cmark(&self, input: &str) -> Result<String, FormatError> {
         let mut out = String::with_capacity(input.len() + 128);
-        self.format_cmark_writer(input, &mut out).unwrap();
-        out
+        self.format_with_diagnostics(input, &mut out)?;
+        Ok(out)
     }
 
-    /// Format markdown, writing the result to the provided Writer.
+    /// Format markdown, writing the result to the provided Writer. Returns an
+    /// error if blocks are nested deeper than the maximum depth, after writing
+    /// the output before them; use `try_format_cmark_writer` to tell it apart
+    /// from an error of the Writer.
     pub fn format_cmark_writer<W: fmt::Write>(&self, input: &str, w: W) -> fmt::Result {
-        let mut opts = POptions::all();
-        opts.remove(POptions::ENABLE_SMART_PUNCTUATION);
-        let parser = Parser::new_ext(input, opts);
+        self.try_format_cmark_writer(input, w)
+            .map_err(|_| fmt::Error)
+    }
+
+    /// Format markdown, writing the result to the provided Writer. Returns
+    /// `FormatError::MaxDepth` if blocks are nested deeper than the maximum
+    /// depth, after writing the output before them, and `FormatError::Fmt` if
+    /// the Writer fails.
+    pub fn try_format_cmark_writer<W: fmt::Write>(
+        &self,
+        input: &str,
+        w: W,
+    ) -> Result<(), FormatError> {
+        self.format_with_diagnostics(input, w).map(|_| ())
+    }
+
+    fn format_with_diagnostics<W: fmt::Write>(
+        &self,
+        input: &str,
+        mut w: W,
+    ) -> Result<Vec<Diagnostic>, FormatError> {
+        let (bom, input) = match input.strip_prefix('\u{feff}') {
+            Some(input) => ('\u{feff}'.len_utf8(), input),
+            None => (0, input),
+        };
+        let write_bom = match self.byte_order_mark {
+            ByteOrderMark::Preserve => bom > 0,
+            ByteOrderMark::Ensure => true,
+            ByteOrderMark::Strip => false,
+        };
+        if write_bom {
+            w.write_char('\u{feff}')?;
+        }
+
+        let parser = Parser::new_ext(input, self.flavor.parser_options());
 
         let mut refdefs = parser
             .reference_definitions()
@@ -104,12 +1439,90 @@ impl<'a> Formatter<'a> {
                 label: label.to_owned(),
                 dest: linkdef.dest.to_string(),
                 title: linkdef.title.as_ref().map(|v| v.to_string()),
+                span: linkdef.span.clone(),
+                verbatim: false,
+                written: false,
             })
             .collect::<Vec<_>>();
-        refdefs.sort_by(|r1, r2| r1.label.cmp(&r2.label));
+        match self.refdef_policy {
+            RefDefPolicy::SortedAtEnd => refdefs.sort_by(|r1, r2| r1.label.cmp(&r2.label)),
+            RefDefPolicy::PreserveOrderAtEnd
+            | RefDefPolicy::KeepInPlace
+            | RefDefPolicy::AfterFirstUse => refdefs.sort_by_key(|r| r.span.start),
+        }
+
+        let mut verbatim = Vec::new();
+        if self.verbatim_badges {
+            verbatim.extend(verbatim::readme_regions(input));
+        }
+        if let Some(block_filter) = self.block_filter {
+            verbatim.extend(verbatim::unselected_regions(input, |b| {
+                block_filter(b.kind)
+            }));
+        }
+        if let Some(lines) = self.lines {
+            verbatim.extend(verbatim::unselected_lines(input, lines));
+        }
+        let verbatim = verbatim::merge(verbatim);
+        for refdef in refdefs.iter_mut() {
+            refdef.verbatim = verbatim
+                .iter()
+                .any(|v| v.start <= refdef.span.start && refdef.span.end <= v.end);
+            if !refdef.verbatim {
+                refdef.label = self.label_case.apply(&refdef.label);
+            }
+        }
+
+        let mut events = parser.into_offset_iter().collect::<Vec<_>>();
+        let mut pruned = Vec::new();
+        let mut broken = Vec::new();
+        if let Some(base) = self.link_base {
+            links::check(&events, input, &verbatim, base, &mut broken);
+        }
+        let mut refdef_aliases = HashMap::new();
+        if self.prune_refdefs {
+            refdef_aliases = refdefs::prune(&mut refdefs, &events, input, &mut pruned);
+        }
+        if self.inline_links {
+            // Definitions in verbatim regions are written with them.
+            refdefs.retain(|v| v.verbatim);
+        }
+        if let Some(rewrite) = self.link_rewriter {
+            for refdef in refdefs.iter_mut().filter(|v| !v.verbatim) {
+                if let Some(dest) = rewrite(LinkType::Reference, &refdef.dest) {
+                    refdef.dest = dest;
+                }
+            }
+        }
 
-        let mut ctx = Context::new(w, refdefs, self.into());
-        ctx.format(parser)
+        let mut ctx = Context::new(w, input, refdefs, Options::new(self, input));
+        ctx.refdef_aliases = refdef_aliases;
+        if self.list_marker_alignment != ListMarkerAlignment::None {
+            ctx.list_items = ordered_list_items(input, self.flavor.parser_options());
+        }
+        if self.convert_html_tables {
+            events = html_tables::convert(events, &verbatim, input);
+        }
+        let events = autolinks::convert(events, &verbatim, self.bare_urls);
+        let events = autolinks::convert_emails(events, &verbatim, self.email_links);
+        let mut events = footnotes::place(events, self.footnote_placement, &verbatim);
+        if self.footnote_renumbering {
+            footnotes::renumber(&mut events, &verbatim);
+        }
+        if let Err(err) = ctx.format(events, verbatim) {
+            if ctx.too_deep {
+                let line = input[..ctx.range.start].matches('\n').count() + 1;
+                return Err(FormatError::MaxDepth(line));
+            }
+            return Err(FormatError::Fmt(err));
+        }
+        ctx.diagnostics.extend(pruned);
+        ctx.diagnostics.extend(broken);
+        // The ranges of diagnostics are offsets into the input as given.
+        for diagnostic in ctx.diagnostics.iter_mut() {
+            diagnostic.range = diagnostic.range.start + bom..diagnostic.range.end + bom;
+        }
+        Ok(ctx.diagnostics)
     }
 
     /// Sets the `Formatter`s code formatter function. By default, code blocks
@@ -118,145 +1531,2079 @@ impl<'a> Formatter<'a> {
         Formatter { code_fmt, ..self }
     }
 
-    /// Sets the blockquote string. Default: ">".
-    pub fn with_blockquote(self, blockquote: &'a str) -> Self {
-        Formatter { blockquote, ..self }
+    /// Sets the `Formatter`s block filter function. By default, all blocks are
+    /// formatted.
+    pub fn with_block_filter(self, block_filter: Option<BlockFilterFn<'a>>) -> Self {
+        Formatter {
+            block_filter,
+            ..self
+        }
     }
 
-    /// Sets the emphasis string. Default: "_".
-    pub fn with_emphasis(self, emphasis: &'a str) -> Self {
-        Formatter { emphasis, ..self }
+    /// Sets the `Formatter`s heading transform function. By default, the text
+    /// of headings is not transformed.
+    pub fn with_heading_transform(self, heading_transform: Option<HeadingTransformFn<'a>>) -> Self {
+        Formatter {
+            heading_transform,
+            ..self
+        }
     }
 
-    /// Sets the unordered list string. Default: "-".
-    pub fn with_unordered_list(self, unordered_list: &'a str) -> Self {
+    /// Sets the `Formatter`s table cell format function. By default, the text
+    /// of table cells is not formatted.
+    pub fn with_table_cell_fmt(self, table_cell_fmt: Option<TableCellFormatFn<'a>>) -> Self {
         Formatter {
-            unordered_list,
+            table_cell_fmt,
             ..self
         }
     }
-}
 
-const STRONG: &str = "**";
-const STRIKETHROUGH: &str = "~~";
-
-enum StackItem {
-    Blockquote,
-    CodeIndent,
-    List(Option<String>, bool, bool),
-}
+    /// Sets the `Formatter`s table sort function. By default, the body rows of
+    /// tables are only sorted by `sort-by` directives.
+    pub fn with_table_sort(self, table_sort: Option<TableSortFn<'a>>) -> Self {
+        Formatter { table_sort, ..self }
+    }
 
-struct Options<'a> {
-    code_fmt: &'a Option<CodeFormatFn<'a>>,
+    /// Sets the `Formatter`s link rewrite function. By default, the
+    /// destinations of links are not rewritten.
+    pub fn with_link_rewriter(self, link_rewriter: Option<LinkRewriteFn<'a>>) -> Self {
+        Formatter {
+            link_rewriter,
+            ..self
+        }
+    }
+
+    /// Sets the `Formatter`s image rewrite function. By default, the sources
+    /// of images are not rewritten.
+    pub fn with_image_rewriter(self, image_rewriter: Option<ImageRewriteFn<'a>>) -> Self {
+        Formatter {
+            image_rewriter,
+            ..self
+        }
+    }
+
+    /// Sets the `Formatter`s alt text function. By default, images without
+    /// alt text are written without it.
+    pub fn with_alt_text(self, alt_text: Option<AltTextFn<'a>>) -> Self {
+        Formatter { alt_text, ..self }
+    }
+
+    /// Sets whether the markers of GitHub alerts, like `[!NOTE]` on the first
+    /// line of a blockquote, are written without escaping their brackets.
+    /// Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "> [!NOTE]\n> Read this.";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default();
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "> \\[!NOTE\\]\n> Read this.\n");
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_alerts(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "> [!NOTE]\n> Read this.\n");
+    /// ```
+    pub fn with_alerts(self, alerts: bool) -> Self {
+        Formatter { alerts, ..self }
+    }
+
+    /// Sets whether table columns without an alignment are aligned to the
+    /// right if every cell of their body is a number, like `-1,234.5` or
+    /// `50%`. Empty cells are ignored. Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "|Item|Price|\n|-|-|\n|Tea|1.50|\n|Cake|12.00|";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_align_numeric_columns(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(
+    ///     output,
+    ///     "| Item | Price |\n| ---- | ----: |\n| Tea  | 1.50  |\n| Cake | 12.00 |\n"
+    /// );
+    /// ```
+    pub fn with_align_numeric_columns(self, align: bool) -> Self {
+        Formatter {
+            align_numeric_columns: align,
+            ..self
+        }
+    }
+
+    /// Sets how bare `http://` and `https://` URLs in text are written, so
+    /// that they're links for renderers that don't detect them. URLs within
+    /// links, code, and HTML anchors are kept as they are. Default:
+    /// `BareUrls::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{BareUrls, Formatter};
+    ///
+    /// let input = "See https://example.com/docs.";
+    ///
+    /// let cmfmt = Formatter::default().with_bare_urls(BareUrls::Autolink);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "See <https://example.com/docs>.\n");
+    /// ```
+    pub fn with_bare_urls(self, bare_urls: BareUrls) -> Self {
+        Formatter { bare_urls, ..self }
+    }
+
+    /// Sets whether items of tight lists ending in a block other than a
+    /// paragraph or list, like a blockquote or code block, are followed by a
+    /// blank line. With `ListSpacing::Loose`, every item is. Default: true.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "- > Quote\n- Item";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_blank_line_after_block_items(false);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "- > Quote\n- Item\n");
+    /// ```
+    pub fn with_blank_line_after_block_items(self, blank_line: bool) -> Self {
+        Formatter {
+            blank_line_after_block_items: blank_line,
+            ..self
+        }
+    }
+
+    /// Sets the number of blank lines written after headings, of at least 1.
+    /// When a heading directly follows another, the larger of this and the
+    /// number of blank lines before the second heading is used. Headings
+    /// within blockquotes and lists are always followed by a single blank
+    /// line, so the looseness of lists is kept. Default: 1.
+    pub fn with_blank_lines_after_headings(self, blank_lines: usize) -> Self {
+        Formatter {
+            blank_lines_after_headings: blank_lines,
+            ..self
+        }
+    }
+
+    /// Sets the number of blank lines written before headings, of at least 1,
+    /// other than at the start of the document. Headings within blockquotes
+    /// and lists are always preceded by a single blank line. Default: 1.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "# Title\nText.\n## Section\nText.";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_blank_lines_before_headings(2);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "# Title\n\nText.\n\n\n## Section\n\nText.\n");
+    /// ```
+    pub fn with_blank_lines_before_headings(self, blank_lines: usize) -> Self {
+        Formatter {
+            blank_lines_before_headings: blank_lines,
+            ..self
+        }
+    }
+
+    /// Sets the blockquote string. Trailing whitespace is ignored; use
+    /// `with_blockquote_spacing` to control the space after the marker.
+    /// Default: ">".
+    pub fn with_blockquote(self, blockquote: &'a str) -> Self {
+        Formatter { blockquote, ..self }
+    }
+
+    /// Sets how adjacent blockquotes, and the blocks within them, are
+    /// separated. Default: `BlockquoteJoin::Preserve`.
+    pub fn with_blockquote_join(self, blockquote_join: BlockquoteJoin) -> Self {
+        Formatter {
+            blockquote_join,
+            ..self
+        }
+    }
+
+    /// Sets how the markers of nested blockquotes are written. Default:
+    /// `BlockquoteNesting::Spaced`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{BlockquoteNesting, Formatter};
+    ///
+    /// let input = "> > Nested";
+    ///
+    /// let cmfmt = Formatter::default().with_blockquote_nesting(BlockquoteNesting::Compact);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, ">> Nested\n");
+    /// ```
+    pub fn with_blockquote_nesting(self, blockquote_nesting: BlockquoteNesting) -> Self {
+        Formatter {
+            blockquote_nesting,
+            ..self
+        }
+    }
+
+    /// Sets the number of spaces written after each blockquote marker, up to a
+    /// maximum of 4. A single space is still used where the quoted content
+    /// depends on its indentation, like indented code blocks. Default: 1.
+    pub fn with_blockquote_spacing(self, blockquote_spacing: usize) -> Self {
+        Formatter {
+            blockquote_spacing: blockquote_spacing.min(4),
+            ..self
+        }
+    }
+
+    /// Sets the heading level that top-level paragraphs consisting solely of a
+    /// single line of strong text, like `**Introduction**`, are converted to.
+    /// Lines ending in punctuation are left alone. Each conversion is reported
+    /// by `Formatter::lint`. Default: None.
+    pub fn with_bold_headings(self, bold_headings: Option<HeadingLevel>) -> Self {
+        Formatter {
+            bold_headings,
+            ..self
+        }
+    }
+
+    /// Sets how text that is both strong and emphasized is written. Default:
+    /// `BoldItalic::Preserve`.
+    pub fn with_bold_italic(self, bold_italic: BoldItalic) -> Self {
+        Formatter {
+            bold_italic,
+            ..self
+        }
+    }
+
+    /// Sets which character the markers of unordered lists are written with.
+    /// With `BulletStyle::Preserve`, lists keep their marker from the input,
+    /// and with `BulletStyle::Depth`, the marker depends on how deeply the
+    /// list is nested, both overriding Prettier's alternation. Default:
+    /// `BulletStyle::Consistent`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{BulletStyle, Formatter};
+    ///
+    /// let input = "* One\n\n+ Two";
+    ///
+    /// let cmfmt = Formatter::default().with_bullet_style(BulletStyle::Preserve);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "* One\n\n+ Two\n");
+    /// ```
+    pub fn with_bullet_style(self, bullet_style: BulletStyle) -> Self {
+        Formatter {
+            bullet_style,
+            ..self
+        }
+    }
+
+    /// Sets whether the output starts with a UTF-8 byte order mark. A byte
+    /// order mark at the start of the input is excluded from parsing either
+    /// way. Default: `ByteOrderMark::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{ByteOrderMark, Formatter};
+    ///
+    /// let input = "\u{feff}# Title";
+    ///
+    /// let output = Formatter::default().format_cmark(input);
+    /// assert_eq!(output, "\u{feff}# Title\n");
+    ///
+    /// let cmfmt = Formatter::default().with_byte_order_mark(ByteOrderMark::Strip);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "# Title\n");
+    /// ```
+    pub fn with_byte_order_mark(self, byte_order_mark: ByteOrderMark) -> Self {
+        Formatter {
+            byte_order_mark,
+            ..self
+        }
+    }
+
+    /// Sets the character that fenced code blocks are written with. Default:
+    /// `FenceChar::Backtick`.
+    pub fn with_code_fence(self, code_fence: FenceChar) -> Self {
+        Formatter { code_fence, ..self }
+    }
+
+    /// Sets how the lines of list items after the first, like those of later
+    /// paragraphs and nested blocks, are indented. Lines of indented code
+    /// blocks are always aligned with the text after the marker. Default:
+    /// `ContinuationIndent::Marker`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{ContinuationIndent, Formatter};
+    ///
+    /// let input = "- One\n\n  Two\n\n  - Three";
+    ///
+    /// let cmfmt = Formatter::default().with_continuation_indent(ContinuationIndent::Four);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "- One\n\n    Two\n\n    - Three\n");
+    /// ```
+    pub fn with_continuation_indent(self, continuation_indent: ContinuationIndent) -> Self {
+        Formatter {
+            continuation_indent,
+            ..self
+        }
+    }
+
+    /// Sets whether HTML blocks holding a simple table are converted to pipe
+    /// tables. Tables are only converted if their first row is a header, every
+    /// row has as many cells as the header, and no cell spans rows or columns
+    /// or holds block content like paragraphs or lists. Inline HTML within
+    /// cells is kept. Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "<table>\n<tr><th>A</th><th align=\"right\">B</th></tr>\n<tr><td>1</td><td>2</td></tr>\n</table>";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_convert_html_tables(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "| A   | B   |\n| --- | --: |\n| 1   | 2   |\n");
+    /// ```
+    pub fn with_convert_html_tables(self, convert_html_tables: bool) -> Self {
+        Formatter {
+            convert_html_tables,
+            ..self
+        }
+    }
+
+    /// Sets how headings with the same anchor as an earlier heading are
+    /// handled. Default: `DuplicateHeadings::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{DuplicateHeadings, Formatter};
+    ///
+    /// let input = "# Usage\n\n## Install {#usage}";
+    ///
+    /// let cmfmt = Formatter::default().with_duplicate_headings(DuplicateHeadings::Deduplicate);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "# Usage\n\n## Install { #usage-1 }\n");
+    /// ```
+    pub fn with_duplicate_headings(self, duplicate_headings: DuplicateHeadings) -> Self {
+        Formatter {
+            duplicate_headings,
+            ..self
+        }
+    }
+
+    /// Sets the form that links to email addresses are written in. Default:
+    /// `EmailLinks::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{EmailLinks, Formatter};
+    ///
+    /// let input = "Mail [me@example.com](mailto:me@example.com) or [us](mailto:us@example.com).";
+    ///
+    /// let cmfmt = Formatter::default().with_email_links(EmailLinks::Autolink);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "Mail <me@example.com> or [us](mailto:us@example.com).\n");
+    ///
+    /// let cmfmt = Formatter::default().with_email_links(EmailLinks::Explicit);
+    /// let output = cmfmt.format_cmark("Mail <me@example.com>.");
+    /// assert_eq!(output, "Mail [me@example.com](mailto:me@example.com).\n");
+    /// ```
+    pub fn with_email_links(self, email_links: EmailLinks) -> Self {
+        Formatter {
+            email_links,
+            ..self
+        }
+    }
+
+    /// Sets the emphasis string. Default: "_".
+    pub fn with_emphasis(self, emphasis: &'a str) -> Self {
+        Formatter { emphasis, ..self }
+    }
+
+    /// Sets the strong emphasis string. Default: "**".
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let cmfmt = cmarkfmt::Formatter::default().with_strong("__");
+    /// let output = cmfmt.format_cmark("Some **bold** and ***bold italic*** text.");
+    /// assert_eq!(output, "Some __bold__ and ___bold italic___ text.\n");
+    /// ```
+    pub fn with_strong(self, strong: &'a str) -> Self {
+        Formatter { strong, ..self }
+    }
+
+    /// Sets whether emphasis and strong emphasis within a word are written
+    /// with `*` and `**` when their strings use underscores, which don't
+    /// delimit emphasis within words. Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "Some *emphasis*, intra*word*, and **strong**ly.";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_strong("__");
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "Some _emphasis_, intra_word_, and __strong__ly.\n");
+    ///
+    /// let cmfmt = cmfmt.with_smart_emphasis(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "Some _emphasis_, intra*word*, and **strong**ly.\n");
+    /// ```
+    pub fn with_smart_emphasis(self, smart_emphasis: bool) -> Self {
+        Formatter {
+            smart_emphasis,
+            ..self
+        }
+    }
+
+    /// Sets how links and images with an empty destination, and links with
+    /// empty text, are handled. Default: `EmptyLinks::Preserve`.
+    pub fn with_empty_links(self, empty_links: EmptyLinks) -> Self {
+        Formatter {
+            empty_links,
+            ..self
+        }
+    }
+
+    /// Sets whether the output ends with a newline. Default:
+    /// `FinalNewline::Ensure`.
+    pub fn with_final_newline(self, final_newline: FinalNewline) -> Self {
+        Formatter {
+            final_newline,
+            ..self
+        }
+    }
+
+    /// Sets the dialect of markdown that input is parsed as. Constructs from
+    /// extensions that are disabled, like tables, are formatted as plain text.
+    /// Default: `Flavor::Gfm`.
+    pub fn with_flavor(self, flavor: Flavor) -> Self {
+        Formatter { flavor, ..self }
+    }
+
+    /// Sets where top-level footnote definitions are written. Definitions
+    /// that are never referenced are moved to the end of the document unless
+    /// preserved. Default: `FootnotePlacement::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{FootnotePlacement, Formatter};
+    ///
+    /// let input = "[^1]: A note.\n\nText[^1].\n\nMore text.";
+    ///
+    /// let cmfmt = Formatter::default().with_footnote_placement(FootnotePlacement::EndOfDocument);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "Text[^1].\n\nMore text.\n\n[^1]: A note.\n");
+    /// ```
+    pub fn with_footnote_placement(self, footnote_placement: FootnotePlacement) -> Self {
+        Formatter {
+            footnote_placement,
+            ..self
+        }
+    }
+
+    /// Sets whether footnote labels are renamed to sequential numbers, in the
+    /// order of their first reference. References and definitions are renamed
+    /// alike. Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::Formatter;
+    ///
+    /// let input = "Text[^note] and more[^3].\n\n[^3]: Second.\n\n[^note]: First.";
+    ///
+    /// let cmfmt = Formatter::default().with_footnote_renumbering(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "Text[^1] and more[^2].\n\n[^2]: Second.\n\n[^1]: First.\n");
+    /// ```
+    pub fn with_footnote_renumbering(self, footnote_renumbering: bool) -> Self {
+        Formatter {
+            footnote_renumbering,
+            ..self
+        }
+    }
+
+    /// Sets how hard line breaks are written. Ignored with
+    /// `with_prettier(true)`, which keeps the style of the input. Default:
+    /// `HardBreak::Backslash`.
+    pub fn with_hard_break(self, hard_break: HardBreak) -> Self {
+        Formatter { hard_break, ..self }
+    }
+
+    /// Sets the order that the attributes of headings are written in. Default:
+    /// `HeadingAttributeOrder::IdFirst`.
+    pub fn with_heading_attribute_order(
+        self,
+        heading_attribute_order: HeadingAttributeOrder,
+    ) -> Self {
+        Formatter {
+            heading_attribute_order,
+            ..self
+        }
+    }
+
+    /// Sets how the attributes of headings are written. Default:
+    /// `HeadingAttributes::Spaced`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, HeadingAttributes};
+    ///
+    /// let cmfmt = Formatter::default().with_heading_attributes(HeadingAttributes::Compact);
+    /// let output = cmfmt.format_cmark("# Title { #title .intro }");
+    /// assert_eq!(output, "# Title {#title .intro}\n");
+    /// ```
+    pub fn with_heading_attributes(self, heading_attributes: HeadingAttributes) -> Self {
+        Formatter {
+            heading_attributes,
+            ..self
+        }
+    }
+
+    /// Sets the number of levels that headings are shifted by, e.g. 1 to write
+    /// first-level headings as second-level ones. Shifted levels are clamped
+    /// from 1 to 6. Default: 0.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::Formatter;
+    ///
+    /// let cmfmt = Formatter::default().with_heading_shift(1);
+    /// let output = cmfmt.format_cmark("# Title\n\n###### Deepest");
+    /// assert_eq!(output, "## Title\n\n###### Deepest\n");
+    /// ```
+    pub fn with_heading_shift(self, heading_shift: i8) -> Self {
+        Formatter {
+            heading_shift,
+            ..self
+        }
+    }
+
+    /// Sets how headings are written. Default: `HeadingStyle::Atx`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, HeadingStyle};
+    ///
+    /// let cmfmt = Formatter::default().with_heading_style(HeadingStyle::Setext);
+    /// let output = cmfmt.format_cmark("# Title\n## Section\n### Subsection");
+    /// assert_eq!(output, "Title\n=====\n\nSection\n-------\n\n### Subsection\n");
+    /// ```
+    pub fn with_heading_style(self, heading_style: HeadingStyle) -> Self {
+        Formatter {
+            heading_style,
+            ..self
+        }
+    }
+
+    /// Sets the width that the content of list items is indented by, from the
+    /// start of their marker, for both continuation lines and nested blocks.
+    /// Markers too wide for the width are followed by a single space. Widths
+    /// above 4 are treated as 4, as wider padding would start a code block.
+    /// `None` indents content by the width of the marker. Default: None.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "- One\n  continued\n  - Two";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_indent_width(Some(4));
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "-   One\n    continued\n    -   Two\n");
+    /// ```
+    pub fn with_indent_width(self, indent_width: Option<usize>) -> Self {
+        Formatter {
+            indent_width: indent_width.map(|width| width.min(4)),
+            ..self
+        }
+    }
+
+    /// Sets how the info strings of fenced code blocks are written. Default:
+    /// `InfoString::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, InfoString};
+    ///
+    /// let input = "```  RUST   title=main.rs\nfn main() {}\n```";
+    ///
+    /// let cmfmt = Formatter::default().with_info_string(InfoString::Normalize);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "```rust title=main.rs\nfn main() {}\n```\n");
+    /// ```
+    pub fn with_info_string(self, info_string: InfoString) -> Self {
+        Formatter {
+            info_string,
+            ..self
+        }
+    }
+
+    /// Sets whether reference links are written as inline links, with the
+    /// destination and title of their definition, and link reference
+    /// definitions are removed. Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "See [the docs][docs] and [home].\n\n[docs]: /docs \"Docs\"\n[home]: /";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_inline_links(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "See [the docs](/docs \"Docs\") and [home](/).\n");
+    /// ```
+    pub fn with_inline_links(self, inline_links: bool) -> Self {
+        Formatter {
+            inline_links,
+            ..self
+        }
+    }
+
+    /// Sets the case that the labels of full reference links and of link
+    /// reference definitions are written in. The labels of collapsed and
+    /// shortcut reference links are their text, so are written as is. Default:
+    /// `LabelCase::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, LabelCase};
+    ///
+    /// let input = "See [the docs][Docs] and [Home].\n\n[docs]: /docs\n[HOME]: /";
+    ///
+    /// let cmfmt = Formatter::default().with_label_case(LabelCase::Lowercase);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "See [the docs][docs] and [Home].\n\n[docs]: /docs\n[home]: /\n");
+    /// ```
+    pub fn with_label_case(self, label_case: LabelCase) -> Self {
+        Formatter { label_case, ..self }
+    }
+
+    /// Sets whether lazy continuation lines of paragraphs in blockquotes,
+    /// which don't start with a `>` marker in the input, are kept lazy rather
+    /// than prefixed with blockquote markers. Ignored when the text is
+    /// reflowed or joined. Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "> A quote\nthat continues.\n> More.";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_lazy_continuation(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "> A quote\nthat continues.\n> More.\n");
+    /// ```
+    pub fn with_lazy_continuation(self, lazy_continuation: bool) -> Self {
+        Formatter {
+            lazy_continuation,
+            ..self
+        }
+    }
+
+    /// Sets how soft line breaks within paragraphs and list items are
+    /// written. Ignored when a line width is set, as the text is then
+    /// reflowed. Default: `LineBreakStyle::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, LineBreakStyle};
+    ///
+    /// let cmfmt = Formatter::default().with_line_break_style(LineBreakStyle::Join);
+    /// let output = cmfmt.format_cmark("- One\n  paragraph,\n  one line.");
+    /// assert_eq!(output, "- One paragraph, one line.\n");
+    /// ```
+    pub fn with_line_break_style(self, line_break_style: LineBreakStyle) -> Self {
+        Formatter {
+            line_break_style,
+            ..self
+        }
+    }
+
+    /// Restricts formatting to the top-level blocks overlapping any of the
+    /// 1-based, inclusive line ranges, like the lines changed in a diff. Other
+    /// blocks, and the blank lines around them, are copied through verbatim.
+    /// Default: None.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "# Title\nSome *text*.\n\nMore *text*.\n";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_lines(Some(&[4..=4]));
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "# Title\nSome *text*.\n\nMore _text_.\n");
+    /// ```
+    pub fn with_lines(self, lines: Option<&'a [RangeInclusive<usize>]>) -> Self {
+        Formatter { lines, ..self }
+    }
+
+    /// Sets the directory that relative link and image destinations are
+    /// resolved against, like that of the file being formatted. When set,
+    /// destinations that don't exist, and fragments that aren't the anchor of
+    /// a heading in the markdown file they point to, are reported by
+    /// `Formatter::lint`. Default: None.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// use cmarkfmt::{Formatter, Rule};
+    ///
+    /// let input = "# Usage\n\nSee [usage](#usage), [the license](LICENSE) and [setup](#setup).";
+    ///
+    /// let cmfmt = Formatter::default().with_link_base(Some(Path::new(".")));
+    /// let diagnostics = cmfmt.lint(input);
+    /// assert_eq!(diagnostics.len(), 1);
+    /// assert_eq!(diagnostics[0].rule, Rule::BrokenLink);
+    /// assert_eq!(diagnostics[0].message, "heading `#setup` doesn't exist");
+    /// ```
+    pub fn with_link_base(self, link_base: Option<&'a Path>) -> Self {
+        Formatter { link_base, ..self }
+    }
+
+    /// Sets the line ending that output lines are written with. Input may mix
+    /// line endings. Default: `LineEnding::Lf`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, LineEnding};
+    ///
+    /// let cmfmt = Formatter::default().with_line_ending(LineEnding::Preserve);
+    /// let output = cmfmt.format_cmark("# Title\r\n\nText.\r\n");
+    /// assert_eq!(output, "# Title\r\n\r\nText.\r\n");
+    /// ```
+    pub fn with_line_ending(self, line_ending: LineEnding) -> Self {
+        Formatter {
+            line_ending,
+            ..self
+        }
+    }
+
+    /// Sets the maximum width, in characters, that the text of paragraphs and
+    /// list items is reflowed to, including the blockquote and list markers
+    /// before it. Lines are only broken between words, outside of code spans
+    /// and inline HTML, and never before a word that would start a new block.
+    /// Words longer than the width are left on their own line. Headings, code
+    /// blocks, and tables are never reflowed. `None` keeps the line breaks of
+    /// the input. Default: None.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "> Some text that is reflowed\n> to fit.";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_line_width(Some(16));
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "> Some text that\n> is reflowed to\n> fit.\n");
+    /// ```
+    pub fn with_line_width(self, line_width: Option<usize>) -> Self {
+        Formatter { line_width, ..self }
+    }
+
+    /// Sets how the markers of ordered list items are aligned, so that the
+    /// text of every item in a list starts at the same column, computed from
+    /// the widest marker. Markers are padded by at most 3 spaces. Default:
+    /// `ListMarkerAlignment::None`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, ListMarkerAlignment, OrderedListNumbering};
+    ///
+    /// let input = "9. Nine\n   more\n10. Ten";
+    ///
+    /// let cmfmt = Formatter::default()
+    ///     .with_ordered_list_numbering(OrderedListNumbering::Preserve)
+    ///     .with_list_marker_alignment(ListMarkerAlignment::Right);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, " 9. Nine\n    more\n10. Ten\n");
+    /// ```
+    pub fn with_list_marker_alignment(self, alignment: ListMarkerAlignment) -> Self {
+        Formatter {
+            list_marker_alignment: alignment,
+            ..self
+        }
+    }
+
+    /// Sets whether list items are separated by blank lines. Items of tight
+    /// lists ending in a blockquote or code block are still followed by a
+    /// blank line, unless disabled with
+    /// `Formatter::with_blank_line_after_block_items`. Default:
+    /// `ListSpacing::Tight`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, ListSpacing};
+    ///
+    /// let input = "- One\n- Two";
+    ///
+    /// let cmfmt = Formatter::default().with_list_spacing(ListSpacing::Loose);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "- One\n\n- Two\n");
+    /// ```
+    pub fn with_list_spacing(self, list_spacing: ListSpacing) -> Self {
+        Formatter {
+            list_spacing,
+            ..self
+        }
+    }
+
+    /// Sets the maximum depth that blocks can be nested to, counting every
+    /// blockquote, list, and footnote definition containing them, for
+    /// formatting untrusted input. Formatting more deeply nested blocks fails
+    /// with `FormatError::MaxDepth`. Default: None.
+    pub fn with_max_depth(self, max_depth: Option<usize>) -> Self {
+        Formatter { max_depth, ..self }
+    }
+
+    /// Sets the maximum length, in characters, of the destinations of inline
+    /// links. Links with longer destinations are written as reference links,
+    /// using a definition with the same destination and title if there is
+    /// one, or adding one labelled by the link text otherwise. Default: None.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "Read [the guide](https://example.com/a/long/path) or [this](/short).";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_max_inline_link_length(Some(20));
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(
+    ///     output,
+    ///     "Read [the guide][the-guide] or [this](/short).\n\n\
+    ///      [the-guide]: https://example.com/a/long/path\n"
+    /// );
+    /// ```
+    pub fn with_max_inline_link_length(self, max_inline_link_length: Option<usize>) -> Self {
+        Formatter {
+            max_inline_link_length,
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of consecutive blank lines kept from the input
+    /// between top-level blocks. Blocks are always separated by at least one
+    /// blank line, and `usize::MAX` keeps every blank line. Default: 1.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "# Title\n\n\n\nText.\n\n\nMore text.";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_max_blank_lines(2);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "# Title\n\n\nText.\n\n\nMore text.\n");
+    /// ```
+    pub fn with_max_blank_lines(self, max_blank_lines: usize) -> Self {
+        Formatter {
+            max_blank_lines,
+            ..self
+        }
+    }
+
+    /// Sets the delimiter written after the numbers of ordered list items.
+    /// Unless preserved, adjacent lists alternate between `.` and `)`, as
+    /// they would otherwise be merged into a single list. Default:
+    /// `OrderedListDelimiter::Period`.
+    pub fn with_ordered_list_delimiter(self, delimiter: OrderedListDelimiter) -> Self {
+        Formatter {
+            ordered_list_delimiter: delimiter,
+            ..self
+        }
+    }
+
+    /// Sets how the items of ordered lists are numbered. Ignored with
+    /// `with_prettier(true)`, which numbers them like Prettier. Default:
+    /// `OrderedListNumbering::Start`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, OrderedListNumbering};
+    ///
+    /// let input = "3. Three\n3. Four\n7. Five";
+    ///
+    /// let cmfmt = Formatter::default().with_ordered_list_numbering(OrderedListNumbering::Sequential);
+    /// assert_eq!(cmfmt.format_cmark(input), "3. Three\n4. Four\n5. Five\n");
+    ///
+    /// let cmfmt = Formatter::default().with_ordered_list_numbering(OrderedListNumbering::AllOnes);
+    /// assert_eq!(cmfmt.format_cmark(input), "3. Three\n1. Four\n1. Five\n");
+    /// ```
+    pub fn with_ordered_list_numbering(self, numbering: OrderedListNumbering) -> Self {
+        Formatter {
+            ordered_list_numbering: numbering,
+            ..self
+        }
+    }
+
+    /// Sets whether output matches Prettier's markdown formatter: ordered
+    /// lists are numbered incrementally from their start, unless the second
+    /// item is numbered 1, in which case every item after the first is too.
+    /// Adjacent lists alternate between the unordered list string and `*` (or
+    /// `-`), and between `.` and `)` for ordered lists. Table cells are padded
+    /// according to the alignment of their column, and hard breaks keep the
+    /// style of the input. Default: false.
+    pub fn with_prettier(self, prettier: bool) -> Self {
+        Formatter { prettier, ..self }
+    }
+
+    /// Sets whether link reference definitions that no link uses are removed,
+    /// along with those with the same destination and title as an
+    /// earlier definition, whose links are rewritten to use the earlier one.
+    /// Duplicates used by collapsed or shortcut links are kept. Each removal
+    /// is reported by `Formatter::lint`. Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "See [a] and [b][bee].\n\n[a]: /x\n[bee]: /x\n[c]: /c";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_prune_refdefs(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "See [a] and [b][a].\n\n[a]: /x\n");
+    /// ```
+    pub fn with_prune_refdefs(self, prune_refdefs: bool) -> Self {
+        Formatter {
+            prune_refdefs,
+            ..self
+        }
+    }
+
+    /// Sets how body rows of tables with more cells than the header are
+    /// written. Rows with more or fewer cells are reported when linting.
+    /// Default: `RaggedRows::Truncate`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, RaggedRows};
+    ///
+    /// let input = "|a|b|\n|-|-|\n|1|2|3|\n|4|";
+    ///
+    /// let cmfmt = Formatter::default().with_ragged_rows(RaggedRows::Extend);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(
+    ///     output,
+    ///     "| a   | b   |     |\n| --- | --- | --- |\n| 1   | 2   | 3   |\n| 4   |     |     |\n"
+    /// );
+    /// ```
+    pub fn with_ragged_rows(self, ragged_rows: RaggedRows) -> Self {
+        Formatter {
+            ragged_rows,
+            ..self
+        }
+    }
+
+    /// Sets where and in which order link reference definitions are written.
+    /// Default: `RefDefPolicy::SortedAtEnd`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, RefDefPolicy};
+    ///
+    /// let input = "# One\n\nSee [b].\n\n[b]: /b\n\n# Two\n\nSee [a].\n\n[a]: /a";
+    ///
+    /// let cmfmt = Formatter::default().with_refdef_policy(RefDefPolicy::KeepInPlace);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "# One\n\nSee [b].\n\n[b]: /b\n\n# Two\n\nSee [a].\n\n[a]: /a\n");
+    ///
+    /// let input = "# One\n\nSee [b].\n\n# Two\n\nSee [a].\n\n[a]: /a\n[b]: /b";
+    ///
+    /// let cmfmt = Formatter::default().with_refdef_policy(RefDefPolicy::AfterFirstUse);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "# One\n\nSee [b].\n\n[b]: /b\n\n# Two\n\nSee [a].\n\n[a]: /a\n");
+    /// ```
+    pub fn with_refdef_policy(self, refdef_policy: RefDefPolicy) -> Self {
+        Formatter {
+            refdef_policy,
+            ..self
+        }
+    }
+
+    /// Sets the form that reference links are written in. Collapsed and
+    /// shortcut links are only written where their text matches their label,
+    /// and shortcut links where they aren't followed by `(`, `[` or `:`.
+    /// Default: `ReferenceLinks::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, ReferenceLinks};
+    ///
+    /// let input = "See [docs][docs], [Docs][], and [the guide][guide].\n\n[docs]: /d\n[guide]: /g";
+    ///
+    /// let cmfmt = Formatter::default().with_reference_links(ReferenceLinks::Shortcut);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(
+    ///     output,
+    ///     "See [docs], [Docs], and [the guide][guide].\n\n[docs]: /d\n[guide]: /g\n"
+    /// );
+    /// ```
+    pub fn with_reference_links(self, reference_links: ReferenceLinks) -> Self {
+        Formatter {
+            reference_links,
+            ..self
+        }
+    }
+
+    /// Sets whether images without alt text, and without alt text supplied by
+    /// the alt text function, are reported by `Formatter::lint`. Default:
+    /// false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, Rule};
+    ///
+    /// let cmfmt = Formatter::default().with_require_alt_text(true);
+    /// let diagnostics = cmfmt.lint("![Logo](logo.png) and ![](photo.jpg)");
+    /// assert_eq!(diagnostics.len(), 1);
+    /// assert_eq!(diagnostics[0].rule, Rule::MissingAltText);
+    /// assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 23));
+    /// ```
+    pub fn with_require_alt_text(self, require_alt_text: bool) -> Self {
+        Formatter {
+            require_alt_text,
+            ..self
+        }
+    }
+
+    /// Sets whether tables are kept as they are in the input, unless a row
+    /// has more or fewer cells than the header, so that reformatting doesn't
+    /// churn hand-aligned tables. Default: false.
+    ///
+    /// A `<!-- cmarkfmt: table=preserve -->` comment before a table keeps it
+    /// regardless, while `table=padded` formats it, and `table=compact` formats
+    /// it without padding its cells. A `no-align` setting in the comment pads
+    /// cells on the right, rather than by the alignment of their column.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "|a|bb|\n|-|--|\n|1|2|\n\n|a|b|\n|-|-|\n|1|\n";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_preserve_tables(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(
+    ///     output,
+    ///     "|a|bb|\n|-|--|\n|1|2|\n\n| a   | b   |\n| --- | --- |\n| 1   |     |\n"
+    /// );
+    /// ```
+    pub fn with_preserve_tables(self, preserve_tables: bool) -> Self {
+        Formatter {
+            preserve_tables,
+            ..self
+        }
+    }
+
+    /// Sets the width of tab stops that tabs are expanded to with
+    /// `Tabs::Expand`. Default: 4.
+    pub fn with_tab_width(self, tab_width: usize) -> Self {
+        Formatter { tab_width, ..self }
+    }
+
+    /// Sets the maximum width that table columns are padded to. Cells wider
+    /// than it aren't padded, so that one long cell doesn't widen every row.
+    /// `None` pads every cell to the widest in its column. Default: None.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "|a|b|\n|-|-|\n|1|A long cell|";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_table_cell_width(Some(5));
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "| a   | b     |\n| --- | ----- |\n| 1   | A long cell |\n");
+    /// ```
+    pub fn with_table_cell_width(self, table_cell_width: Option<usize>) -> Self {
+        Formatter {
+            table_cell_width,
+            ..self
+        }
+    }
+
+    /// Sets whether table cells wider than the maximum cell width are wrapped
+    /// with `<br>` tags. Cells are only broken between words, outside of code
+    /// spans, inline HTML, links and emphasis, so an element wider than the
+    /// maximum is kept on a line of its own. Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "|a|b|\n|-|-|\n|1|A long cell|";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default()
+    ///     .with_table_cell_width(Some(6))
+    ///     .with_table_cell_wrap(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "| a   | b      |\n| --- | ------ |\n| 1   | A long<br>cell |\n");
+    /// ```
+    pub fn with_table_cell_wrap(self, table_cell_wrap: bool) -> Self {
+        Formatter {
+            table_cell_wrap,
+            ..self
+        }
+    }
+
+    /// Sets the tag that line breaks within table cells, like `<br>` and
+    /// `<br/>`, are written with. Default: `TableLineBreak::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, TableLineBreak};
+    ///
+    /// let input = "|a|b|\n|-|-|\n|1|one<br/>two|";
+    ///
+    /// let cmfmt = Formatter::default().with_table_line_break(TableLineBreak::Br);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "| a   | b          |\n| --- | ---------- |\n| 1   | one<br>two |\n");
+    /// ```
+    pub fn with_table_line_break(self, table_line_break: TableLineBreak) -> Self {
+        Formatter {
+            table_line_break,
+            ..self
+        }
+    }
+
+    /// Sets whether table columns are padded by the width of the text of their
+    /// cells as rendered, without inline markup like emphasis markers, code
+    /// span backticks, and escapes, so that the text of the cells lines up.
+    /// Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "|a|b|\n|-|-|\n|**bold**|`code`|\n|plain|text|";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_table_rendered_width(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(
+    ///     output,
+    ///     "| a     | b    |\n| ----- | ---- |\n| **bold**  | `code` |\n| plain | text |\n"
+    /// );
+    /// ```
+    pub fn with_table_rendered_width(self, table_rendered_width: bool) -> Self {
+        Formatter {
+            table_rendered_width,
+            ..self
+        }
+    }
+
+    /// Sets whether the rows of tables are written as they're formatted,
+    /// instead of once the whole table is, so that the memory used doesn't
+    /// grow with the size of tables. Columns are as wide as their header cell,
+    /// or the maximum cell width if wider, and wider cells aren't padded.
+    /// Streamed tables are always written with outer pipes, and aren't
+    /// preserved, extended, sorted, or aligned by their content. Default:
+    /// false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "|a|b|\n|-|:-:|\n|1|2|\n|333|4|";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_table_streaming(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "| a   | b   |\n| --- | :-: |\n| 1   | 2   |\n| 333 | 4   |\n");
+    /// ```
+    pub fn with_table_streaming(self, table_streaming: bool) -> Self {
+        Formatter {
+            table_streaming,
+            ..self
+        }
+    }
+
+    /// Sets whether the rows of tables are written with leading and trailing
+    /// pipes. Tables with a single column, or a row whose first or last cell
+    /// is empty or would start a new block, are always written with them.
+    /// Default: `TableStyle::Piped`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, TableStyle};
+    ///
+    /// let input = "| a | b |\n|:-|-|\n| 1 | 2 |";
+    ///
+    /// let cmfmt = Formatter::default().with_table_style(TableStyle::Unpiped);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "a   | b\n:-- | ---\n1   | 2\n");
+    /// ```
+    pub fn with_table_style(self, table_style: TableStyle) -> Self {
+        Formatter {
+            table_style,
+            ..self
+        }
+    }
+
+    /// Sets how the width of table cells is measured when padding their
+    /// columns. Default: `WidthMode::Chars`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// # #[cfg(feature = "unicode-width")]
+    /// # {
+    /// use cmarkfmt::{Formatter, WidthMode};
+    ///
+    /// let input = "| 名前 | a |\n| - | - |";
+    ///
+    /// let cmfmt = Formatter::default().with_table_width_mode(WidthMode::Display);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "| 名前 | a   |\n| ---- | --- |\n");
+    /// # }
+    /// ```
+    pub fn with_table_width_mode(self, table_width_mode: WidthMode) -> Self {
+        Formatter {
+            table_width_mode,
+            ..self
+        }
+    }
+
+    /// Sets how tab characters in text outside of code are handled. Tabs in
+    /// code are kept. Default: `Tabs::Preserve`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, Tabs};
+    ///
+    /// let cmfmt = Formatter::default().with_tabs(Tabs::Expand);
+    /// let output = cmfmt.format_cmark("a\tb\tc `\t`");
+    /// assert_eq!(output, "a   b   c `\t`\n");
+    /// ```
+    pub fn with_tabs(self, tabs: Tabs) -> Self {
+        Formatter { tabs, ..self }
+    }
+
+    /// Sets the character written in the markers of checked task list items,
+    /// like `[x]`. `None` keeps the character of the input, e.g. `[X]`.
+    /// Default: `Some('x')`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "- [X] Done\n- [ ] To do";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default();
+    /// assert_eq!(cmfmt.format_cmark(input), "- [x] Done\n- [ ] To do\n");
+    ///
+    /// let cmfmt = cmfmt.with_task_checked(None);
+    /// assert_eq!(cmfmt.format_cmark(input), "- [X] Done\n- [ ] To do\n");
+    /// ```
+    pub fn with_task_checked(self, task_checked: Option<char>) -> Self {
+        Formatter {
+            task_checked,
+            ..self
+        }
+    }
+
+    /// Sets the delimiters that the titles of links, images, and link
+    /// reference definitions are written with. Delimiters within titles are
+    /// escaped. Default: `TitleQuote::Double`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, TitleQuote};
+    ///
+    /// let input = r#"See [the docs](/docs "The 'best' docs")."#;
+    ///
+    /// let cmfmt = Formatter::default().with_title_quote(TitleQuote::Single);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "See [the docs](/docs 'The \\'best\\' docs').\n");
+    /// ```
+    pub fn with_title_quote(self, title_quote: TitleQuote) -> Self {
+        Formatter {
+            title_quote,
+            ..self
+        }
+    }
+
+    /// Sets where trailing whitespace is trimmed from the ends of lines. It's
+    /// never trimmed from verbatim regions. Default: `TrailingWhitespace::Trim`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{Formatter, TrailingWhitespace};
+    ///
+    /// let input = "<pre>  \nkept  \n</pre>\n";
+    ///
+    /// let cmfmt = Formatter::default();
+    /// assert_eq!(cmfmt.format_cmark(input), "<pre>\nkept\n</pre>\n");
+    ///
+    /// let cmfmt = cmfmt.with_trailing_whitespace(TrailingWhitespace::KeepInBlocks);
+    /// assert_eq!(cmfmt.format_cmark(input), "<pre>  \nkept  \n</pre>\n");
+    /// ```
+    pub fn with_trailing_whitespace(self, trailing_whitespace: TrailingWhitespace) -> Self {
+        Formatter {
+            trailing_whitespace,
+            ..self
+        }
+    }
+
+    /// Sets the unordered list string. Default: "-".
+    pub fn with_unordered_list(self, unordered_list: &'a str) -> Self {
+        Formatter {
+            unordered_list,
+            ..self
+        }
+    }
+
+    /// Sets whether README decorations are copied through verbatim: HTML
+    /// blocks with an `align` attribute (up to their closing tag), paragraphs
+    /// made up only of images, and tables containing badge images. Default:
+    /// false.
+    pub fn with_verbatim_badges(self, verbatim_badges: bool) -> Self {
+        Formatter {
+            verbatim_badges,
+            ..self
+        }
+    }
+
+    /// Sets whether wikilinks, like `[[Page Name]]` and `[[Page|alias]]`, are
+    /// written without escaping their brackets, and kept on one line when
+    /// wrapping. Default: false.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// let input = "See [[Page Name]] and [[Page|alias]].";
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default();
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "See \\[\\[Page Name\\]\\] and \\[\\[Page|alias\\]\\].\n");
+    ///
+    /// let cmfmt = cmarkfmt::Formatter::default().with_wikilinks(true);
+    /// let output = cmfmt.format_cmark(input);
+    /// assert_eq!(output, "See [[Page Name]] and [[Page|alias]].\n");
+    /// ```
+    pub fn with_wikilinks(self, wikilinks: bool) -> Self {
+        Formatter { wikilinks, ..self }
+    }
+}
+
+fn parser_options() -> POptions {
+    let mut opts = POptions::all();
+    opts.remove(POptions::ENABLE_SMART_PUNCTUATION);
+    opts
+}
+
+const STRIKETHROUGH: &str = "~~";
+
+enum StackItem {
+    // Whether the blockquote directly follows the marker of its parent in the
+    // input.
+    Blockquote(bool),
+    CodeIndent,
+    // The label of a footnote definition, and whether it has been written.
+    Footnote(String, bool),
+    List(ListMarker, bool, bool),
+}
+
+// The marker of the current item of a list.
+#[derive(Clone)]
+struct ListMarker {
+    // The marker as written, e.g. "-" or "1.", including any padding.
+    text: String,
+    // The width of the widest marker of an ordered list, when aligned.
+    width: usize,
+    // The start number and delimiter of an ordered list.
+    ordered: Option<(u64, char)>,
+    // The index of this list among adjacent lists of the same kind.
+    sibling: usize,
+    // The number of items started so far.
+    items: u64,
+    // Whether every item after the first is numbered 1.
+    ones: bool,
+    // Whether the current item starts with an indented code block, so its
+    // content must start a single space after the marker.
+    narrow: bool,
+    // Whether a paragraph has ended directly in an item, so the list is loose.
+    loose: bool,
+    // Whether a block has ended in the current item, so that its later lines
+    // are continuation lines.
+    continued: bool,
+}
+
+impl ListMarker {
+    // Creates the marker of a list nested in `depth` others, given the
+    // delimiter it has in the input and the numbers of its items in the
+    // input, if ordered.
+    fn new(
+        start: Option<u64>,
+        depth: usize,
+        sibling: usize,
+        delimiter: Option<char>,
+        numbers: &[Option<u64>],
+        opts: &Options,
+    ) -> Self {
+        // Adjacent lists would otherwise be merged into a single list.
+        let alternate = sibling % 2 == 1;
+        let (text, ordered) = match start {
+            Some(start) => {
+                let delimiter = match opts.ordered_list_delimiter {
+                    OrderedListDelimiter::Preserve => delimiter.unwrap_or('.'),
+                    OrderedListDelimiter::Period if alternate => ')',
+                    OrderedListDelimiter::Period => '.',
+                    OrderedListDelimiter::Paren if alternate => '.',
+                    OrderedListDelimiter::Paren => ')',
+                };
+                (format!("{start}{delimiter}"), Some((start, delimiter)))
+            }
+            None if opts.bullet_style == BulletStyle::Preserve => (
+                delimiter.map_or_else(|| opts.unordered_list_str.to_string(), String::from),
+                None,
+            ),
+            None if opts.bullet_style == BulletStyle::Depth => {
+                const BULLETS: [&str; 3] = ["-", "*", "+"];
+                let first = BULLETS
+                    .iter()
+                    .position(|&bullet| bullet == opts.unordered_list_str)
+                    .unwrap_or(0);
+                (BULLETS[(first + depth) % BULLETS.len()].to_string(), None)
+            }
+            None if opts.prettier && alternate && opts.unordered_list_str == "*" => {
+                ("-".to_string(), None)
+            }
+            None if opts.prettier && alternate => ("*".to_string(), None),
+            None => (opts.unordered_list_str.to_string(), None),
+        };
+        let mut marker = ListMarker {
+            text,
+            width: 0,
+            ordered,
+            sibling,
+            items: 0,
+            ones: false,
+            narrow: false,
+            loose: false,
+            continued: false,
+        };
+        if opts.list_marker_alignment != ListMarkerAlignment::None {
+            let mut widest = marker.clone();
+            for &number in numbers {
+                widest.next_item(number, opts);
+                marker.width = marker.width.max(widest.text.len());
+            }
+        }
+        marker
+    }
+
+    // Returns the width of the marker and the spaces after it.
+    fn indent(&self, opts: &Options) -> usize {
+        let width = self.text.chars().count() + 1;
+        if self.narrow {
+            width
+        } else {
+            width.max(opts.indent_width)
+        }
+    }
+
+    // Returns the indentation of the lines of an item after the first.
+    fn continuation(&self, opts: &Options) -> usize {
+        let indent = self.indent(opts);
+        match opts.continuation_indent {
+            ContinuationIndent::Marker => indent,
+            ContinuationIndent::Four => indent.max(4),
+        }
+    }
+
+    // Numbers the next item of an ordered list, given the number it has in
+    // the input. With Prettier, lists are numbered incrementally, unless the
+    // items after the first are numbered 1 (or, when starting at 0, the second
+    // and third are).
+    fn next_item(&mut self, number: Option<u64>, opts: &Options) {
+        let index = self.items;
+        self.items += 1;
+        let Some((start, delimiter)) = self.ordered else {
+            return;
+        };
+        if opts.prettier {
+            match index {
+                1 => self.ones = number == Some(1),
+                2 if start == 0 && self.ones => self.ones = number == Some(1),
+                _ => {}
+            }
+        }
+        let n = match (index, opts.ordered_list_numbering) {
+            (0, _) => start,
+            _ if opts.prettier && self.ones => 1,
+            _ if opts.prettier => start + index,
+            (_, OrderedListNumbering::Start) => start,
+            (_, OrderedListNumbering::Sequential) => start + index,
+            (_, OrderedListNumbering::AllOnes) => 1,
+            (_, OrderedListNumbering::Preserve) => number.unwrap_or(start + index),
+        };
+        self.text = format!("{n}{delimiter}");
+        let padding = " ".repeat(self.width.saturating_sub(self.text.len()).min(3));
+        match opts.list_marker_alignment {
+            ListMarkerAlignment::None => {}
+            ListMarkerAlignment::Left => self.text.push_str(&padding),
+            ListMarkerAlignment::Right => self.text.insert_str(0, &padding),
+        }
+    }
+}
+
+struct Options<'a> {
+    code_fmt: &'a Option<CodeFormatFn<'a>>,
+    heading_transform: Option<HeadingTransformFn<'a>>,
+    table_cell_fmt: Option<TableCellFormatFn<'a>>,
+    table_sort: Option<TableSortFn<'a>>,
+    link_rewriter: Option<LinkRewriteFn<'a>>,
+    image_rewriter: Option<ImageRewriteFn<'a>>,
+    alt_text: Option<AltTextFn<'a>>,
+    alerts: bool,
+    align_numeric_columns: bool,
+    blank_line_after_block_items: bool,
+    blank_lines_after_headings: usize,
+    blank_lines_before_headings: usize,
+    blockquote_join: BlockquoteJoin,
+    blockquote_nesting: BlockquoteNesting,
+    blockquote_spacing: usize,
     blockquote_str: &'a str,
+    bold_headings: Option<HeadingLevel>,
+    bold_italic: BoldItalic,
+    bullet_style: BulletStyle,
+    code_fence: FenceChar,
+    continuation_indent: ContinuationIndent,
+    duplicate_headings: DuplicateHeadings,
     emphasis_str: &'a str,
+    empty_links: EmptyLinks,
+    final_newline: FinalNewline,
+    hard_break: HardBreak,
+    heading_attribute_order: HeadingAttributeOrder,
+    heading_attributes: HeadingAttributes,
+    heading_shift: i8,
+    heading_style: HeadingStyle,
+    indent_width: usize,
+    info_string: InfoString,
+    inline_links: bool,
+    label_case: LabelCase,
+    lazy_continuation: bool,
+    line_break_style: LineBreakStyle,
+    line_ending: &'static str,
+    line_width: Option<usize>,
+    list_marker_alignment: ListMarkerAlignment,
+    list_spacing: ListSpacing,
+    max_blank_lines: usize,
+    max_depth: Option<usize>,
+    max_inline_link_length: Option<usize>,
+    ordered_list_delimiter: OrderedListDelimiter,
+    ordered_list_numbering: OrderedListNumbering,
+    preserve_tables: bool,
+    prettier: bool,
+    ragged_rows: RaggedRows,
+    refdef_policy: RefDefPolicy,
+    reference_links: ReferenceLinks,
+    require_alt_text: bool,
+    smart_emphasis: bool,
+    strong_str: &'a str,
+    tab_width: usize,
+    table_cell_width: Option<usize>,
+    table_cell_wrap: bool,
+    table_line_break: TableLineBreak,
+    table_rendered_width: bool,
+    table_streaming: bool,
+    table_style: TableStyle,
+    table_width_mode: WidthMode,
+    tabs: Tabs,
+    task_checked: Option<char>,
+    title_quote: TitleQuote,
+    trailing_whitespace: TrailingWhitespace,
     unordered_list_str: &'a str,
+    wikilinks: bool,
 }
 
-impl<'a> From<&'a Formatter<'a>> for Options<'a> {
-    fn from(v: &'a Formatter<'a>) -> Self {
+impl<'a> Options<'a> {
+    fn new(v: &'a Formatter<'a>, input: &str) -> Self {
         Options {
             code_fmt: &v.code_fmt,
-            blockquote_str: v.blockquote,
+            heading_transform: v.heading_transform,
+            table_cell_fmt: v.table_cell_fmt,
+            table_sort: v.table_sort,
+            link_rewriter: v.link_rewriter,
+            image_rewriter: v.image_rewriter,
+            alt_text: v.alt_text,
+            alerts: v.alerts,
+            align_numeric_columns: v.align_numeric_columns,
+            blank_line_after_block_items: v.blank_line_after_block_items,
+            blank_lines_after_headings: v.blank_lines_after_headings,
+            blank_lines_before_headings: v.blank_lines_before_headings,
+            blockquote_join: v.blockquote_join,
+            blockquote_nesting: v.blockquote_nesting,
+            blockquote_spacing: v.blockquote_spacing,
+            blockquote_str: v.blockquote.trim_end(),
+            bold_headings: v.bold_headings,
+            bold_italic: v.bold_italic,
+            bullet_style: v.bullet_style,
+            code_fence: v.code_fence,
+            continuation_indent: v.continuation_indent,
+            duplicate_headings: v.duplicate_headings,
             emphasis_str: v.emphasis,
+            empty_links: v.empty_links,
+            final_newline: v.final_newline,
+            hard_break: v.hard_break,
+            heading_attribute_order: v.heading_attribute_order,
+            heading_attributes: v.heading_attributes,
+            heading_shift: v.heading_shift,
+            heading_style: v.heading_style,
+            indent_width: v.indent_width.unwrap_or(0),
+            info_string: v.info_string,
+            inline_links: v.inline_links,
+            label_case: v.label_case,
+            lazy_continuation: v.lazy_continuation,
+            line_break_style: v.line_break_style,
+            line_ending: v.line_ending.as_str(input),
+            line_width: v.line_width,
+            list_marker_alignment: v.list_marker_alignment,
+            list_spacing: v.list_spacing,
+            max_blank_lines: v.max_blank_lines,
+            max_depth: v.max_depth,
+            max_inline_link_length: v.max_inline_link_length,
+            ordered_list_delimiter: v.ordered_list_delimiter,
+            ordered_list_numbering: v.ordered_list_numbering,
+            preserve_tables: v.preserve_tables,
+            prettier: v.prettier,
+            ragged_rows: v.ragged_rows,
+            refdef_policy: v.refdef_policy,
+            reference_links: v.reference_links,
+            require_alt_text: v.require_alt_text,
+            smart_emphasis: v.smart_emphasis,
+            strong_str: v.strong,
+            tab_width: v.tab_width.max(1),
+            table_cell_width: v.table_cell_width.map(|width| width.max(3)),
+            table_cell_wrap: v.table_cell_wrap,
+            table_line_break: v.table_line_break,
+            table_rendered_width: v.table_rendered_width,
+            table_streaming: v.table_streaming,
+            table_style: v.table_style,
+            table_width_mode: v.table_width_mode,
+            tabs: v.tabs,
+            task_checked: v.task_checked,
+            title_quote: v.title_quote,
+            trailing_whitespace: v.trailing_whitespace,
             unordered_list_str: v.unordered_list,
+            wikilinks: v.wikilinks,
         }
     }
 }
 
 struct Context<'a, W: fmt::Write> {
     writer: W,
+    input: &'a str,
+    range: Range<usize>,
+    diagnostics: Vec<Diagnostic>,
     refdefs: Vec<Reference>,
+    // The index of the next reference definition to write, when they're kept
+    // in place.
+    next_refdef: usize,
+    // The normalized labels of removed duplicate reference definitions, mapped
+    // to the label of the definition that's kept.
+    refdef_aliases: HashMap<String, String>,
+    // The indices of the reference definitions first used within the current
+    // top-level block, when they follow their first use.
+    used_refdefs: Vec<usize>,
     opts: Options<'a>,
     table: Option<Table>,
     stack: Vec<StackItem>,
     text_buf: String,
     scratch: String,
     newline_required: bool,
+    // The number of blank lines written when a newline is required, if not
+    // one.
+    blank_lines: Option<usize>,
     code_block: Option<Option<String>>,
     last_line_blank: bool,
+    // Whether the newline ending the last line written is yet to be written,
+    // as it depends on whether it is the final line.
+    newline_pending: bool,
+    // Whether the next line is a lazy continuation line, written without
+    // blockquote markers.
+    lazy_line: bool,
+    links: Vec<usize>,
+    // The depth of brackets within an inline footnote, like `^[note]`.
+    inline_notes: usize,
+    // The stack depth, orderedness, and sibling index of the list that was
+    // just closed, if no other block followed it.
+    prev_list: Option<(usize, bool, usize)>,
+    // The numbers of the items of every ordered list in the input, by the
+    // offset of the list, when markers are aligned.
+    list_items: HashMap<usize, Vec<Option<u64>>>,
+    // Whether the buffered text is reflowed to the line width when written.
+    wrapping: bool,
+    // The ranges of the buffered text that can't be broken across lines.
+    unbreakable: Vec<Range<usize>>,
+    // The offsets in the buffered text of the emphasis and links being written
+    // in a table cell, which isn't wrapped within them.
+    cell_spans: Vec<usize>,
+    // The plain text of the heading being written.
+    heading_text: Option<String>,
+    // The directive in the HTML block just before a table.
+    table_directive: Option<TableDirective>,
+    // The anchors of the headings written so far.
+    anchors: HashSet<String>,
+    // The ranges of the wikilinks in the input, when they're kept.
+    wikilinks: Vec<Range<usize>>,
+    // The offset in the buffered text of the wikilink being written.
+    wikilink_start: Option<usize>,
+    // Whether formatting stopped at blocks nested deeper than the maximum.
+    too_deep: bool,
+    // The offset in the input of the text following a removed empty link,
+    // whose leading spaces are dropped so that no double space is left.
+    removed_link_end: Option<usize>,
 }
 
 impl<'a, W: fmt::Write> Context<'a, W> {
-    fn new(writer: W, refdefs: Vec<Reference>, opts: Options<'a>) -> Self {
+    fn new(writer: W, input: &'a str, refdefs: Vec<Reference>, opts: Options<'a>) -> Self {
+        let wikilinks = if opts.wikilinks {
+            wikilinks(input)
+        } else {
+            Vec::new()
+        };
         Context {
             writer,
+            input,
+            range: 0..0,
+            diagnostics: Vec::new(),
             refdefs,
+            next_refdef: 0,
+            refdef_aliases: HashMap::new(),
+            used_refdefs: Vec::new(),
             opts,
             table: None,
             stack: Vec::new(),
             scratch: String::with_capacity(512),
             text_buf: String::with_capacity(512),
             newline_required: false,
+            blank_lines: None,
             code_block: None,
             last_line_blank: true,
+            newline_pending: false,
+            lazy_line: false,
+            links: Vec::new(),
+            inline_notes: 0,
+            prev_list: None,
+            list_items: HashMap::new(),
+            wrapping: false,
+            unbreakable: Vec::new(),
+            cell_spans: Vec::new(),
+            heading_text: None,
+            table_directive: None,
+            anchors: HashSet::new(),
+            wikilinks,
+            wikilink_start: None,
+            too_deep: false,
+            removed_link_end: None,
         }
     }
 
-    fn format(&mut self, parser: Parser) -> fmt::Result {
+    fn format(
+        &mut self,
+        events: Vec<(Event<'a>, Range<usize>)>,
+        verbatim: Vec<Range<usize>>,
+    ) -> fmt::Result {
+        let mut verbatim = verbatim.into_iter().peekable();
+        // Footnote definitions may have been moved, so events before the last
+        // verbatim region aren't necessarily within it.
+        let mut verbatim_range = 0..0;
         let mut is_last_html = false;
-        for event in parser {
+        let mut bold_heading: Option<(HeadingLevel, usize)> = None;
+        let mut bold_italics: Vec<Range<usize>> = Vec::new();
+        let bold_headings = self.bold_headings(&events);
+        let mut events = events.into_iter().peekable();
+        while let Some((event, range)) = events.next() {
+            while let Some(region) = verbatim.next_if(|v| v.start <= range.start) {
+                self.range = region.clone();
+                self.write_verbatim(&self.input[region.clone()])?;
+                verbatim_range = region.clone();
+                is_last_html = false;
+            }
+            if verbatim_range.contains(&range.start) {
+                continue;
+            }
+            self.range = range.clone();
+
+            // Definitions at the end of a container are written within it.
+            let offset = match event {
+                Event::End(_) => range.end,
+                _ => range.start,
+            };
+            if self.write_refdefs_before(offset, is_last_html)? {
+                is_last_html = false;
+            }
+
+            // Inline HTML may be followed by any inline content, which can't
+            // follow an HTML block.
             if is_last_html {
                 match event {
-                    Event::Html(_) | Event::Text(_) | Event::SoftBreak | Event::End(_) => {}
-                    _ => self.write_newline()?,
+                    Event::Html(_)
+                    | Event::Text(_)
+                    | Event::Code(_)
+                    | Event::FootnoteReference(_)
+                    | Event::SoftBreak
+                    | Event::HardBreak
+                    | Event::Start(
+                        Tag::Emphasis
+                        | Tag::Strong
+                        | Tag::Strikethrough
+                        | Tag::Link(..)
+                        | Tag::Image(..),
+                    )
+                    | Event::End(_) => {}
+                    _ => self.write_blank_lines()?,
                 }
                 is_last_html = false;
             }
 
+            // Images are written inline, so don't use their definitions.
+            if let Event::Start(Tag::Link(typ, ..)) = &event {
+                self.use_refdef(*typ);
+            }
+            let block_end = matches!(
+                &event,
+                Event::End(
+                    Tag::Paragraph
+                        | Tag::Heading(..)
+                        | Tag::BlockQuote
+                        | Tag::CodeBlock(_)
+                        | Tag::List(_)
+                        | Tag::FootnoteDefinition(_)
+                        | Tag::Table(_)
+                )
+            );
+
             match event {
+                Event::Start(Tag::Paragraph) if bold_headings.contains(&range.start) => {
+                    let level = self.opts.bold_headings.unwrap_or(HeadingLevel::H1);
+                    self.diagnose(Rule::BoldHeading, "strong text used as a heading");
+                    self.tag_start(Tag::Heading(level, None, Vec::new()))?;
+                    events.next();
+                    bold_heading = Some((level, 0));
+                }
+                Event::Start(Tag::Strong) if bold_heading.is_some() => {
+                    if let Some((_, depth)) = bold_heading.as_mut() {
+                        *depth += 1;
+                    }
+                    self.tag_start(Tag::Strong)?;
+                }
+                Event::End(Tag::Strong) if bold_heading.is_some() => {
+                    if let Some((_, depth)) = bold_heading.as_mut() {
+                        if *depth == 0 {
+                            continue;
+                        }
+                        *depth -= 1;
+                    }
+                    self.tag_end(Tag::Strong)?;
+                }
+                Event::End(Tag::Paragraph) if bold_heading.is_some() => {
+                    if let Some((level, _)) = bold_heading.take() {
+                        self.tag_end(Tag::Heading(level, None, Vec::new()))?;
+                    }
+                }
+                Event::Start(Tag::Emphasis | Tag::Strong)
+                    if self.opts.bold_italic != BoldItalic::Preserve
+                        && matches!(
+                            events.peek(),
+                            Some((Event::Start(Tag::Emphasis | Tag::Strong), inner))
+                                if is_bold_italic(&range, inner)
+                        ) =>
+                {
+                    if let Some((_, inner)) = events.next() {
+                        bold_italics.push(inner);
+                    }
+                    self.write_bold_italic(true)?;
+                }
+                Event::End(Tag::Emphasis | Tag::Strong) if bold_italics.last() == Some(&range) => {
+                    bold_italics.pop();
+                    // The delimiters are those of the outer span.
+                    if let Some((_, outer)) = events.next() {
+                        self.range = outer;
+                    }
+                    self.write_bold_italic(false)?;
+                }
                 Event::Start(tag) => self.tag_start(tag)?,
+                Event::End(Tag::BlockQuote)
+                    if self.opts.blockquote_join == BlockquoteJoin::Merge
+                        && matches!(events.peek(), Some((Event::Start(Tag::BlockQuote), _))) =>
+                {
+                    events.next();
+                }
                 Event::End(tag) => self.tag_end(tag)?,
                 Event::Text(s) => {
-                    let out: String;
-                    let mut text: &str = &s;
+                    let (code, out): (String, String);
+                    let mut s: &str = &s;
+                    // The parser only strips the indentation of the fence from
+                    // the code of fenced blocks in footnote definitions, not
+                    // the indentation that continues the definition.
+                    if let (Some(Some(_)), Some(StackItem::Footnote(..))) =
+                        (&self.code_block, self.stack.last())
+                    {
+                        code = s
+                            .split_inclusive('\n')
+                            .map(|line| {
+                                let indent = line.len() - line.trim_start_matches(' ').len();
+                                &line[indent.min(3)..]
+                            })
+                            .collect();
+                        s = &code;
+                    }
+                    let mut text = s;
+                    if self.removed_link_end == Some(self.range.start) {
+                        text = text.trim_start_matches([' ', '\t']);
+                    }
                     if let Some(Some(lang)) = &self.code_block {
                         if let Some(code_fmt) = &self.opts.code_fmt {
-                            if let Some(v) = (code_fmt)(lang, &s) {
+                            if let Some(v) = (code_fmt)(lang, s) {
                                 out = v;
                                 text = &out;
                             }
                         }
                     }
-                    self.write_optional_escape(text)?;
+                    let expanded: String;
+                    if self.code_block.is_none() && text.contains('\t') {
+                        match self.opts.tabs {
+                            Tabs::Preserve => {}
+                            Tabs::Expand => {
+                                expanded = self.expand_tabs(text);
+                                text = &expanded;
+                            }
+                            Tabs::Diagnose => self.diagnose(Rule::HardTab, "hard tab in text"),
+                        }
+                    }
+                    if let Some(heading) = self.heading_text.as_mut() {
+                        heading.push_str(text);
+                    }
+                    let before_link =
+                        matches!(events.peek(), Some((Event::Start(Tag::Link(..)), _)));
+                    let bracket = self.alert_bracket(text)
+                        || self.inline_note_bracket(text)
+                        || self.wikilink_bracket(text, before_link);
+                    if !bracket {
+                        self.write_optional_escape(text)?;
+                    }
                     self.write_str(text)?;
                 }
                 Event::Code(s) => {
+                    if let Some(heading) = self.heading_text.as_mut() {
+                        heading.push_str(&s);
+                    }
+                    let start = self.text_buf.len();
                     self.write_char('`')?;
                     if let Some('`') = s.chars().next() {
                         self.write_backslash()?;
                     }
                     self.write_str(&s)?;
                     self.write_char('`')?;
+                    self.unbreakable.push(start..self.text_buf.len());
                 }
                 Event::Html(s) => {
-                    if self.text_buf.is_empty() {
+                    self.prev_list = None;
+                    if let Some((Event::Start(Tag::Table(_)), _)) = events.peek() {
+                        self.table_directive = TableDirective::parse(&s);
+                    }
+                    let start = self.text_buf.len();
+                    if start == 0 {
                         self.write_newline_if_required()?;
+                        self.wrapping = false;
                     }
-                    self.write_str(&s)?;
-                    if s.ends_with('\n') {
-                        self.write_newline()?;
+                    match self.opts.table_line_break.tag() {
+                        Some(br) if self.table.is_some() && wrap::is_line_break(&s) => {
+                            self.write_str(br)?
+                        }
+                        _ => self.write_str(&s)?,
+                    }
+                    self.unbreakable.push(start..self.text_buf.len());
+                    // The last line of an HTML block at the end of the input
+                    // may not end with a newline.
+                    let block_end = matches!(
+                        events.peek(),
+                        None | Some((
+                            Event::End(Tag::BlockQuote | Tag::Item | Tag::FootnoteDefinition(_)),
+                            _
+                        ))
+                    );
+                    if s.ends_with('\n') || block_end {
+                        self.write_newline_with_trim(!self.keep_trailing_whitespace())?;
                     }
                     is_last_html = true;
                 }
-                Event::SoftBreak => self.write_newline()?,
+                // Line breaks within links are buffered so that the link can
+                // still be rewritten when it ends.
+                Event::SoftBreak if self.is_wrapping() => self.write_char(' ')?,
+                Event::SoftBreak if !self.links.is_empty() => self.write_char('\n')?,
+                Event::SoftBreak => {
+                    self.write_newline()?;
+                    // The next line of the input is lazy if it doesn't start
+                    // with a blockquote marker.
+                    let next = self.input[self.range.end..].trim_start_matches([' ', '\t']);
+                    self.lazy_line = self.opts.lazy_continuation
+                        && self
+                            .stack
+                            .iter()
+                            .any(|v| matches!(v, StackItem::Blockquote(_)))
+                        && !next.starts_with('>');
+                }
                 Event::HardBreak => {
-                    //self.write_str("  ")?;
-                    self.write_char('\\')?;
-                    self.write_newline_with_trim(false)?;
+                    let spaces = if self.opts.trailing_whitespace == TrailingWhitespace::TrimAll {
+                        false
+                    } else if self.opts.prettier {
+                        self.input[self.range.start..].starts_with(char::is_whitespace)
+                    } else {
+                        self.opts.hard_break == HardBreak::TwoSpaces && self.links.is_empty()
+                    };
+                    if spaces {
+                        self.write_str("  ")?;
+                    } else {
+                        self.write_char('\\')?;
+                    }
+                    if self.links.is_empty() {
+                        self.write_newline_with_trim(false)?;
+                    } else {
+                        self.write_char('\n')?;
+                    }
                 }
                 Event::Rule => {
+                    self.prev_list = None;
+                    self.wrapping = false;
                     if self.newline_required {
-                        self.write_newline()?;
+                        self.write_blank_lines()?;
                     }
                     self.write_str("---")?;
                     self.write_newline()?;
                     self.newline_required = true;
                 }
                 Event::TaskListMarker(is_checked) => {
+                    let checked = self.opts.task_checked.or_else(|| {
+                        self.input[self.range.start..]
+                            .strip_prefix('[')
+                            .and_then(|v| v.chars().next())
+                    });
+                    // The marker is kept on one line with the word after it.
+                    let start = self.text_buf.len();
                     self.write_char('[')?;
-                    self.write_char(if is_checked { 'x' } else { ' ' })?;
+                    self.write_char(match checked {
+                        Some(c) if is_checked => c,
+                        None if is_checked => 'x',
+                        _ => ' ',
+                    })?;
                     self.write_str("] ")?;
+                    self.unbreakable.push(start..self.text_buf.len());
                 }
                 Event::FootnoteReference(label) => {
                     self.write_str("[^")?;
@@ -264,115 +3611,413 @@ impl<'a, W: fmt::Write> Context<'a, W> {
                     self.write_char(']')?;
                 }
             }
+
+            if block_end && self.stack.is_empty() {
+                self.write_used_refdefs()?;
+            }
         }
 
-        let refdefs = std::mem::take(&mut self.refdefs);
-        if !refdefs.is_empty() {
+        for region in verbatim {
+            self.write_refdefs_before(region.start, is_last_html)?;
+            self.range = region.clone();
+            self.write_verbatim(&self.input[region])?;
+            is_last_html = false;
+        }
+        self.write_refdefs_before(usize::MAX, is_last_html)?;
+
+        let mut refdefs = std::mem::take(&mut self.refdefs);
+        refdefs.retain(|v| !v.verbatim);
+        refdefs.retain(|v| !v.written);
+        if self.opts.refdef_policy == RefDefPolicy::SortedAtEnd {
+            // Definitions may have been added for long inline links.
+            refdefs.sort_by(|r1, r2| r1.label.cmp(&r2.label));
+        }
+        if !refdefs.is_empty() && self.opts.refdef_policy != RefDefPolicy::KeepInPlace {
             self.write_newline()?;
-            for refdef in refdefs {
-                self.write_char('[')?;
-                self.write_str(&refdef.label)?;
-                self.write_str("]: ")?;
-                self.write_str(&refdef.dest)?;
-                if let Some(title) = refdef.title {
-                    self.write_str(" \"")?;
-                    self.write_str(&title)?;
-                    self.write_char('"')?;
-                }
-                self.write_newline()?;
+            for refdef in &refdefs {
+                self.write_refdef(refdef)?;
             }
         }
 
+        let final_newline = match self.opts.final_newline {
+            FinalNewline::Ensure => true,
+            FinalNewline::Preserve => self.input.ends_with('\n'),
+            FinalNewline::Strip => false,
+        };
+        if self.newline_pending && final_newline {
+            self.writer.write_str(self.opts.line_ending)?;
+        }
         Ok(())
     }
 
     fn tag_start(&mut self, tag: Tag) -> fmt::Result {
+        if let (Tag::BlockQuote | Tag::List(_) | Tag::FootnoteDefinition(_), Some(max)) =
+            (&tag, self.opts.max_depth)
+        {
+            let depth = self
+                .stack
+                .iter()
+                .filter(|item| !matches!(item, StackItem::CodeIndent))
+                .count();
+            if depth >= max {
+                self.too_deep = true;
+                return Err(fmt::Error);
+            }
+        }
+        if let (
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..),
+            Some(_),
+        ) = (&tag, &self.table)
+        {
+            self.cell_spans.push(self.text_buf.len());
+        }
+        if let Tag::Heading(..) = tag {
+            self.heading_text = Some(String::new());
+            if self.stack.is_empty() {
+                let before = self.opts.blank_lines_before_headings.max(1);
+                self.blank_lines = Some(self.blank_lines.map_or(before, |v| v.max(before)));
+            }
+        }
+        // Blocks after the text of a tight item are continuation blocks, like
+        // those after a block that has ended.
+        if let (
+            Tag::Paragraph | Tag::Heading(..) | Tag::BlockQuote | Tag::CodeBlock(_) | Tag::Table(_),
+            Some(StackItem::List(marker, written, _)),
+        ) = (&tag, self.stack.last_mut())
+        {
+            if *written || !self.text_buf.is_empty() {
+                marker.continued = true;
+            }
+            // The text is written before the block, on the marker's line.
+            if !self.text_buf.is_empty() && matches!(tag, Tag::Heading(..) | Tag::BlockQuote) {
+                self.write_newline()?;
+            }
+        }
+        // These blocks can't interrupt a paragraph, so they're separated from
+        // the paragraph or list before them in the same item.
+        if let (
+            Tag::Paragraph | Tag::CodeBlock(CodeBlockKind::Indented) | Tag::Table(_),
+            Some(StackItem::List(_, _, true)),
+        ) = (&tag, self.stack.last())
+        {
+            self.newline_required = true;
+        }
+        // The content of an item of a loose list is a paragraph, which a nested
+        // list is separated from.
+        if let (Tag::List(_), Some(StackItem::List(_, written, _))) = (&tag, self.stack.last()) {
+            if self.opts.list_spacing == ListSpacing::Loose
+                && (*written || !self.text_buf.is_empty())
+            {
+                if !self.text_buf.is_empty() {
+                    self.write_newline()?;
+                }
+                self.newline_required = true;
+            }
+        }
+        if let (Tag::Item, Some(StackItem::List(marker, ..))) = (&tag, self.stack.last()) {
+            if marker.items > 0 {
+                match self.opts.list_spacing {
+                    ListSpacing::Loose => self.newline_required = true,
+                    ListSpacing::Preserve if marker.loose => {}
+                    _ if !self.opts.blank_line_after_block_items => {
+                        self.newline_required = false;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let separated = self.newline_required;
         self.write_newline_if_required()?;
+        let prev_list = self.prev_list.take();
+        let wrapping = match tag {
+            Tag::Paragraph | Tag::Item => true,
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..) => {
+                self.wrapping
+            }
+            _ => false,
+        };
         match tag {
-            Tag::Heading(lvl, _, _) => self.write_heading_level(lvl)?,
-            Tag::BlockQuote => self.stack.push(StackItem::Blockquote),
+            Tag::Heading(lvl, _, _) if self.setext_underline(lvl).is_none() => {
+                self.write_heading_level(lvl)?
+            }
+            Tag::Heading(..) => {}
+            Tag::BlockQuote => {
+                let compact = self.input[..self.range.start].ends_with('>');
+                self.stack.push(StackItem::Blockquote(compact));
+            }
             Tag::CodeBlock(kind) => {
                 if !self.text_buf.is_empty() {
                     self.write_newline()?;
                 }
+                self.wrapping = false;
                 match kind {
                     CodeBlockKind::Indented => {
+                        if let Some(StackItem::List(marker, false, _)) = self.stack.last_mut() {
+                            marker.narrow = true;
+                        }
                         self.code_block = Some(None);
                         self.stack.push(StackItem::CodeIndent)
                     }
-                    CodeBlockKind::Fenced(s) => {
-                        self.write_str("```")?;
-                        self.write_str(&s)?;
-                        self.write_newline()?;
-                        self.code_block = Some(Some(s.into_string()));
-                    }
+                    // The opening fence is written when the block ends, as its
+                    // length depends on the code.
+                    CodeBlockKind::Fenced(s) => self.code_block = Some(Some(s.into_string())),
                 }
             }
             Tag::List(l) => {
                 if let Some(StackItem::List(_, _, newline)) = self.stack.last_mut() {
                     *newline = true;
-                    self.write_newline()?;
+                    // The blank line after a paragraph before the list may
+                    // already have been written.
+                    if !separated || !self.text_buf.is_empty() {
+                        self.write_newline()?;
+                    }
                 }
-                let l = l.map(|v| v.to_string());
-                self.stack.push(StackItem::List(l, false, false));
+                if let Some(StackItem::List(marker, ..)) = self.stack.last_mut() {
+                    marker.continued = true;
+                }
+                let sibling = match prev_list {
+                    Some((depth, ordered, i))
+                        if depth == self.stack.len() && ordered == l.is_some() =>
+                    {
+                        i + 1
+                    }
+                    _ => 0,
+                };
+                let delimiter = self.input[self.range.start..]
+                    .trim_start()
+                    .chars()
+                    .find(|c| !c.is_ascii_digit());
+                let numbers = self
+                    .list_items
+                    .get(&self.range.start)
+                    .map_or(&[][..], Vec::as_slice);
+                let depth = self
+                    .stack
+                    .iter()
+                    .filter(|item| matches!(item, StackItem::List(..)))
+                    .count();
+                let marker = ListMarker::new(l, depth, sibling, delimiter, numbers, &self.opts);
+                self.stack.push(StackItem::List(marker, false, false));
             }
             Tag::Item => {
-                if let Some(StackItem::List(_, written, newline)) = self.stack.last_mut() {
+                let number = item_number(&self.input[self.range.start..]);
+                if let Some(StackItem::List(marker, written, newline)) = self.stack.last_mut() {
+                    marker.next_item(number, &self.opts);
+                    marker.narrow = false;
+                    marker.continued = false;
                     *written = false;
                     *newline = false;
                 }
             }
             Tag::FootnoteDefinition(value) => {
-                self.write_str("[^")?;
-                self.write_str(&value)?;
-                self.write_str("]: ")?;
+                self.stack
+                    .push(StackItem::Footnote(value.into_string(), false));
+            }
+            Tag::Table(alignments) => {
+                let mut table = Table::new(alignments);
+                table.html = self.input[self.range.clone()]
+                    .get(..6)
+                    .is_some_and(|start| start.eq_ignore_ascii_case("<table"));
+                if let Some(directive) = self.table_directive.take() {
+                    table.directive = directive;
+                }
+                self.table = Some(table);
             }
-            Tag::Table(alignments) => self.table = Some(Table::new(alignments)),
             Tag::TableRow => {
-                if let Some(table) = self.table.as_mut() {
+                if let Some(mut table) = self.table.take() {
+                    // A row that looked like a caption wasn't the last.
+                    if let Some(caption) = table.caption.take() {
+                        if table.widths.is_some() {
+                            self.write_streamed_row(&mut table)?;
+                        } else {
+                            table.rows.push(caption);
+                        }
+                    }
                     table.body.push(Vec::with_capacity(table.head.len()));
+                    table.row_end = self.range.end;
+                    self.table = Some(table);
                 }
             }
-            Tag::Emphasis => self.write_str(self.opts.emphasis_str)?,
-            Tag::Strong => self.write_str(STRONG)?,
+            Tag::Emphasis => self.write_str(self.emphasis_str())?,
+            Tag::Strong => self.write_str(self.strong_str())?,
             Tag::Strikethrough => self.write_str(STRIKETHROUGH)?,
-            Tag::Link(typ, _, _) => match typ {
-                LinkType::Autolink | LinkType::Email => self.write_char('<')?,
-                _ => self.write_char('[')?,
-            },
-            Tag::Image(_, _, _) => self.write_str("![")?,
-            Tag::Paragraph | Tag::TableHead | Tag::TableCell => {}
+            Tag::Link(typ, _, _) => {
+                self.links.push(self.text_buf.len());
+                match typ {
+                    LinkType::Autolink | LinkType::Email => self.write_char('<')?,
+                    _ => self.write_char('[')?,
+                }
+            }
+            Tag::Image(_, _, _) => {
+                self.links.push(self.text_buf.len());
+                self.write_str("![")?
+            }
+            Tag::TableHead => {
+                if let Some(table) = self.table.as_mut() {
+                    table.row_end = self.range.end;
+                }
+            }
+            Tag::Paragraph | Tag::TableCell => {}
         }
+        self.wrapping = wrapping;
         Ok(())
     }
 
     fn tag_end(&mut self, tag: Tag) -> fmt::Result {
+        let block = matches!(
+            tag,
+            Tag::Paragraph
+                | Tag::Heading(..)
+                | Tag::BlockQuote
+                | Tag::CodeBlock(_)
+                | Tag::List(_)
+                | Tag::Table(_)
+        );
+        let span = match tag {
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..)
+                if self.table.is_some() =>
+            {
+                self.cell_spans.pop()
+            }
+            _ => None,
+        };
+        self.write_tag_end(tag)?;
+        if let Some(start) = span.filter(|&start| start < self.text_buf.len()) {
+            self.unbreakable.push(start..self.text_buf.len());
+        }
+        if block {
+            self.inline_notes = 0;
+        }
+        if let (true, Some(StackItem::List(marker, ..))) = (block, self.stack.last_mut()) {
+            marker.continued = true;
+        }
+        Ok(())
+    }
+
+    fn write_tag_end(&mut self, tag: Tag) -> fmt::Result {
+        let mut rewritten = false;
+        let tag = match tag {
+            Tag::Link(typ, dest, title) => {
+                match self.opts.link_rewriter.and_then(|f| f(typ, &dest)) {
+                    Some(dest) => {
+                        rewritten = true;
+                        Tag::Link(typ, dest.into(), title)
+                    }
+                    None => Tag::Link(typ, dest, title),
+                }
+            }
+            Tag::Image(typ, dest, title) => {
+                // The alt text follows the image's `![`.
+                let alt = self
+                    .links
+                    .last()
+                    .and_then(|&start| self.text_buf.get(start + 2..));
+                match self
+                    .opts
+                    .image_rewriter
+                    .zip(alt)
+                    .and_then(|(f, alt)| f(alt, &dest))
+                {
+                    Some(dest) => Tag::Image(typ, dest.into(), title),
+                    None => Tag::Image(typ, dest, title),
+                }
+            }
+            tag => tag,
+        };
+        let mut link_start = None;
+        if let Tag::Link(..) | Tag::Image(..) = tag {
+            if let Some(start) = self.links.pop() {
+                if self.write_empty_link(&tag, start)? {
+                    return Ok(());
+                }
+                link_start = Some(start);
+            }
+        }
+        if let (Tag::Image(_, dest, _), Some(start)) = (&tag, link_start) {
+            self.write_missing_alt_text(dest, start)?;
+        }
+
         match tag {
             Tag::Paragraph => {
-                if !matches!(self.stack.last(), Some(StackItem::List(..))) {
+                // Paragraphs in the items of tight lists aren't followed by a
+                // blank line.
+                if !matches!(self.stack.last(), Some(StackItem::List(..)))
+                    || self.opts.list_spacing != ListSpacing::Tight
+                {
                     self.newline_required = true;
                 }
-                if let Some(StackItem::List(_, _, newline)) = self.stack.last_mut() {
+                if let Some(StackItem::List(marker, _, newline)) = self.stack.last_mut() {
+                    marker.loose = true;
                     *newline = true;
                 }
-                self.write_newline_if_content()
+                self.write_newline_if_content()?;
+                self.wrapping = false;
+                Ok(())
             }
-            Tag::Heading(_, id, classes) => {
-                if id.is_some() || !classes.is_empty() {
-                    self.write_char('{')?;
-                    if let Some(id) = id {
-                        self.write_str(" #")?;
-                        self.write_str(id)?;
+            Tag::Heading(lvl, id, classes) => {
+                let text = self.heading_text.take().unwrap_or_default();
+                let id = self.heading_id(id, &text);
+                let mut underline = self.setext_underline(lvl);
+                if let Some(transform) = self.opts.heading_transform {
+                    // ATX headings start with their level.
+                    let start = match underline {
+                        Some(_) => 0,
+                        None => self.shift_heading(lvl) as usize + 1,
+                    };
+                    let text = self.text_buf.get(start..).unwrap_or_default();
+                    if let Some(text) = transform(self.shift_heading(lvl), text) {
+                        self.text_buf.truncate(start);
+                        self.text_buf.push_str(&text);
                     }
-                    for class in classes {
-                        self.write_str(" .")?;
-                        self.write_str(class)?;
+                }
+                if underline.is_some() {
+                    let text = self.text_buf.trim_start();
+                    let first = text.split(char::is_whitespace).next().unwrap_or_default();
+                    if text.is_empty() || !wrap::can_start_line(first) {
+                        let text = std::mem::take(&mut self.text_buf);
+                        self.write_heading_level(lvl)?;
+                        self.write_str(&text)?;
+                        underline = None;
+                    }
+                }
+                if (id.is_some() || !classes.is_empty())
+                    && self.opts.heading_attributes != HeadingAttributes::Remove
+                {
+                    if !self.text_buf.is_empty() && !self.text_buf.ends_with(' ') {
+                        self.write_char(' ')?;
+                    }
+                    let id_index = match self.opts.heading_attribute_order {
+                        HeadingAttributeOrder::IdFirst => 0,
+                        HeadingAttributeOrder::Preserve => self.heading_id_index(),
+                    };
+                    let mut attributes = classes
+                        .iter()
+                        .map(|class| format!(".{class}"))
+                        .collect::<Vec<_>>();
+                    if let Some(id) = &id {
+                        attributes.insert(id_index.min(attributes.len()), format!("#{id}"));
+                    }
+                    let attributes = attributes.join(" ");
+                    if self.opts.heading_attributes == HeadingAttributes::Compact {
+                        self.write_str(&format!("{{{attributes}}}"))?;
+                    } else {
+                        self.write_str(&format!("{{ {attributes} }}"))?;
                     }
-                    self.write_str(" }")?;
                 }
 
+                let width = self.text_buf.lines().map(|line| line.chars().count()).max();
+                if self.stack.is_empty() {
+                    self.blank_lines = Some(self.opts.blank_lines_after_headings.max(1));
+                }
                 self.newline_required = true;
-                self.write_newline()
+                self.write_newline()?;
+                if let Some(underline) = underline {
+                    for _ in 0..width.unwrap_or_default().max(3) {
+                        self.write_char(underline)?;
+                    }
+                    self.write_newline()?;
+                }
+                Ok(())
             }
             Tag::BlockQuote => {
                 self.stack.pop();
@@ -382,10 +4027,13 @@ impl<'a, W: fmt::Write> Context<'a, W> {
                 Ok(())
             }
             Tag::CodeBlock(kind) => {
-                if let CodeBlockKind::Fenced(_) = kind {
-                    self.write_str("```")?;
+                if let CodeBlockKind::Fenced(info) = &kind {
+                    let fence = self.code_fence();
+                    let info = self.opts.info_string.apply(info);
+                    self.text_buf.insert_str(0, &format!("{fence}{info}\n"));
+                    self.write_str(&fence)?;
                 }
-                self.write_newline()?;
+                self.write_newline_with_trim(!self.keep_trailing_whitespace())?;
                 if let CodeBlockKind::Indented = kind {
                     self.stack.pop();
                 }
@@ -394,7 +4042,20 @@ impl<'a, W: fmt::Write> Context<'a, W> {
                 Ok(())
             }
             Tag::List(_) => {
-                self.stack.pop();
+                if let Some(StackItem::List(marker, ..)) = self.stack.pop() {
+                    let ordered = marker.ordered.is_some();
+                    self.prev_list = Some((self.stack.len(), ordered, marker.sibling));
+                    // The blank line after the last item of a loose list isn't
+                    // kept between the items of the tight list around it.
+                    if let Some(StackItem::List(outer, ..)) = self.stack.last() {
+                        if self.opts.list_spacing == ListSpacing::Preserve
+                            && marker.loose
+                            && !outer.loose
+                        {
+                            self.newline_required = false;
+                        }
+                    }
+                }
                 if !self
                     .stack
                     .iter()
@@ -405,121 +4066,601 @@ impl<'a, W: fmt::Write> Context<'a, W> {
                 Ok(())
             }
             Tag::Item => {
-                if let Some(StackItem::List(_, _, false)) = self.stack.last() {
-                    self.write_newline_if_content()?;
+                // Blocks ending the item have already been written, and any
+                // blank line after them is written before the next item.
+                if let Some(StackItem::List(_, written, false)) = self.stack.last() {
+                    if !*written || !self.text_buf.is_empty() {
+                        self.write_newline()?;
+                    }
                 }
+                self.wrapping = false;
                 Ok(())
             }
             Tag::Table(_) => {
-                let table = match self.table.take() {
+                let mut table = match self.table.take() {
                     Some(table) => table,
                     None => return Ok(()),
                 };
-                let widths = table.column_widths();
-                self.write_table_row(&table.head, &widths)?;
-
-                self.write_char('|')?;
-                for (w, a) in widths.iter().zip(table.alignments.iter()) {
-                    self.write_char(' ')?;
-                    self.write_char(if matches!(a, Alignment::Left | Alignment::Center) {
-                        ':'
-                    } else {
-                        '-'
-                    })?;
-                    for _ in 0..*w - 2 {
-                        self.write_char('-')?;
-                    }
-                    self.write_char(if matches!(a, Alignment::Right | Alignment::Center) {
-                        ':'
-                    } else {
-                        '-'
-                    })?;
-                    self.write_str(" |")?;
+                if table.widths.is_some() {
+                    return self.end_table(table.caption);
                 }
-                self.write_newline()?;
-
-                for b in &table.body {
-                    self.write_table_row(b, &widths)?;
+                if table.caption.is_some() {
+                    table.body.pop();
                 }
-
-                self.table = None;
-                self.newline_required = true;
-
-                if let Some(StackItem::List(_, _, newline)) = self.stack.last_mut() {
-                    *newline = true;
+                let sort_by = table
+                    .directive
+                    .sort_by
+                    .or_else(|| self.opts.table_sort.and_then(|f| f(&table.head)));
+                let layout = table.directive.layout;
+                let preserve = match layout {
+                    Some(layout) => layout == TableLayout::Preserve,
+                    None => self.opts.preserve_tables,
+                };
+                if preserve && !table.html && !table.ragged && sort_by.is_none() {
+                    return self.write_table_source(&table);
+                }
+                table.pad_columns();
+                if let Some(column) = sort_by.filter(|&column| column < table.head.len()) {
+                    table
+                        .body
+                        .sort_by(|a, b| compare_cells(&a[column], &b[column]));
+                }
+                if self.opts.align_numeric_columns {
+                    table.align_numeric_columns();
                 }
+                let widths = if layout == Some(TableLayout::Compact) {
+                    vec![0; table.head.len()]
+                } else {
+                    table.column_widths(|cell| self.cell_width(cell), self.opts.table_cell_width)
+                };
+                // The delimiter row is at least three characters wide, and is
+                // the only row with the alignments without `no-align`.
+                let delimiters = widths.iter().map(|&w| w.max(3)).collect::<Vec<_>>();
+                let alignments = if table.directive.no_align {
+                    &[]
+                } else {
+                    table.alignments.as_slice()
+                };
+                let pipes = match self.opts.table_style {
+                    TableStyle::Piped => true,
+                    TableStyle::Unpiped => false,
+                    // The header row is always the first.
+                    TableStyle::Preserve => {
+                        table.html
+                            || table.rows.first().is_some_and(|row| {
+                                self.input[row.clone()].trim_start().starts_with('|')
+                            })
+                    }
+                } || table.needs_pipes();
+                self.write_table_row(&table.head, &widths, alignments, pipes)?;
+                self.write_delimiter_row(&delimiters, &table.alignments, pipes)?;
 
-                Ok(())
+                for b in &table.body {
+                    self.write_table_row(b, &widths, alignments, pipes)?;
+                }
+                self.end_table(table.caption)
             }
             Tag::TableCell => {
                 if let Some(table) = self.table.as_mut() {
+                    // Cells missing from the input are added empty at the end
+                    // of the row, unlike empty cells between pipes.
+                    if self.range.is_empty() && self.range.start >= table.row_end {
+                        table.missing += 1;
+                    }
+                    table.cell_end = self.range.end;
+                    let mut cell = self.text_buf.to_string();
+                    let mut unbreakable = self.unbreakable.as_slice();
+                    let column = table.body.last().map_or(table.head.len(), Vec::len);
+                    if let Some(text) = self.opts.table_cell_fmt.and_then(|f| f(column, &cell)) {
+                        // The ranges of code spans and HTML are unknown in the
+                        // formatted text.
+                        cell = text;
+                        unbreakable = &[];
+                    }
+                    if let (Some(width), true) =
+                        (self.opts.table_cell_width, self.opts.table_cell_wrap)
+                    {
+                        let mode = self.opts.table_width_mode;
+                        if wrap::split_line_breaks(&cell).any(|line| text_width(line, mode) > width)
+                        {
+                            let br = self.opts.table_line_break.tag().unwrap_or("<br>");
+                            cell = wrap::wrap_cell(&cell, unbreakable, width, br);
+                        }
+                    }
                     if let Some(b) = table.body.last_mut() {
-                        b.push(self.text_buf.to_string());
+                        b.push(cell);
                     } else {
-                        table.head.push(self.text_buf.to_string());
+                        table.head.push(cell);
                     }
                     self.text_buf.clear();
+                    self.unbreakable.clear();
+                }
+                Ok(())
+            }
+            Tag::Emphasis => self.write_str(self.emphasis_str()),
+            Tag::Strong => self.write_str(self.strong_str()),
+            Tag::Strikethrough => self.write_str(STRIKETHROUGH),
+            Tag::Link(
+                LinkType::Reference | LinkType::Shortcut | LinkType::Collapsed,
+                dest,
+                title,
+            ) if self.opts.inline_links => self.write_inline_dest(&dest, &title),
+            Tag::Link(typ @ (LinkType::Reference | LinkType::ReferenceUnknown), dest, title) => {
+                // The label is written as in the input, unless its definition
+                // was removed as a duplicate of another.
+                let label = self.source_label(typ).and_then(|label| {
+                    let normalized = normalize_label(&label);
+                    if let Some(kept) = self.refdef_aliases.get(&normalized) {
+                        return Some(kept.clone());
+                    }
+                    self.refdefs
+                        .iter()
+                        .any(|v| normalize_label(&v.label) == normalized)
+                        .then_some(label)
+                });
+                match label {
+                    Some(label) => self.write_reference_end(typ, &label, link_start),
+                    None => self.write_inline_dest(&dest, &title),
+                }
+            }
+            Tag::Link(
+                typ @ (LinkType::Shortcut
+                | LinkType::ShortcutUnknown
+                | LinkType::Collapsed
+                | LinkType::CollapsedUnknown),
+                ..,
+            ) => {
+                // Links to a removed duplicate use the definition kept.
+                let label = self.source_label(typ).map(|label| {
+                    match self.refdef_aliases.get(&normalize_label(&label)) {
+                        Some(kept) => kept.clone(),
+                        None => label,
+                    }
+                });
+                match label {
+                    Some(label) => self.write_reference_end(typ, &label, link_start),
+                    None if matches!(typ, LinkType::Shortcut | LinkType::ShortcutUnknown) => {
+                        self.write_char(']')
+                    }
+                    None => self.write_str("][]"),
+                }
+            }
+            Tag::Link(LinkType::Autolink | LinkType::Email, dest, _) => {
+                // The destination of an autolink is also its text.
+                if let (true, Some(start)) = (rewritten, link_start) {
+                    self.text_buf.truncate(start + 1);
+                    self.write_str(&dest)?;
+                }
+                self.write_char('>')
+            }
+            Tag::Link(LinkType::Inline, dest, title)
+                if self
+                    .opts
+                    .max_inline_link_length
+                    .is_some_and(|max| dest.chars().count() > max) =>
+            {
+                // The text of the link follows its `[`.
+                let text = link_start.map_or(String::new(), |start| {
+                    self.text_buf
+                        .get(start + 1..)
+                        .unwrap_or_default()
+                        .to_string()
+                });
+                let label = self.link_reference(&text, &dest, &title);
+                self.write_str("][")?;
+                self.write_str(&label)?;
+                self.write_char(']')
+            }
+            Tag::Link(_, dest, title) | Tag::Image(_, dest, title) => {
+                self.write_inline_dest(&dest, &title)
+            }
+            Tag::FootnoteDefinition(_) => {
+                // Definitions without content are written alone.
+                if let Some(StackItem::Footnote(_, false)) = self.stack.last() {
+                    self.write_newline()?;
+                }
+                self.stack.pop();
+                self.newline_required = true;
+                Ok(())
+            }
+            Tag::TableHead => {
+                let Some(mut table) = self.table.take() else {
+                    return Ok(());
+                };
+                let result = if self.opts.table_streaming {
+                    self.write_streamed_head(&mut table)
+                } else {
+                    table.rows.push(self.range.clone());
+                    Ok(())
+                };
+                self.table = Some(table);
+                result
+            }
+            Tag::TableRow => {
+                let Some(table) = self.table.as_mut() else {
+                    return Ok(());
+                };
+                let streamed = table.widths.is_some();
+                if is_table_caption(&self.input[self.range.clone()]) {
+                    // The row is kept until it's known whether it's the last.
+                    table.caption = Some(self.range.clone());
+                    table.missing = 0;
+                    return Ok(());
+                }
+                if !streamed {
+                    table.rows.push(self.range.clone());
+                }
+                // Cells beyond the header's are dropped by the parser.
+                let extra = split_cells(&self.input[table.cell_end..self.range.end]);
+                let missing = std::mem::take(&mut table.missing);
+                if !extra.is_empty() || missing > 0 {
+                    table.ragged = true;
+                    let header = table.head.len();
+                    if self.opts.ragged_rows == RaggedRows::Extend && !streamed {
+                        if let Some(row) = table.body.last_mut() {
+                            row.extend(extra.iter().map(|cell| cell.to_string()));
+                        }
+                    }
+                    let message = format!(
+                        "expected {header} table cells, found {}",
+                        header + extra.len() - missing
+                    );
+                    self.diagnose(Rule::RaggedTableRow, message);
+                }
+                if !streamed {
+                    return Ok(());
+                }
+                let Some(mut table) = self.table.take() else {
+                    return Ok(());
+                };
+                let result = self.write_streamed_row(&mut table);
+                self.table = Some(table);
+                result
+            }
+        }
+    }
+
+    // Records the anchor of the heading with the ID and plain text, applying
+    // the `DuplicateHeadings` policy. Returns the ID to write.
+    fn heading_id(&mut self, id: Option<&str>, text: &str) -> Option<String> {
+        let anchor = id.map_or_else(|| slug(text), str::to_string);
+        if self.anchors.insert(anchor.clone()) {
+            return id.map(str::to_string);
+        }
+        // Renderers make generated anchors unique in the same way.
+        let unique = (1..)
+            .map(|i| format!("{anchor}-{i}"))
+            .find(|v| !self.anchors.contains(v))
+            .unwrap_or_default();
+        self.anchors.insert(unique.clone());
+        match self.opts.duplicate_headings {
+            DuplicateHeadings::Deduplicate if id.is_some() => return Some(unique),
+            DuplicateHeadings::Diagnose => {
+                let message = format!("heading anchor `{anchor}` is already used");
+                self.diagnose(Rule::DuplicateHeading, message);
+            }
+            _ => {}
+        }
+        id.map(str::to_string)
+    }
+
+    // Returns the number of classes before the ID in the attributes of the
+    // current heading in the input.
+    fn heading_id_index(&self) -> usize {
+        let heading = &self.input[self.range.clone()];
+        let attributes = heading
+            .rfind('{')
+            .and_then(|start| heading[start + 1..].split('}').next())
+            .unwrap_or_default();
+        attributes
+            .split_whitespace()
+            .take_while(|v| !v.starts_with('#'))
+            .filter(|v| v.starts_with('.'))
+            .count()
+    }
+
+    // Applies the `EmptyLinks` policy to the link or image that starts at
+    // `start` in the text buffer. Returns true if it was written as plain text.
+    fn write_empty_link(&mut self, tag: &Tag, start: usize) -> Result<bool, fmt::Error> {
+        let (is_image, dest) = match tag {
+            Tag::Link(LinkType::Autolink | LinkType::Email, ..) => return Ok(false),
+            Tag::Link(_, dest, _) => (false, dest),
+            Tag::Image(_, dest, _) => (true, dest),
+            _ => return Ok(false),
+        };
+        let marker_len = if is_image { 2 } else { 1 };
+        let text_empty = match self.text_buf.get(start + marker_len..) {
+            Some(text) => text.trim().is_empty(),
+            None => return Ok(false),
+        };
+        let (rule, message) = match (is_image, dest.is_empty(), text_empty) {
+            (false, true, _) => (Rule::EmptyLink, "link has an empty destination"),
+            (false, false, true) => (Rule::EmptyLink, "link has empty text"),
+            (true, true, _) => (Rule::EmptyImage, "image has an empty destination"),
+            _ => return Ok(false),
+        };
+
+        match self.opts.empty_links {
+            EmptyLinks::Preserve => Ok(false),
+            EmptyLinks::Diagnose => {
+                self.diagnose(rule, message);
+                Ok(false)
+            }
+            EmptyLinks::PlainText => {
+                if dest.is_empty() && text_empty {
+                    self.text_buf.truncate(start);
+                    if self.text_buf.is_empty() || self.text_buf.ends_with([' ', '\n']) {
+                        self.removed_link_end = Some(self.range.end);
+                    }
+                } else if dest.is_empty() {
+                    self.text_buf.replace_range(start..start + marker_len, "");
+                } else {
+                    self.text_buf.truncate(start);
+                    self.write_optional_escape(dest)?;
+                    self.write_str(dest)?;
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    // Writes the alt text supplied for the image that starts at `start` in the
+    // text buffer if it has none, or reports it if alt text is required.
+    fn write_missing_alt_text(&mut self, dest: &str, start: usize) -> fmt::Result {
+        let alt = self.text_buf.get(start + 2..).unwrap_or_default();
+        if !alt.trim().is_empty() {
+            return Ok(());
+        }
+        if let Some(alt) = self.opts.alt_text.and_then(|f| f(dest)) {
+            self.text_buf.truncate(start + 2);
+            for c in alt.chars() {
+                if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`' | '<' | '>' | '~') {
+                    self.write_backslash()?;
+                }
+                self.write_char(c)?;
+            }
+        } else if self.opts.require_alt_text {
+            self.diagnose(Rule::MissingAltText, "image has no alt text");
+        }
+        Ok(())
+    }
+
+    fn write_bold_italic(&mut self, open: bool) -> fmt::Result {
+        let (emphasis, strong) = (self.emphasis_str(), self.strong_str());
+        match (self.opts.bold_italic, open) {
+            (BoldItalic::Nested, true) => {
+                self.write_str(strong)?;
+                self.write_str(emphasis)
+            }
+            (BoldItalic::Nested, false) => {
+                self.write_str(emphasis)?;
+                self.write_str(strong)
+            }
+            _ => {
+                self.write_str(strong)?;
+                self.write_str(&strong[..1])
+            }
+        }
+    }
+
+    fn emphasis_str(&self) -> &'a str {
+        self.delimiter(self.opts.emphasis_str, "*")
+    }
+
+    fn strong_str(&self) -> &'a str {
+        self.delimiter(self.opts.strong_str, "**")
+    }
+
+    // Returns the delimiter of the emphasis or strong span at the current
+    // event, or the fallback when smart emphasis is enabled and the span with
+    // underscore delimiters is within a word, ignoring the delimiters of the
+    // spans directly around it.
+    fn delimiter(&self, delimiter: &'a str, fallback: &'static str) -> &'a str {
+        if !self.opts.smart_emphasis || !delimiter.starts_with('_') {
+            return delimiter;
+        }
+        let before = self.input[..self.range.start]
+            .trim_end_matches(['*', '_'])
+            .chars()
+            .next_back();
+        let after = self.input[self.range.end..]
+            .trim_start_matches(['*', '_'])
+            .chars()
+            .next();
+        if before.is_some_and(char::is_alphanumeric) || after.is_some_and(char::is_alphanumeric) {
+            fallback
+        } else {
+            delimiter
+        }
+    }
+
+    // Returns the starts of the paragraphs that should be converted to
+    // headings: those at the top level that consist solely of one line of
+    // strong text that doesn't end with punctuation.
+    fn bold_headings(&self, events: &[(Event<'a>, Range<usize>)]) -> HashSet<usize> {
+        let mut starts = HashSet::new();
+        if self.opts.bold_headings.is_none() {
+            return starts;
+        }
+        let mut depth = 0usize;
+        for (i, (event, range)) in events.iter().enumerate() {
+            match event {
+                Event::Start(Tag::Paragraph)
+                    if depth == 0 && self.is_bold_heading(&events[i..]) =>
+                {
+                    starts.insert(range.start);
+                }
+                _ => {}
+            }
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                _ => {}
+            }
+        }
+        starts
+    }
+
+    // Returns true if the paragraph starting at the first event consists
+    // solely of one line of strong text that doesn't end with punctuation.
+    fn is_bold_heading(&self, events: &[(Event<'a>, Range<usize>)]) -> bool {
+        let src = self.input[events[0].1.clone()].trim();
+        if src.contains('\n') || src.trim_end_matches(['*', '_']).ends_with(is_punctuation) {
+            return false;
+        }
+
+        let Some(end) = events
+            .iter()
+            .position(|(event, _)| matches!(event, Event::End(Tag::Paragraph)))
+        else {
+            return false;
+        };
+        let events = &events[..=end];
+        if !matches!(
+            events,
+            [
+                (Event::Start(Tag::Paragraph), _),
+                (Event::Start(Tag::Strong), _),
+                ..,
+                (Event::End(Tag::Strong), _),
+                (Event::End(Tag::Paragraph), _)
+            ]
+        ) {
+            return false;
+        }
+        // The outer strong span must close at the end of the paragraph.
+        let mut depth = 0;
+        events[1..events.len() - 2].iter().all(|(event, _)| {
+            match event {
+                Event::Start(Tag::Strong) => depth += 1,
+                Event::End(Tag::Strong) => depth -= 1,
+                _ => {}
+            }
+            depth > 0
+        })
+    }
+
+    fn diagnose(&mut self, rule: Rule, message: impl Into<String>) {
+        let diagnostic = Diagnostic::new(self.input, rule, message.into(), self.range.clone());
+        self.diagnostics.push(diagnostic);
+    }
+
+    // Returns the text with its tabs expanded to spaces, up to the next tab
+    // stop from the start of the line in the text buffer.
+    fn expand_tabs(&self, text: &str) -> String {
+        let line = self.text_buf.rfind('\n').map_or(0, |i| i + 1);
+        let mut column = self.text_buf[line..].chars().count();
+        let mut out = String::with_capacity(text.len() + 8);
+        for c in text.chars() {
+            match c {
+                '\t' => {
+                    let width = self.opts.tab_width - column % self.opts.tab_width;
+                    out.push_str(&" ".repeat(width));
+                    column += width;
                 }
-                Ok(())
-            }
-            Tag::Emphasis => self.write_str(self.opts.emphasis_str),
-            Tag::Strong => self.write_str(STRONG),
-            Tag::Strikethrough => self.write_str(STRIKETHROUGH),
-            Tag::Link(LinkType::Reference | LinkType::ReferenceUnknown, dest, title) => {
-                let refdefs = std::mem::take(&mut self.refdefs);
-                if let Some(refdef) = refdefs.iter().find(|v| dest.eq_ignore_ascii_case(&v.dest)) {
-                    self.write_str("][")?;
-                    self.write_str(&refdef.label)?;
-                    self.write_char(']')?;
-                } else {
-                    self.write_str("](")?;
-                    self.write_str(&dest)?;
-                    if !title.is_empty() {
-                        self.write_str(" \"")?;
-                        self.write_str(&title)?;
-                        self.write_char('"')?;
-                    }
-                    self.write_char(')')?;
+                '\n' => {
+                    out.push(c);
+                    column = 0;
+                }
+                _ => {
+                    out.push(c);
+                    column += 1;
                 }
-                self.refdefs = refdefs;
-                Ok(())
             }
-            Tag::Link(LinkType::Shortcut | LinkType::ShortcutUnknown, ..) => self.write_char(']'),
-            Tag::Link(LinkType::Collapsed | LinkType::CollapsedUnknown, ..) => {
-                self.write_str("][]")
+        }
+        out
+    }
+
+    // Returns true if the text is a bracket of the marker of a GitHub alert,
+    // like `[!NOTE]` at the start of a blockquote, which isn't escaped.
+    fn alert_bracket(&self, s: &str) -> bool {
+        if !self.opts.alerts || !matches!(self.stack.last(), Some(StackItem::Blockquote(_))) {
+            return false;
+        }
+        let start = match s {
+            "[" if self.text_buf.is_empty() => Some(self.range.start),
+            "]" if self.text_buf.starts_with("[!") => {
+                self.range.start.checked_sub(self.text_buf.len())
             }
-            Tag::Link(LinkType::Autolink | LinkType::Email, ..) => self.write_char('>'),
-            Tag::Link(_, dest, title) | Tag::Image(_, dest, title) => {
-                self.write_str("](")?;
-                self.write_str(&dest)?;
-                if !title.is_empty() {
-                    self.write_str(" \"")?;
-                    self.write_str(&title)?;
-                    self.write_char('"')?;
-                }
-                self.write_char(')')
+            _ => None,
+        };
+        let Some(line) = start.and_then(|start| self.input[start..].lines().next()) else {
+            return false;
+        };
+        [
+            "[!NOTE]",
+            "[!TIP]",
+            "[!IMPORTANT]",
+            "[!WARNING]",
+            "[!CAUTION]",
+        ]
+        .iter()
+        .any(|marker| line.trim_end().eq_ignore_ascii_case(marker))
+    }
+
+    // Returns true if the text is an unescaped bracket of an inline footnote,
+    // like `^[note]`, which isn't escaped so that it's kept for the renderers
+    // that support them.
+    fn inline_note_bracket(&mut self, s: &str) -> bool {
+        if self.code_block.is_some() {
+            return false;
+        }
+        let before = &self.input[..self.range.start];
+        match s {
+            "[" if before.ends_with('^') || (self.inline_notes > 0 && !before.ends_with('\\')) => {
+                self.inline_notes += 1;
+                true
+            }
+            "]" if self.inline_notes > 0 && !before.ends_with('\\') => {
+                self.inline_notes -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Returns whether the text is a bracket of a wikilink, which isn't escaped.
+    // The brackets of a wikilink were unescaped in the input, so writing them
+    // as is keeps the markdown around them parsed the same. The content of a
+    // wikilink may be parsed as a link, which is written like any other, with
+    // the brackets around it escaped.
+    fn wikilink_bracket(&mut self, s: &str, before_link: bool) -> bool {
+        if self.code_block.is_some() || !matches!(s, "[" | "]") {
+            return false;
+        }
+        let start = self.range.start;
+        let Some(link) = self.wikilinks.iter().find(|v| v.contains(&start)).cloned() else {
+            return false;
+        };
+        if start == link.start && before_link {
+            self.wikilinks.retain(|v| *v != link);
+            return false;
+        }
+        if start == link.start {
+            self.wikilink_start = Some(self.text_buf.len());
+        } else if start + 1 == link.end {
+            if let Some(begin) = self.wikilink_start.take() {
+                self.unbreakable.push(begin..self.text_buf.len() + 1);
             }
-            Tag::FootnoteDefinition(_) | Tag::TableHead | Tag::TableRow => Ok(()),
         }
+        true
     }
 
     fn write_optional_escape(&mut self, s: &str) -> fmt::Result {
         if self.code_block.is_some() {
-            if s.starts_with("```") {
-                self.write_backslash()?;
-            }
             return Ok(());
         }
         if let Some(first) = s.chars().next() {
             if self.table.is_some() && first == '|' {
                 return self.write_backslash();
             }
+            #[allow(clippy::collapsible_match)]
             match first {
                 '\\' | '<' | '>' | '*' | '_' | '`' | '[' | ']' | '~' => {
                     return self.write_backslash()
                 }
                 '#' | '-' | '+' => {
-                    if self.text_buf.is_empty() {
+                    if self.text_buf.is_empty() || self.text_buf.ends_with('\n') {
                         return self.write_backslash();
                     }
                 }
@@ -529,32 +4670,451 @@ impl<'a, W: fmt::Write> Context<'a, W> {
         Ok(())
     }
 
+    fn write_verbatim(&mut self, s: &str) -> fmt::Result {
+        // Regions starting with blank lines keep them as the separator.
+        if s.lines().next().is_some_and(|line| line.trim().is_empty()) {
+            self.newline_required = false;
+        } else {
+            self.write_newline_if_required()?;
+        }
+        for line in s.lines() {
+            // Nothing is written before the first line of the output.
+            if !self.newline_pending && line.trim().is_empty() {
+                continue;
+            }
+            if self.newline_pending {
+                self.writer.write_str(self.opts.line_ending)?;
+            }
+            self.writer.write_str(line)?;
+            self.newline_pending = true;
+            self.last_line_blank = line.trim().is_empty();
+        }
+        // Regions ending with blank lines keep them as the separator.
+        self.newline_required = !self.last_line_blank;
+        Ok(())
+    }
+
     fn write_backslash(&mut self) -> fmt::Result {
         self.text_buf.write_char('\\')
     }
 
-    fn write_table_row(&mut self, row: &[String], widths: &[usize]) -> fmt::Result {
-        self.write_str("|")?;
-        for (s, w) in row.iter().zip(widths.iter()) {
-            let width = s.chars().count();
-            self.write_char(' ')?;
+    // Writes the header and delimiter rows of a table whose rows are written
+    // as they're formatted, fixing the widths of its columns.
+    fn write_streamed_head(&mut self, table: &mut Table) -> fmt::Result {
+        let widths = table
+            .head
+            .iter()
+            .map(|cell| {
+                let width = self.cell_width(cell);
+                width.max(self.opts.table_cell_width.unwrap_or(0)).max(3)
+            })
+            .collect::<Vec<_>>();
+        self.write_table_row(&table.head, &widths, &table.alignments, true)?;
+        self.write_delimiter_row(&widths, &table.alignments, true)?;
+        table.widths = Some(widths);
+        Ok(())
+    }
+
+    // Writes the last body row of a table whose rows are written as they're
+    // formatted, and drops it.
+    fn write_streamed_row(&mut self, table: &mut Table) -> fmt::Result {
+        let (Some(widths), Some(row)) = (&table.widths, table.body.pop()) else {
+            return Ok(());
+        };
+        self.write_table_row(&row, widths, &table.alignments, true)
+    }
+
+    fn write_table_source(&mut self, table: &Table) -> fmt::Result {
+        for (i, row) in table.rows.iter().enumerate() {
+            self.write_str(self.input[row.clone()].trim())?;
+            self.write_newline()?;
+            if i == 0 {
+                // The delimiter row follows the header, after any container
+                // markers on its line.
+                let line = self.input[row.end..].lines().next().unwrap_or_default();
+                let line = line.trim_start_matches(|c: char| c == '>' || c.is_whitespace());
+                self.write_str(line.trim_end())?;
+                self.write_newline()?;
+            }
+        }
+        self.end_table(table.caption.clone())
+    }
+
+    // Writes the caption of a table, from the source range of its line, and
+    // ends the table.
+    fn end_table(&mut self, caption: Option<Range<usize>>) -> fmt::Result {
+        if let Some(caption) = caption {
+            self.write_str(self.input[caption].trim())?;
+            self.write_newline()?;
+        }
+        self.table = None;
+        self.newline_required = true;
+
+        if let Some(StackItem::List(_, _, newline)) = self.stack.last_mut() {
+            *newline = true;
+        }
+        Ok(())
+    }
+
+    // Returns the width of the text of a table cell that its column is padded
+    // by.
+    fn cell_width(&self, cell: &str) -> usize {
+        if !self.opts.table_rendered_width {
+            return text_width(cell, self.opts.table_width_mode);
+        }
+        let mut text = String::with_capacity(cell.len());
+        for event in Parser::new_ext(cell, parser_options()) {
+            if let Event::Text(s) | Event::Code(s) = event {
+                text.push_str(&s);
+            }
+        }
+        text_width(&text, self.opts.table_width_mode)
+    }
+
+    fn write_table_row(
+        &mut self,
+        row: &[String],
+        widths: &[usize],
+        alignments: &[Alignment],
+        pipes: bool,
+    ) -> fmt::Result {
+        let paddings = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(s, w)| w.saturating_sub(self.cell_width(s)))
+            .collect::<Vec<_>>();
+        self.write_table_cells(row, &paddings, alignments, pipes)
+    }
+
+    fn write_delimiter_row(
+        &mut self,
+        widths: &[usize],
+        alignments: &[Alignment],
+        pipes: bool,
+    ) -> fmt::Result {
+        let delimiters = delimiter_row(widths, alignments);
+        self.write_table_cells(&delimiters, &vec![0; widths.len()], alignments, pipes)
+    }
+
+    // Writes a row of table cells, each followed by its padding.
+    fn write_table_cells(
+        &mut self,
+        row: &[String],
+        paddings: &[usize],
+        alignments: &[Alignment],
+        pipes: bool,
+    ) -> fmt::Result {
+        for (i, (s, &padding)) in row.iter().zip(paddings.iter()).enumerate() {
+            let left = match alignments.get(i) {
+                Some(Alignment::Center) if self.opts.prettier => padding / 2,
+                Some(Alignment::Right) if self.opts.prettier => padding,
+                _ => 0,
+            };
+            self.write_str(match (i, pipes) {
+                (0, true) => "| ",
+                (0, false) => "",
+                _ => " | ",
+            })?;
+            for _ in 0..left {
+                self.write_char(' ')?;
+            }
             self.write_str(s)?;
-            for _ in 0..(w - width) {
+            for _ in 0..(padding - left) {
                 self.write_char(' ')?;
             }
+        }
+        if pipes {
             self.write_str(" |")?;
         }
         self.write_newline()
     }
 
+    // Records the first use of the reference definition of the link that
+    // starts at the current event, when definitions follow their first
+    // use.
+    fn use_refdef(&mut self, typ: LinkType) {
+        if self.opts.refdef_policy != RefDefPolicy::AfterFirstUse {
+            return;
+        }
+        let Some(label) = self.refdef_label(typ) else {
+            return;
+        };
+        let used = self
+            .refdefs
+            .iter()
+            .position(|v| normalize_label(&v.label) == label);
+        if let Some(i) =
+            used.filter(|&i| !self.refdefs[i].written && !self.used_refdefs.contains(&i))
+        {
+            self.used_refdefs.push(i);
+        }
+    }
+
+    // Returns the label of the reference definition that a long inline link is
+    // written with: one with the same destination and title, or a new one
+    // labelled by the slug of the link text.
+    fn link_reference(&mut self, text: &str, dest: &str, title: &str) -> String {
+        let title = (!title.is_empty()).then(|| title.to_string());
+        let i = match self
+            .refdefs
+            .iter()
+            .position(|v| v.dest == dest && v.title == title)
+        {
+            Some(i) => i,
+            None => {
+                let slug = slug(text);
+                let slug = if slug.is_empty() {
+                    "link".to_string()
+                } else {
+                    slug
+                };
+                let label = (1..)
+                    .map(|i| match i {
+                        1 => slug.clone(),
+                        _ => format!("{slug}-{i}"),
+                    })
+                    .find(|label| {
+                        let label = normalize_label(label);
+                        !self
+                            .refdefs
+                            .iter()
+                            .any(|v| normalize_label(&v.label) == label)
+                    })
+                    .unwrap_or_default();
+                // Definitions that are added follow those in the input.
+                let end = self.input.len();
+                self.refdefs.push(Reference {
+                    label,
+                    dest: dest.to_string(),
+                    title,
+                    span: end..end,
+                    verbatim: false,
+                    written: false,
+                });
+                self.refdefs.len() - 1
+            }
+        };
+        if self.opts.refdef_policy == RefDefPolicy::AfterFirstUse
+            && !self.refdefs[i].written
+            && !self.used_refdefs.contains(&i)
+        {
+            self.used_refdefs.push(i);
+        }
+        self.refdefs[i].label.clone()
+    }
+
+    // Returns the normalized label of the reference link at the current event,
+    // or of the definition kept in place of its removed duplicate.
+    fn refdef_label(&self, typ: LinkType) -> Option<String> {
+        let label = normalize_label(&self.source_label(typ)?);
+        match self.refdef_aliases.get(&label) {
+            Some(kept) => Some(normalize_label(kept)),
+            None => Some(label),
+        }
+    }
+
+    // Returns the label of the reference link at the current event as in the
+    // input, without the blockquote markers of its continuation lines.
+    fn source_label(&self, typ: LinkType) -> Option<String> {
+        let label = link_label(&self.input[self.range.clone()], typ)?;
+        let depth = self
+            .stack
+            .iter()
+            .filter(|v| matches!(v, StackItem::Blockquote(_)))
+            .count();
+        Some(strip_markers(label, depth))
+    }
+
+    // Writes the reference definitions first used within the top-level block
+    // that just ended.
+    fn write_used_refdefs(&mut self) -> fmt::Result {
+        let mut refdefs = std::mem::take(&mut self.refdefs);
+        let mut written = false;
+        for i in std::mem::take(&mut self.used_refdefs) {
+            let refdef = &mut refdefs[i];
+            if refdef.verbatim || refdef.written {
+                continue;
+            }
+            if !written {
+                self.write_newline_if_required()?;
+                self.prev_list = None;
+                written = true;
+            }
+            refdef.written = true;
+            self.write_refdef(&refdefs[i])?;
+        }
+        self.refdefs = refdefs;
+        if written {
+            self.newline_required = true;
+        }
+        Ok(())
+    }
+
+    // Writes the reference definitions that start before the offset in the
+    // input, when they're kept in place, returning true if any were written.
+    fn write_refdefs_before(
+        &mut self,
+        offset: usize,
+        after_html: bool,
+    ) -> Result<bool, fmt::Error> {
+        if self.opts.refdef_policy != RefDefPolicy::KeepInPlace {
+            return Ok(false);
+        }
+        let refdefs = std::mem::take(&mut self.refdefs);
+        let mut written = false;
+        while let Some(refdef) = refdefs
+            .get(self.next_refdef)
+            .filter(|v| v.span.start < offset)
+        {
+            self.next_refdef += 1;
+            if refdef.verbatim {
+                continue;
+            }
+            if !written {
+                // The definitions would otherwise continue an HTML block or
+                // the text of a tight list item.
+                if !self.text_buf.is_empty() {
+                    self.write_newline()?;
+                }
+                if after_html || !self.last_line_blank {
+                    self.newline_required = true;
+                }
+                self.write_newline_if_required()?;
+                self.prev_list = None;
+                written = true;
+            }
+            self.write_refdef(refdef)?;
+        }
+        self.refdefs = refdefs;
+        if written {
+            self.newline_required = true;
+        }
+        Ok(written)
+    }
+
+    // Writes the end of a reference link of the type with the label, in the
+    // form of the `ReferenceLinks` setting. Links are written in full unless
+    // their formatted text, which starts at `start`, matches the label.
+    fn write_reference_end(
+        &mut self,
+        typ: LinkType,
+        label: &str,
+        start: Option<usize>,
+    ) -> fmt::Result {
+        let text = start
+            .and_then(|start| self.text_buf.get(start + 1..))
+            .unwrap_or_default();
+        let matches = normalize_label(text) == normalize_label(label);
+        let shortcut = matches!(typ, LinkType::Shortcut | LinkType::ShortcutUnknown);
+        let collapsed = matches!(typ, LinkType::Collapsed | LinkType::CollapsedUnknown);
+        if self.opts.reference_links == ReferenceLinks::Preserve && (shortcut || collapsed) {
+            // The text is written as in the input if formatting it, like its
+            // emphasis markers, changed the label it refers to.
+            if let (false, Some(start)) = (matches, start) {
+                self.text_buf.truncate(start + 1);
+                self.write_str(label)?;
+            }
+            return if shortcut {
+                self.write_char(']')
+            } else {
+                self.write_str("][]")
+            };
+        }
+        let form = match self.opts.reference_links {
+            ReferenceLinks::Preserve => ReferenceLinks::Full,
+            form => form,
+        };
+        // The source of collapsed links may not include their `[]`.
+        let rest = &self.input[self.range.end..];
+        let rest = match typ {
+            LinkType::Collapsed | LinkType::CollapsedUnknown => {
+                rest.strip_prefix("[]").unwrap_or(rest)
+            }
+            _ => rest,
+        };
+        match form {
+            ReferenceLinks::Shortcut if matches && !rest.starts_with(['(', '[', ':']) => {
+                self.write_char(']')
+            }
+            ReferenceLinks::Collapsed | ReferenceLinks::Shortcut if matches => {
+                self.write_str("][]")
+            }
+            _ => {
+                self.write_str("][")?;
+                self.write_str(&self.opts.label_case.apply(label))?;
+                self.write_char(']')
+            }
+        }
+    }
+
+    // Writes the destination and title of an inline link or image, after its
+    // text. They're never broken across lines.
+    fn write_inline_dest(&mut self, dest: &str, title: &str) -> fmt::Result {
+        let start = self.text_buf.len();
+        self.write_str("](")?;
+        self.write_str(&link_destination(dest))?;
+        if !title.is_empty() {
+            self.write_char(' ')?;
+            self.write_str(&self.opts.title_quote.apply(title))?;
+        }
+        self.write_char(')')?;
+        self.unbreakable.push(start..self.text_buf.len());
+        Ok(())
+    }
+
+    fn write_refdef(&mut self, refdef: &Reference) -> fmt::Result {
+        self.write_char('[')?;
+        self.write_str(&refdef.label)?;
+        self.write_str("]: ")?;
+        match refdef.dest.as_str() {
+            "" => self.write_str("<>")?,
+            dest => self.write_str(&link_destination(dest))?,
+        }
+        if let Some(title) = &refdef.title {
+            self.write_char(' ')?;
+            self.write_str(&self.opts.title_quote.apply(title))?;
+        }
+        self.write_newline()
+    }
+
     fn write_newline_if_required(&mut self) -> fmt::Result {
         if self.newline_required {
-            self.write_newline()?;
+            if self.opts.blockquote_join == BlockquoteJoin::Split
+                && matches!(self.stack.last(), Some(StackItem::Blockquote(_)))
+            {
+                let blockquote = self.stack.pop();
+                self.write_blank_lines()?;
+                self.stack.extend(blockquote);
+            } else {
+                self.write_blank_lines()?;
+            }
             self.newline_required = false;
         }
         Ok(())
     }
 
+    fn write_blank_lines(&mut self) -> fmt::Result {
+        let mut blank_lines = self.blank_lines.take().unwrap_or(1);
+        if self.stack.is_empty() && self.opts.max_blank_lines > 1 {
+            let before = &self.input[..self.range.start];
+            let before = &before[..before.rfind('\n').map_or(0, |i| i + 1)];
+            let input = before
+                .lines()
+                .rev()
+                .take_while(|line| line.trim().is_empty())
+                .count();
+            blank_lines = blank_lines.max(input.min(self.opts.max_blank_lines));
+        }
+        for i in 0..blank_lines {
+            // Consecutive blank lines are otherwise collapsed by `write_line`.
+            if i > 0 {
+                self.last_line_blank = false;
+            }
+            self.write_newline()?;
+        }
+        Ok(())
+    }
+
     fn write_newline_if_content(&mut self) -> fmt::Result {
         if !self.text_buf.is_empty() || !self.stack.is_empty() {
             self.write_newline()?;
@@ -562,8 +5122,39 @@ impl<'a, W: fmt::Write> Context<'a, W> {
         Ok(())
     }
 
+    // Returns a fence for the buffered code block, longer than any run of the
+    // fence character starting one of its lines.
+    fn code_fence(&self) -> String {
+        let c = self.opts.code_fence.char();
+        let longest = self
+            .text_buf
+            .lines()
+            .map(|line| line.trim_start().chars().take_while(|&v| v == c).count())
+            .max()
+            .unwrap_or_default();
+        c.to_string().repeat((longest + 1).max(3))
+    }
+
+    // Returns the character underlining headings of the level, if they are
+    // written in the setext style.
+    fn setext_underline(&self, lvl: HeadingLevel) -> Option<char> {
+        match (self.opts.heading_style, self.shift_heading(lvl)) {
+            (HeadingStyle::Setext, HeadingLevel::H1) => Some('='),
+            (HeadingStyle::Setext, HeadingLevel::H2) => Some('-'),
+            _ => None,
+        }
+    }
+
+    // Returns the level that headings of the level are written at.
+    fn shift_heading(&self, lvl: HeadingLevel) -> HeadingLevel {
+        let lvl = (lvl as i8)
+            .saturating_add(self.opts.heading_shift)
+            .clamp(1, 6);
+        HeadingLevel::try_from(lvl as usize).unwrap_or(HeadingLevel::H6)
+    }
+
     fn write_heading_level(&mut self, lvl: HeadingLevel) -> fmt::Result {
-        match lvl {
+        match self.shift_heading(lvl) {
             HeadingLevel::H1 => self.write_str("# "),
             HeadingLevel::H2 => self.write_str("## "),
             HeadingLevel::H3 => self.write_str("### "),
@@ -581,11 +5172,21 @@ impl<'a, W: fmt::Write> Context<'a, W> {
         self.text_buf.write_char(c)
     }
 
+    // Returns true if trailing whitespace is kept in HTML and code blocks.
+    fn keep_trailing_whitespace(&self) -> bool {
+        self.opts.trailing_whitespace == TrailingWhitespace::KeepInBlocks
+    }
+
     fn write_newline(&mut self) -> fmt::Result {
         self.write_newline_with_trim(true)
     }
 
     fn write_newline_with_trim(&mut self, trim: bool) -> fmt::Result {
+        if let (Some(width), true) = (self.opts.line_width, self.is_wrapping()) {
+            let width = width.saturating_sub(self.padding_width());
+            self.text_buf = wrap::reflow(&self.text_buf, &self.unbreakable, width);
+        }
+        self.unbreakable.clear();
         if !self.text_buf.is_empty() {
             let mut text_buf = std::mem::take(&mut self.text_buf);
             for line in text_buf.lines() {
@@ -600,55 +5201,106 @@ impl<'a, W: fmt::Write> Context<'a, W> {
     }
 
     fn write_line(&mut self, line: &str, trim: bool) -> fmt::Result {
-        self.write_padding_to_scratch()?;
+        self.write_padding_to_scratch(line)?;
         self.scratch.write_str(line)?;
-        let buf = if trim {
+        // The padding of lines without content is always trimmed.
+        let buf = if trim || line.is_empty() {
             self.scratch.trim_end()
         } else {
             &self.scratch
         };
         if !buf.is_empty() || !self.last_line_blank {
+            if self.newline_pending {
+                self.writer.write_str(self.opts.line_ending)?;
+            }
             self.writer.write_str(buf)?;
-            self.writer.write_char('\n')?;
+            self.newline_pending = true;
         }
         self.last_line_blank = buf.is_empty();
         self.scratch.clear();
         Ok(())
     }
 
-    fn write_padding_to_scratch(&mut self) -> fmt::Result {
-        for item in self.stack.iter_mut() {
-            match item {
-                StackItem::Blockquote => {
+    fn is_wrapping(&self) -> bool {
+        self.wrapping
+            && (self.opts.line_width.is_some()
+                || self.opts.line_break_style == LineBreakStyle::Join)
+            && self.code_block.is_none()
+    }
+
+    // Returns the width of the padding written before each line.
+    fn padding_width(&self) -> usize {
+        let footnote = self.innermost_footnote();
+        self.stack
+            .iter()
+            .enumerate()
+            .map(|(i, item)| match item {
+                StackItem::Blockquote(_) => {
+                    self.opts.blockquote_str.chars().count() + self.opts.blockquote_spacing
+                }
+                StackItem::CodeIndent => 4,
+                StackItem::Footnote(..) if footnote != Some(i) => 0,
+                StackItem::Footnote(label, false) => label.chars().count() + 5,
+                StackItem::Footnote(_, true) => 4,
+                StackItem::List(marker, ..) if marker.continued => marker.continuation(&self.opts),
+                StackItem::List(marker, ..) => marker.indent(&self.opts),
+            })
+            .sum()
+    }
+
+    // Returns the stack index of the innermost footnote definition. The parser
+    // nests the definitions that follow one ending in a list, so only the
+    // innermost is written, keeping them at the same level.
+    fn innermost_footnote(&self) -> Option<usize> {
+        self.stack
+            .iter()
+            .rposition(|item| matches!(item, StackItem::Footnote(..)))
+    }
+
+    fn write_padding_to_scratch(&mut self, line: &str) -> fmt::Result {
+        let lazy = std::mem::take(&mut self.lazy_line);
+        let footnote = self.innermost_footnote();
+        for i in 0..self.stack.len() {
+            let spacing = self.blockquote_spacing(i, line);
+            let code = matches!(self.stack.get(i + 1), Some(StackItem::CodeIndent));
+            match &mut self.stack[i] {
+                StackItem::Blockquote(_) if lazy => {}
+                StackItem::Blockquote(_) => {
                     self.scratch.write_str(self.opts.blockquote_str)?;
-                    self.scratch.write_char(' ')?
+                    for _ in 0..spacing {
+                        self.scratch.write_char(' ')?;
+                    }
                 }
                 StackItem::CodeIndent => self.scratch.write_str("    ")?,
-                StackItem::List(l, written, _) => {
+                StackItem::Footnote(..) if footnote != Some(i) => {}
+                // Content after the first line is indented to continue the
+                // definition.
+                StackItem::Footnote(label, written) => {
                     if *written {
-                        match l {
-                            None => {
-                                for _ in 0..self.opts.unordered_list_str.chars().count() + 1 {
-                                    self.scratch.write_char(' ')?;
-                                }
-                            }
-                            Some(n) => {
-                                for _ in 0..n.chars().count() + 2 {
-                                    self.scratch.write_char(' ')?;
-                                }
-                            }
+                        self.scratch.write_str("    ")?;
+                    } else {
+                        *written = true;
+                        self.scratch.write_str("[^")?;
+                        self.scratch.write_str(label)?;
+                        self.scratch.write_str("]: ")?;
+                    }
+                }
+                StackItem::List(marker, written, _) => {
+                    let mut indent = marker.indent(&self.opts);
+                    if *written {
+                        // Indented code is relative to the text after the
+                        // marker.
+                        if marker.continued && !code {
+                            indent = marker.continuation(&self.opts);
+                        }
+                        for _ in 0..indent {
+                            self.scratch.write_char(' ')?;
                         }
                     } else {
                         *written = true;
-                        match l {
-                            None => {
-                                self.scratch.write_str(self.opts.unordered_list_str)?;
-                                self.scratch.write_char(' ')?
-                            }
-                            Some(n) => {
-                                self.scratch.write_str(n)?;
-                                self.scratch.write_str(". ")?
-                            }
+                        self.scratch.write_str(&marker.text)?;
+                        for _ in marker.text.chars().count()..indent {
+                            self.scratch.write_char(' ')?;
                         }
                     }
                 }
@@ -656,12 +5308,277 @@ impl<'a, W: fmt::Write> Context<'a, W> {
         }
         Ok(())
     }
+
+    // Returns the number of spaces to write after the blockquote marker at
+    // stack index `i`. The first space after a marker is consumed by the
+    // parser, so exactly one is required when the rest of the line depends on
+    // its indentation.
+    fn blockquote_spacing(&self, i: usize, line: &str) -> usize {
+        let spacing = self.opts.blockquote_spacing;
+        let indented = match self.stack.get(i + 1) {
+            Some(StackItem::CodeIndent) => return 1,
+            Some(StackItem::List(_, written, _) | StackItem::Footnote(_, written)) => *written,
+            Some(StackItem::Blockquote(compact)) => {
+                return match self.opts.blockquote_nesting {
+                    BlockquoteNesting::Spaced => spacing,
+                    BlockquoteNesting::Compact => 0,
+                    BlockquoteNesting::Preserve if *compact => 0,
+                    BlockquoteNesting::Preserve => spacing.max(1),
+                };
+            }
+            None => line.starts_with(char::is_whitespace),
+        };
+        if spacing == 0 && indented {
+            1
+        } else {
+            spacing
+        }
+    }
+}
+
+// Returns the anchor generated for a heading with the text, as GitHub does:
+// lowercase, without punctuation, and with spaces replaced by hyphens.
+fn slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+// Returns the ranges of the wikilinks in the input: an unescaped `[[`,
+// followed by text without brackets or line breaks, and `]]`.
+fn wikilinks(input: &str) -> Vec<Range<usize>> {
+    let mut links = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = input[pos..].find("[[").map(|i| pos + i) {
+        pos = start + 1;
+        let escapes = input[..start].len() - input[..start].trim_end_matches('\\').len();
+        if escapes % 2 == 1 {
+            continue;
+        }
+        let rest = &input[start + 2..];
+        let Some(len) = rest.find(['[', ']', '\n', '\r']) else {
+            break;
+        };
+        if len > 0 && rest[len..].starts_with("]]") {
+            links.push(start..start + len + 4);
+            pos = start + len + 4;
+        }
+    }
+    links
+}
+
+// Returns the link destination as written, in angle brackets if it contains
+// whitespace or unbalanced parentheses, or starts with `<`, so that it's parsed
+// as one destination.
+fn link_destination(dest: &str) -> Cow<'_, str> {
+    let mut depth = 0usize;
+    let mut balanced = true;
+    let mut chars = dest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '(' => depth += 1,
+            ')' if depth == 0 => balanced = false,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    if balanced && depth == 0 && !dest.starts_with('<') && !dest.contains(char::is_whitespace) {
+        return Cow::Borrowed(dest);
+    }
+    let mut out = String::with_capacity(dest.len() + 2);
+    out.push('<');
+    for c in dest.chars() {
+        if matches!(c, '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('>');
+    Cow::Owned(out)
+}
+
+// Returns the number of the ordered list item starting the input.
+fn item_number(input: &str) -> Option<u64> {
+    input
+        .trim_start()
+        .split(|c: char| !c.is_ascii_digit())
+        .next()
+        .and_then(|v| v.parse().ok())
+}
+
+// Returns the numbers of the items of every ordered list in the input, by the
+// offset of the list.
+fn ordered_list_items(input: &str, opts: POptions) -> HashMap<usize, Vec<Option<u64>>> {
+    let mut lists = HashMap::new();
+    let mut stack: Vec<Option<usize>> = Vec::new();
+    for (event, range) in Parser::new_ext(input, opts).into_offset_iter() {
+        match event {
+            Event::Start(Tag::List(start)) => {
+                stack.push(start.map(|_| range.start));
+                if start.is_some() {
+                    lists.insert(range.start, Vec::new());
+                }
+            }
+            Event::End(Tag::List(_)) => {
+                stack.pop();
+            }
+            Event::Start(Tag::Item) => {
+                if let Some(Some(offset)) = stack.last() {
+                    if let Some(numbers) = lists.get_mut(offset) {
+                        numbers.push(item_number(&input[range.start..]));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    lists
+}
+
+// Returns true if the strong or emphasis span at `inner` is directly nested in
+// the one at `outer`, with nothing but delimiters between their boundaries.
+fn is_bold_italic(outer: &Range<usize>, inner: &Range<usize>) -> bool {
+    let open = inner.start - outer.start;
+    (1..=2).contains(&open) && outer.end - inner.end == open
+}
+
+fn is_punctuation(c: char) -> bool {
+    matches!(c, '.' | ',' | ';' | ':' | '!' | '?')
+}
+
+// Returns the cells of the delimiter row of a table, with the widths of its
+// columns.
+fn delimiter_row(widths: &[usize], alignments: &[Alignment]) -> Vec<String> {
+    widths
+        .iter()
+        .zip(alignments.iter())
+        .map(|(w, a)| {
+            let mut delimiter = String::with_capacity(*w);
+            delimiter.push(if matches!(a, Alignment::Left | Alignment::Center) {
+                ':'
+            } else {
+                '-'
+            });
+            for _ in 0..*w - 2 {
+                delimiter.push('-');
+            }
+            delimiter.push(if matches!(a, Alignment::Right | Alignment::Center) {
+                ':'
+            } else {
+                '-'
+            });
+            delimiter
+        })
+        .collect()
+}
+
+// Returns true if the source of a table row is a caption, like `Table: caption`
+// or `[caption]`, that's parsed as a row as it immediately follows a table.
+fn is_table_caption(source: &str) -> bool {
+    let source = source.trim();
+    !source.contains('|')
+        && (source
+            .get(..6)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("table:"))
+            || (source.len() > 2 && source.starts_with('[') && source.ends_with(']')))
+}
+
+// Splits the source of table cells at the pipes that aren't escaped.
+fn split_cells(source: &str) -> Vec<&str> {
+    let source = source.trim();
+    let source = source.strip_prefix('|').unwrap_or(source);
+    let source = match source.strip_suffix('|') {
+        Some(s) if !s.ends_with('\\') => s,
+        _ => source,
+    };
+    if source.trim().is_empty() {
+        return Vec::new();
+    }
+    let mut cells = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in source.char_indices() {
+        match c {
+            '\\' => escaped = !escaped,
+            '|' if !escaped => {
+                cells.push(source[start..i].trim());
+                start = i + 1;
+            }
+            _ => escaped = false,
+        }
+    }
+    cells.push(source[start..].trim());
+    cells
+}
+
+// Returns true if the text of a table cell is a number, optionally signed,
+// with thousands separators, a decimal point, or a percent sign. The sign may
+// be escaped.
+fn is_number(text: &str) -> bool {
+    let text = text.strip_prefix('\\').unwrap_or(text);
+    let text = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let text = text.strip_suffix('%').unwrap_or(text);
+    text.starts_with(|c: char| c.is_ascii_digit())
+        && text.ends_with(|c: char| c.is_ascii_digit())
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ',' || c == '.')
+        && text.matches('.').count() <= 1
+}
+
+// Compares the text of table cells, as numbers if both are, and as
+// case-insensitive text otherwise.
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    let number = |text: &str| {
+        is_number(text)
+            .then(|| text.replace(['\\', ',', '%', '+'], "").parse::<f64>().ok())
+            .flatten()
+    };
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+// Returns the width of the text in columns.
+fn text_width(text: &str, mode: WidthMode) -> usize {
+    match mode {
+        #[cfg(feature = "unicode-width")]
+        WidthMode::Display => unicode_width::UnicodeWidthStr::width(text),
+        _ => text.chars().count(),
+    }
 }
 
 struct Table {
     alignments: Vec<Alignment>,
     head: Vec<String>,
     body: Vec<Vec<String>>,
+    // The source ranges of the header and body rows.
+    rows: Vec<Range<usize>>,
+    // The end of the last cell of the current row.
+    cell_end: usize,
+    // The end of the source of the current row.
+    row_end: usize,
+    // The number of cells of the current row missing from the input.
+    missing: usize,
+    // The widths of the columns, once the header of a streamed table is
+    // written.
+    widths: Option<Vec<usize>>,
+    // The directive before the table, overriding the formatting settings.
+    directive: TableDirective,
+    // The source range of the last row, if it's a caption rather than cells.
+    caption: Option<Range<usize>>,
+    // Whether a row has more or fewer cells than the header.
+    ragged: bool,
+    // Whether the table was converted from an HTML block, so has no source
+    // rows.
+    html: bool,
 }
 
 impl Table {
@@ -670,20 +5587,75 @@ impl Table {
             alignments,
             head: Vec::new(),
             body: Vec::new(),
+            rows: Vec::new(),
+            cell_end: 0,
+            row_end: 0,
+            missing: 0,
+            widths: None,
+            directive: TableDirective::default(),
+            caption: None,
+            ragged: false,
+            html: false,
+        }
+    }
+
+    // Returns true if the rows can't be written without outer pipes, as the
+    // table would have a single column, or a row would lose a cell or start a
+    // new block.
+    fn needs_pipes(&self) -> bool {
+        self.head.len() < 2
+            || std::iter::once(&self.head).chain(&self.body).any(|row| {
+                let first = row.first().map_or("", |cell| cell.as_str());
+                let last = row.last().map_or("", |cell| cell.as_str());
+                first.is_empty()
+                    || last.is_empty()
+                    || !wrap::can_start_line(first.split(' ').next().unwrap_or_default())
+            })
+    }
+
+    // Aligns the columns without an alignment to the right if every nonempty
+    // cell of their body is a number.
+    fn align_numeric_columns(&mut self) {
+        for (i, alignment) in self.alignments.iter_mut().enumerate() {
+            let mut cells = self
+                .body
+                .iter()
+                .map(|row| row[i].as_str())
+                .filter(|cell| !cell.is_empty())
+                .peekable();
+            if *alignment == Alignment::None && cells.peek().is_some() && cells.all(is_number) {
+                *alignment = Alignment::Right;
+            }
+        }
+    }
+
+    // Adds empty cells so that the header and every row have as many cells as
+    // the longest row.
+    fn pad_columns(&mut self) {
+        let columns = self
+            .body
+            .iter()
+            .map(Vec::len)
+            .fold(self.head.len(), usize::max);
+        self.head.resize(columns, String::new());
+        self.alignments.resize(columns, Alignment::None);
+        for row in self.body.iter_mut() {
+            row.resize(columns, String::new());
         }
     }
 
-    fn column_widths(&self) -> Vec<usize> {
+    fn column_widths(&self, width: impl Fn(&str) -> usize, max: Option<usize>) -> Vec<usize> {
         self.head
             .iter()
             .enumerate()
             .map(|(i, h)| {
                 self.body
                     .iter()
-                    .map(|b| b.get(i).map(|b| b.chars().count()).unwrap_or(0))
+                    .map(|b| b.get(i).map(|b| width(b)).unwrap_or(0))
                     .max()
                     .unwrap_or_default()
-                    .max(h.chars().count())
+                    .max(width(h))
+                    .min(max.unwrap_or(usize::MAX))
                     .max(3)
             })
             .collect()
@@ -694,4 +5666,9 @@ struct Reference {
     label: String,
     dest: String,
     title: Option<String>,
+    span: Range<usize>,
+    // Whether the definition is copied through within a verbatim region.
+    verbatim: bool,
+    // Whether the definition has been written after its first use.
+    written: bool,
 }
@@ -0,0 +1,305 @@
+//! Extraction and checking of the links and images in markdown.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{Event, LinkType, Parser, Tag};
+
+use crate::{parser_options, slug, Diagnostic, Rule};
+
+type OffsetEvent<'a> = (Event<'a>, Range<usize>);
+
+/// A link, along with its location in the input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkInfo {
+    /// The type of the link.
+    pub kind: LinkType,
+    /// The plain text of the link, without its inline markup. Line breaks
+    /// within the text are replaced by spaces.
+    pub text: String,
+    /// The destination of the link, from its definition for reference links.
+    pub dest: String,
+    /// The title of the link, or an empty string if it has none.
+    pub title: String,
+    /// The byte range of the link in the input.
+    pub range: Range<usize>,
+}
+
+/// An image, along with its location in the input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageInfo {
+    /// The type of the image.
+    pub kind: LinkType,
+    /// The plain text of the image's alt text, without its inline markup.
+    /// Line breaks within the text are replaced by spaces.
+    pub alt: String,
+    /// The destination of the image, from its definition for reference
+    /// images.
+    pub dest: String,
+    /// The title of the image, or an empty string if it has none.
+    pub title: String,
+    /// The byte range of the image in the input.
+    pub range: Range<usize>,
+}
+
+/// Returns the links of the markdown input, in the order they appear, using
+/// the same parsing options as the `Formatter`. Links within link reference
+/// definitions and HTML are not included.
+///
+/// Example:
+///
+/// ```
+/// use cmarkfmt::LinkType;
+///
+/// let input = "See [the *docs*](/docs \"Docs\") and [home].\n\n[home]: /";
+/// let links = cmarkfmt::extract_links(input);
+/// assert_eq!(links.len(), 2);
+/// assert_eq!(links[0].kind, LinkType::Inline);
+/// assert_eq!(links[0].text, "the docs");
+/// assert_eq!(links[0].dest, "/docs");
+/// assert_eq!(links[0].title, "Docs");
+/// assert_eq!(&input[links[1].range.clone()], "[home]");
+/// assert_eq!(links[1].dest, "/");
+/// ```
+pub fn extract_links(input: &str) -> Vec<LinkInfo> {
+    let mut links = Vec::new();
+    let mut current: Option<LinkInfo> = None;
+    for (event, range) in Parser::new_ext(input, parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link(kind, dest, title)) => {
+                current = Some(LinkInfo {
+                    kind,
+                    text: String::new(),
+                    dest: dest.to_string(),
+                    title: title.to_string(),
+                    range: link_range(input, kind, range),
+                });
+            }
+            Event::End(Tag::Link(..)) => links.extend(current.take()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(link) = current.as_mut() {
+                    link.text.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(link) = current.as_mut() {
+                    link.text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    links
+}
+
+/// Returns the images of the markdown input, in the order they appear, using
+/// the same parsing options as the `Formatter`. Images within links are
+/// included, but not those in HTML.
+///
+/// Example:
+///
+/// ```
+/// let input = "![A *logo*](logo.png \"Logo\")\n\n[![Badge][b]](https://example.com)\n\n[b]: badge.svg";
+/// let images = cmarkfmt::extract_images(input);
+/// assert_eq!(images.len(), 2);
+/// assert_eq!(images[0].alt, "A logo");
+/// assert_eq!(images[0].dest, "logo.png");
+/// assert_eq!(images[0].title, "Logo");
+/// assert_eq!(&input[images[1].range.clone()], "![Badge][b]");
+/// assert_eq!(images[1].dest, "badge.svg");
+/// ```
+pub fn extract_images(input: &str) -> Vec<ImageInfo> {
+    let mut images = Vec::new();
+    // Images may be nested within the alt text of others.
+    let mut open: Vec<ImageInfo> = Vec::new();
+    for (event, range) in Parser::new_ext(input, parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Image(kind, dest, title)) => {
+                open.push(ImageInfo {
+                    kind,
+                    alt: String::new(),
+                    dest: dest.to_string(),
+                    title: title.to_string(),
+                    range: link_range(input, kind, range),
+                });
+            }
+            Event::End(Tag::Image(..)) => {
+                let Some(image) = open.pop() else {
+                    continue;
+                };
+                if let Some(outer) = open.last_mut() {
+                    outer.alt.push_str(&image.alt);
+                }
+                images.push(image);
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(image) = open.last_mut() {
+                    image.alt.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(image) = open.last_mut() {
+                    image.alt.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    images.sort_by_key(|image| image.range.start);
+    images
+}
+
+/// Reports the links and images outside of the verbatim regions whose
+/// relative destination, resolved against the directory `base`, doesn't
+/// exist, or whose fragment isn't the anchor of a heading in the markdown
+/// file it points to.
+pub(crate) fn check(
+    events: &[OffsetEvent],
+    input: &str,
+    verbatim: &[Range<usize>],
+    base: &Path,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut anchors = HashMap::new();
+    for (event, range) in events {
+        let (Event::Start(Tag::Link(kind, dest, _)) | Event::Start(Tag::Image(kind, dest, _))) =
+            event
+        else {
+            continue;
+        };
+        if verbatim.iter().any(|v| v.contains(&range.start)) || !is_relative(dest) {
+            continue;
+        }
+        let dest = percent_decode(dest);
+        let (path, fragment) = match dest.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment)),
+            None => (dest.as_str(), None),
+        };
+        let path = path.split('?').next().unwrap_or_default();
+        let message = if path.is_empty() {
+            let found = anchors
+                .entry(PathBuf::new())
+                .or_insert_with(|| Some(heading_anchors(input)));
+            match (fragment, found) {
+                (Some(fragment), Some(found))
+                    if !fragment.is_empty() && !found.contains(fragment) =>
+                {
+                    format!("heading `#{fragment}` doesn't exist")
+                }
+                _ => continue,
+            }
+        } else {
+            let target = base.join(path);
+            if !target.exists() {
+                format!("link target `{path}` doesn't exist")
+            } else {
+                let found = anchors.entry(target.clone()).or_insert_with(|| {
+                    let markdown = target
+                        .extension()
+                        .is_some_and(|ext| ext == "md" || ext == "markdown");
+                    markdown
+                        .then(|| fs::read_to_string(&target).ok())
+                        .flatten()
+                        .map(|input| heading_anchors(&input))
+                });
+                match (fragment, found) {
+                    (Some(fragment), Some(found))
+                        if !fragment.is_empty() && !found.contains(fragment) =>
+                    {
+                        format!("heading `#{fragment}` doesn't exist in `{path}`")
+                    }
+                    _ => continue,
+                }
+            }
+        };
+        let range = link_range(input, *kind, range.clone());
+        diagnostics.push(Diagnostic::new(input, Rule::BrokenLink, message, range));
+    }
+}
+
+// Returns whether the destination is relative to the document, rather than a
+// URL with a scheme, like `https:` or `mailto:`, or an absolute path.
+fn is_relative(dest: &str) -> bool {
+    let scheme = dest.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    !dest.is_empty() && !scheme && !dest.starts_with('/')
+}
+
+// Decodes the percent-encoded bytes of the destination, like `%20`.
+fn percent_decode(dest: &str) -> String {
+    let bytes = dest.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = dest
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).unwrap_or_else(|_| dest.to_string())
+}
+
+// Returns the anchors of the headings of the markdown input: their IDs, or
+// anchors generated from their text, made unique as renderers do.
+fn heading_anchors(input: &str) -> HashSet<String> {
+    let mut anchors = HashSet::new();
+    let mut heading: Option<(Option<String>, String)> = None;
+    for event in Parser::new_ext(input, parser_options()) {
+        match event {
+            Event::Start(Tag::Heading(_, id, _)) => {
+                heading = Some((id.map(str::to_string), String::new()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, heading)) = heading.as_mut() {
+                    heading.push_str(&text);
+                }
+            }
+            Event::End(Tag::Heading(..)) => {
+                let Some((id, text)) = heading.take() else {
+                    continue;
+                };
+                let anchor = id.unwrap_or_else(|| slug(&text));
+                if !anchors.contains(&anchor) {
+                    anchors.insert(anchor);
+                    continue;
+                }
+                let unique = (1..)
+                    .map(|i| format!("{anchor}-{i}"))
+                    .find(|v| !anchors.contains(v))
+                    .unwrap_or_default();
+                anchors.insert(unique);
+            }
+            _ => {}
+        }
+    }
+    anchors
+}
+
+// Returns the range of a link or image, as the parser doesn't include the
+// `[]` of collapsed references.
+fn link_range(input: &str, kind: LinkType, range: Range<usize>) -> Range<usize> {
+    match kind {
+        LinkType::Collapsed | LinkType::CollapsedUnknown
+            if input[range.end..].starts_with("[]") =>
+        {
+            range.start..range.end + 2
+        }
+        _ => range,
+    }
+}
@@ -0,0 +1,171 @@
+//! Diagnostics reported while formatting markdown.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::{FormatError, Formatter};
+
+/// A rule that can report diagnostics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Rule {
+    /// A link with an empty destination or empty text.
+    EmptyLink,
+    /// An image with an empty destination.
+    EmptyImage,
+    /// A paragraph of strong text used in place of a heading.
+    BoldHeading,
+    /// A heading with the same anchor as an earlier heading.
+    DuplicateHeading,
+    /// A tab character in text outside of code.
+    HardTab,
+    /// A table row with more or fewer cells than the header.
+    RaggedTableRow,
+    /// A link reference definition that no link uses.
+    UnusedDefinition,
+    /// A link reference definition with the same destination and title as
+    /// an earlier definition.
+    DuplicateDefinition,
+    /// A link or image whose relative destination doesn't exist.
+    BrokenLink,
+    /// An image without alt text.
+    MissingAltText,
+    /// Blocks nested deeper than the maximum depth, which can't be
+    /// formatted.
+    MaxDepth,
+    /// Input that formatting would change, as reported by tools checking
+    /// formatting.
+    Unformatted,
+}
+
+impl Rule {
+    /// Returns the identifier of the rule, e.g. `"empty-link"`.
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::EmptyLink => "empty-link",
+            Rule::EmptyImage => "empty-image",
+            Rule::BoldHeading => "bold-heading",
+            Rule::DuplicateHeading => "duplicate-heading",
+            Rule::HardTab => "hard-tab",
+            Rule::RaggedTableRow => "ragged-table-row",
+            Rule::UnusedDefinition => "unused-definition",
+            Rule::DuplicateDefinition => "duplicate-definition",
+            Rule::BrokenLink => "broken-link",
+            Rule::MissingAltText => "missing-alt-text",
+            Rule::MaxDepth => "max-depth",
+            Rule::Unformatted => "unformatted",
+        }
+    }
+
+    /// Returns a short description of the problems the rule reports.
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::EmptyLink => "Links should have a destination and text.",
+            Rule::EmptyImage => "Images should have a destination.",
+            Rule::BoldHeading => "Headings should be used instead of strong paragraphs.",
+            Rule::DuplicateHeading => "Headings should have unique anchors.",
+            Rule::HardTab => "Text should be indented with spaces instead of tabs.",
+            Rule::RaggedTableRow => "Table rows should have as many cells as the header.",
+            Rule::UnusedDefinition => "Reference definitions should be used.",
+            Rule::DuplicateDefinition => "Reference definitions should be unique.",
+            Rule::BrokenLink => "Relative links should point to existing files and headings.",
+            Rule::MissingAltText => "Images should have alt text.",
+            Rule::MaxDepth => "Blocks should be nested no deeper than the maximum depth.",
+            Rule::Unformatted => "Markdown should be formatted.",
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// A problem found in a markdown document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The rule that reported the problem.
+    pub rule: Rule,
+    /// A description of the problem.
+    pub message: String,
+    /// The byte range of the problem in the input.
+    pub range: Range<usize>,
+    /// The 1-based line of the start of `range`.
+    pub line: usize,
+    /// The 1-based column, in characters, of the start of `range`.
+    pub column: usize,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(input: &str, rule: Rule, message: String, range: Range<usize>) -> Self {
+        let before = &input[..range.start];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Diagnostic {
+            rule,
+            message,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            range,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {} [{}]",
+            self.line, self.column, self.message, self.rule
+        )
+    }
+}
+
+impl<'a> Formatter<'a> {
+    /// Lint markdown, returning the diagnostics reported by the rules enabled
+    /// on this `Formatter`. If blocks are nested deeper than the maximum
+    /// depth, the only diagnostic is a `Rule::MaxDepth` one for the line
+    /// where formatting stopped.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use cmarkfmt::{EmptyLinks, Formatter, Rule};
+    ///
+    /// let cmfmt = Formatter::default().with_empty_links(EmptyLinks::Diagnose);
+    /// let diagnostics = cmfmt.lint("Read [the docs]().");
+    /// assert_eq!(diagnostics.len(), 1);
+    /// assert_eq!(diagnostics[0].rule, Rule::EmptyLink);
+    /// assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 6));
+    /// ```
+    pub fn lint(&self, input: &str) -> Vec<Diagnostic> {
+        match self.format_with_diagnostics(input, Sink) {
+            Ok(diagnostics) => diagnostics,
+            Err(FormatError::MaxDepth(line)) => {
+                let start = input
+                    .split_inclusive('\n')
+                    .take(line - 1)
+                    .map(str::len)
+                    .sum::<usize>();
+                let end = input[start..].find('\n').map_or(input.len(), |i| start + i);
+                let message = "blocks are nested deeper than the maximum depth";
+                vec![Diagnostic::new(
+                    input,
+                    Rule::MaxDepth,
+                    message.to_string(),
+                    start..end,
+                )]
+            }
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// A writer that discards its output.
+struct Sink;
+
+impl fmt::Write for Sink {
+    fn write_str(&mut self, _: &str) -> fmt::Result {
+        Ok(())
+    }
+}
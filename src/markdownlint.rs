@@ -0,0 +1,353 @@
+//! Mapping of markdownlint configuration onto `Formatter` settings.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    BulletStyle, EmptyLinks, FenceChar, FinalNewline, FormatterConfig, HeadingStyle,
+    OrderedListNumbering,
+};
+
+/// The rules enabled in a markdownlint configuration, as read from a
+/// `.markdownlint.jsonc`, `.markdownlint.json`, or `.markdownlint.yaml` file,
+/// used to configure the formatter so that its output passes them.
+///
+/// Rules are looked up by name, like `MD004`, or alias, like `ul-style`. Rules
+/// that aren't listed are enabled unless `default` is false. The rules mapped
+/// onto settings are:
+///
+/// - `MD003`/`heading-style`: the heading style, from its `style`.
+/// - `MD004`/`ul-style`: the unordered list string, from its `style`, or
+///   `BulletStyle::Depth` if `sublist`.
+/// - `MD012`/`no-multiple-blanks`: the maximum number of blank lines, from
+///   its `maximum`.
+/// - `MD013`/`line-length`: the line width, from its `line_length` or 80,
+///   if the rule is listed.
+/// - `MD022`/`blanks-around-headings`: the blank lines before and after
+///   headings, from its `lines_above` and `lines_below`.
+/// - `MD027`/`no-multiple-space-blockquote`: a single space after blockquote
+///   markers.
+/// - `MD029`/`ol-prefix`: the ordered list numbering, from its `style`.
+/// - `MD042`/`no-empty-links`: `EmptyLinks::Diagnose`, so that empty links
+///   are reported by `Formatter::lint`.
+/// - `MD048`/`code-fence-style`: the code fence character, from its `style`.
+/// - `MD047`/`single-trailing-newline`: `FinalNewline::Ensure`.
+/// - `MD049`/`emphasis-style`: the emphasis string, from its `style`.
+/// - `MD050`/`strong-style`: the strong emphasis string, from its `style`.
+///
+/// `MD028`/`no-blanks-blockquote` isn't mapped, as joining blockquotes that
+/// are separated by a blank line changes the document. Set `blockquote_join`
+/// to satisfy it.
+///
+/// Example:
+///
+/// ```
+/// use cmarkfmt::{Formatter, FormatterConfig, Markdownlint};
+///
+/// let markdownlint = Markdownlint::from_json(r#"{
+///     "default": true,
+///     "MD004": { "style": "asterisk" },
+///     "emphasis-style": { "style": "asterisk" }
+/// }"#).unwrap();
+///
+/// let mut config = FormatterConfig::default();
+/// markdownlint.apply(&mut config);
+/// let cmfmt = Formatter::from(&config);
+/// assert_eq!(cmfmt.format_cmark("- _text_"), "* *text*\n");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Markdownlint {
+    rules: Map<String, Value>,
+}
+
+impl Markdownlint {
+    /// The names of markdownlint configuration files, in the order they are
+    /// looked for.
+    pub const FILE_NAMES: [&'static str; 4] = [
+        ".markdownlint.jsonc",
+        ".markdownlint.json",
+        ".markdownlint.yaml",
+        ".markdownlint.yml",
+    ];
+
+    /// Parses a configuration from JSON, which may hold comments and trailing
+    /// commas, as markdownlint allows.
+    pub fn from_json(s: &str) -> Result<Self, MarkdownlintError> {
+        serde_json::from_str(&strip_jsonc(s))
+            .map(|rules| Markdownlint { rules })
+            .map_err(|err| MarkdownlintError::Json(None, err))
+    }
+
+    /// Parses a configuration from YAML.
+    pub fn from_yaml(s: &str) -> Result<Self, MarkdownlintError> {
+        serde_yaml::from_str::<Option<_>>(s)
+            .map(|rules| Markdownlint {
+                rules: rules.unwrap_or_default(),
+            })
+            .map_err(|err| MarkdownlintError::Yaml(None, err))
+    }
+
+    /// Loads the configuration file at `path`, parsed as JSON unless its
+    /// extension is `.yaml` or `.yml`.
+    pub fn load(path: &Path) -> Result<Self, MarkdownlintError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| MarkdownlintError::Io(path.to_path_buf(), err))?;
+        let is_yaml = path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml");
+        let result = if is_yaml {
+            Self::from_yaml(&contents)
+        } else {
+            Self::from_json(&contents)
+        };
+        result.map_err(|err| match err {
+            MarkdownlintError::Json(_, err) => {
+                MarkdownlintError::Json(Some(path.to_path_buf()), err)
+            }
+            MarkdownlintError::Yaml(_, err) => {
+                MarkdownlintError::Yaml(Some(path.to_path_buf()), err)
+            }
+            err => err,
+        })
+    }
+
+    /// Finds and loads the configuration for the file at `path`, from the
+    /// nearest markdownlint configuration file in its directory or any parent
+    /// directory. Returns `None` if there isn't one.
+    pub fn discover(path: &Path) -> Result<Option<Self>, MarkdownlintError> {
+        for dir in path.ancestors().skip(1) {
+            for name in Self::FILE_NAMES {
+                let file = dir.join(name);
+                if file.is_file() {
+                    return Self::load(&file).map(Some);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns true if the rule, known by any of `names`, is enabled.
+    pub fn is_enabled(&self, names: &[&str]) -> bool {
+        match self.rule(names) {
+            Some(Value::Bool(enabled)) => *enabled,
+            Some(Value::Object(_)) => true,
+            Some(_) => false,
+            None => !matches!(self.rules.get("default"), Some(Value::Bool(false))),
+        }
+    }
+
+    /// Sets every setting of `config` that is unset to one satisfying the
+    /// enabled rules. Settings that are already set are left alone.
+    pub fn apply(&self, config: &mut FormatterConfig) {
+        if self.is_enabled(&["MD003", "heading-style"]) {
+            let style = match self.style(&["MD003", "heading-style"]) {
+                Some("atx") => Some(HeadingStyle::Atx),
+                Some("setext" | "setext_with_atx") => Some(HeadingStyle::Setext),
+                _ => None,
+            };
+            if let Some(style) = style {
+                config.heading_style.get_or_insert(style);
+            }
+        }
+        if self.is_enabled(&["MD004", "ul-style"]) {
+            let marker = match self.style(&["MD004", "ul-style"]) {
+                Some("asterisk") => Some("*"),
+                Some("plus") => Some("+"),
+                Some("dash") => Some("-"),
+                _ => None,
+            };
+            if let Some(marker) = marker {
+                config
+                    .unordered_list
+                    .get_or_insert_with(|| marker.to_string());
+            } else if self.style(&["MD004", "ul-style"]) == Some("sublist") {
+                config.bullet_style.get_or_insert(BulletStyle::Depth);
+            }
+        }
+        let blanks = ["MD012", "no-multiple-blanks"];
+        if self.is_enabled(&blanks) {
+            let maximum = self
+                .rule(&blanks)
+                .and_then(|rule| rule.get("maximum")?.as_u64());
+            if let Some(maximum) = maximum {
+                config.max_blank_lines.get_or_insert(maximum as usize);
+            }
+        }
+        let line_length = ["MD013", "line-length"];
+        if self.rule(&line_length).is_some() && self.is_enabled(&line_length) {
+            let width = self
+                .rule(&line_length)
+                .and_then(|rule| rule.get("line_length"))
+                .and_then(Value::as_u64)
+                .unwrap_or(80);
+            config.line_width.get_or_insert(width as usize);
+        }
+        let blanks = ["MD022", "blanks-around-headings"];
+        if self.is_enabled(&blanks) {
+            let lines = |key| {
+                let lines = self.rule(&blanks)?.get(key)?.as_u64()?;
+                Some(lines as usize)
+            };
+            if let Some(lines) = lines("lines_above") {
+                config.blank_lines_before_headings.get_or_insert(lines);
+            }
+            if let Some(lines) = lines("lines_below") {
+                config.blank_lines_after_headings.get_or_insert(lines);
+            }
+        }
+        if self.is_enabled(&["MD027", "no-multiple-space-blockquote"]) {
+            config.blockquote_spacing.get_or_insert(1);
+        }
+        if self.is_enabled(&["MD029", "ol-prefix"]) {
+            let numbering = match self.style(&["MD029", "ol-prefix"]) {
+                Some("one") => Some(OrderedListNumbering::AllOnes),
+                Some("ordered") => Some(OrderedListNumbering::Sequential),
+                _ => None,
+            };
+            if let Some(numbering) = numbering {
+                config.ordered_list_numbering.get_or_insert(numbering);
+            }
+        }
+        if self.is_enabled(&["MD042", "no-empty-links"]) {
+            config.empty_links.get_or_insert(EmptyLinks::Diagnose);
+        }
+        if self.is_enabled(&["MD047", "single-trailing-newline"]) {
+            config.final_newline.get_or_insert(FinalNewline::Ensure);
+        }
+        if self.is_enabled(&["MD048", "code-fence-style"]) {
+            let fence = match self.style(&["MD048", "code-fence-style"]) {
+                Some("backtick") => Some(FenceChar::Backtick),
+                Some("tilde") => Some(FenceChar::Tilde),
+                _ => None,
+            };
+            if let Some(fence) = fence {
+                config.code_fence.get_or_insert(fence);
+            }
+        }
+        if self.is_enabled(&["MD049", "emphasis-style"]) {
+            let emphasis = match self.style(&["MD049", "emphasis-style"]) {
+                Some("asterisk") => Some("*"),
+                Some("underscore") => Some("_"),
+                _ => None,
+            };
+            if let Some(emphasis) = emphasis {
+                config.emphasis.get_or_insert_with(|| emphasis.to_string());
+            }
+        }
+        if self.is_enabled(&["MD050", "strong-style"]) {
+            let strong = match self.style(&["MD050", "strong-style"]) {
+                Some("asterisk") => Some("**"),
+                Some("underscore") => Some("__"),
+                _ => None,
+            };
+            if let Some(strong) = strong {
+                config.strong.get_or_insert_with(|| strong.to_string());
+            }
+        }
+    }
+
+    fn rule(&self, names: &[&str]) -> Option<&Value> {
+        self.rules
+            .iter()
+            .find(|(key, _)| names.iter().any(|name| key.eq_ignore_ascii_case(name)))
+            .map(|(_, value)| value)
+    }
+
+    fn style(&self, names: &[&str]) -> Option<&str> {
+        self.rule(names)?.get("style")?.as_str()
+    }
+}
+
+/// Returns the JSON with comments and trailing commas replaced by spaces, so
+/// that errors keep their positions.
+fn strip_jsonc(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                out.push(' ');
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    out.push(' ');
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                out.push_str("  ");
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    out.push(if c == '\n' { c } else { ' ' });
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            ('}' | ']', _) => {
+                let trimmed = out.trim_end().len();
+                if out[..trimmed].ends_with(',') {
+                    out.replace_range(trimmed - 1..trimmed, " ");
+                }
+                out.push(c);
+            }
+            _ => {
+                in_string = c == '"';
+                out.push(c);
+            }
+        }
+    }
+    out
+}
+
+/// The error returned when a markdownlint configuration can't be loaded.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MarkdownlintError {
+    /// The configuration file at the path couldn't be read.
+    Io(PathBuf, io::Error),
+    /// The JSON configuration, from the file at the path if any, is invalid.
+    Json(Option<PathBuf>, serde_json::Error),
+    /// The YAML configuration, from the file at the path if any, is invalid.
+    Yaml(Option<PathBuf>, serde_yaml::Error),
+}
+
+impl fmt::Display for MarkdownlintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkdownlintError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            MarkdownlintError::Json(Some(path), err) => write!(f, "{}: {err}", path.display()),
+            MarkdownlintError::Json(None, err) => write!(f, "{err}"),
+            MarkdownlintError::Yaml(Some(path), err) => write!(f, "{}: {err}", path.display()),
+            MarkdownlintError::Yaml(None, err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for MarkdownlintError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MarkdownlintError::Io(_, err) => Some(err),
+            MarkdownlintError::Json(_, err) => Some(err),
+            MarkdownlintError::Yaml(_, err) => Some(err),
+        }
+    }
+}
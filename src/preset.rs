@@ -0,0 +1,159 @@
+//! Named formatting presets, and the mapping of file names to them.
+
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::glob::glob_match;
+
+/// A named bundle of `Formatter` settings tuned for a particular kind of
+/// document.
+///
+/// Example:
+///
+/// ```
+/// use cmarkfmt::{Formatter, Preset};
+///
+/// let cmfmt = Formatter::preset(Preset::Readme);
+/// let output = cmfmt.format_cmark("<p align=\"center\">\n  <b>Hi</b>\n</p>");
+/// assert_eq!(output, "<p align=\"center\">\n  <b>Hi</b>\n</p>\n");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Preset {
+    /// The settings of `Formatter::default()`.
+    #[default]
+    Default,
+    /// Tuned for project READMEs. Centered HTML headers, badge rows, and
+    /// tables of badges are copied through verbatim, and prose is never
+    /// wrapped.
+    Readme,
+    /// For documents rendered by GitHub: GitHub Flavored Markdown, with `-`
+    /// bullets and `_` emphasis, and the markers of alerts, like `> [!NOTE]`,
+    /// kept unescaped.
+    Github,
+    /// For mdBook chapters: GitHub Flavored Markdown with footnotes, `-`
+    /// bullets, and `*` emphasis, as in Rust documentation.
+    Mdbook,
+    /// For renderers supporting only CommonMark: extensions are disabled, and
+    /// `*` is used for bullets and emphasis, as in the CommonMark spec.
+    CommonmarkStrict,
+    /// Matches the output of Prettier's markdown formatter: `-` bullets, `_`
+    /// emphasis, emphasis nested within strong text, and the list numbering,
+    /// table padding, and hard breaks of `Formatter::with_prettier`.
+    Prettier,
+}
+
+impl Preset {
+    /// Returns the name of the preset, as accepted by `Preset::from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Default => "default",
+            Preset::Readme => "readme",
+            Preset::Github => "github",
+            Preset::Mdbook => "mdbook",
+            Preset::CommonmarkStrict => "commonmark-strict",
+            Preset::Prettier => "prettier",
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = ParsePresetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "default" => Ok(Preset::Default),
+            "readme" => Ok(Preset::Readme),
+            "github" => Ok(Preset::Github),
+            "mdbook" => Ok(Preset::Mdbook),
+            "commonmark-strict" => Ok(Preset::CommonmarkStrict),
+            "prettier" => Ok(Preset::Prettier),
+            _ => Err(ParsePresetError(s.to_string())),
+        }
+    }
+}
+
+/// The error returned when parsing an unknown `Preset` name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsePresetError(String);
+
+impl fmt::Display for ParsePresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown preset: {:?}", self.0)
+    }
+}
+
+impl Error for ParsePresetError {}
+
+/// Maps file name patterns to the `Preset` used to format matching files, so
+/// that a tree containing different kinds of markdown is formatted correctly.
+///
+/// Patterns are matched against the file name only, and support `*` (any
+/// sequence of characters), `?` (any single character), and `[...]` (any
+/// character in the class). When multiple
+/// patterns match, the one added last wins. The default map formats `*.md`
+/// and `*.markdown` files with `Preset::Default`.
+///
+/// Example:
+///
+/// ```
+/// use std::path::Path;
+/// use cmarkfmt::{Preset, PresetMap};
+///
+/// let map = PresetMap::default().with("README.md", Preset::Readme);
+/// assert_eq!(map.resolve(Path::new("docs/README.md")), Some(Preset::Readme));
+/// assert_eq!(map.resolve(Path::new("docs/guide.md")), Some(Preset::Default));
+/// assert_eq!(map.resolve(Path::new("src/main.rs")), None);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PresetMap {
+    entries: Vec<(String, Preset)>,
+}
+
+impl Default for PresetMap {
+    fn default() -> Self {
+        PresetMap::empty()
+            .with("*.md", Preset::Default)
+            .with("*.markdown", Preset::Default)
+    }
+}
+
+impl PresetMap {
+    /// Creates a map without any patterns.
+    pub fn empty() -> Self {
+        PresetMap {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a pattern mapping to the provided `Preset`, taking precedence over
+    /// all previously added patterns.
+    pub fn with(mut self, pattern: &str, preset: Preset) -> Self {
+        self.entries.push((pattern.to_string(), preset));
+        self
+    }
+
+    /// Returns the `Preset` for the provided path, or `None` if the file name
+    /// doesn't match any pattern.
+    pub fn resolve(&self, path: &Path) -> Option<Preset> {
+        let name = path.file_name()?.to_str()?;
+        self.entries
+            .iter()
+            .rev()
+            .find(|(pattern, _)| glob_match(pattern, name))
+            .map(|(_, preset)| *preset)
+    }
+}
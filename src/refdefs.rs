@@ -0,0 +1,125 @@
+//! Matching and cleanup of link reference definitions.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use pulldown_cmark::{Event, LinkType, Tag};
+use unicase::UniCase;
+
+use crate::{Diagnostic, Reference, Rule};
+
+type OffsetEvent<'a> = (Event<'a>, Range<usize>);
+
+/// Removes the reference definitions outside of the verbatim regions that no
+/// link uses, as images are written inline, and those with the same
+/// destination and title as an earlier definition, reporting each as a
+/// diagnostic. Duplicates used by collapsed or shortcut links are kept, as
+/// their label is their text. Returns the normalized labels of the removed
+/// duplicates, mapped to the label of the definition that's kept.
+pub(crate) fn prune(
+    refdefs: &mut Vec<Reference>,
+    events: &[OffsetEvent],
+    input: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> HashMap<String, String> {
+    // The normalized labels used by links, and those used as link text.
+    let mut used = HashSet::new();
+    let mut used_as_text = HashSet::new();
+    let mut depth = 0;
+    for (event, range) in events {
+        match event {
+            Event::Start(Tag::BlockQuote) => depth += 1,
+            Event::End(Tag::BlockQuote) => depth -= 1,
+            Event::Start(Tag::Link(typ, ..)) => {
+                let Some(label) = link_label(&input[range.clone()], *typ) else {
+                    continue;
+                };
+                let label = normalize_label(&strip_markers(label, depth));
+                if !matches!(typ, LinkType::Reference | LinkType::ReferenceUnknown) {
+                    used_as_text.insert(label.clone());
+                }
+                used.insert(label);
+            }
+            _ => {}
+        }
+    }
+
+    let mut order = (0..refdefs.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| refdefs[i].span.start);
+    let mut removed = vec![false; refdefs.len()];
+    let mut aliases = HashMap::new();
+    for (n, &i) in order.iter().enumerate() {
+        let refdef = &refdefs[i];
+        if refdef.verbatim {
+            continue;
+        }
+        let (rule, message) = if !used.contains(&normalize_label(&refdef.label)) {
+            let message = format!("reference definition `{}` is unused", refdef.label);
+            (Rule::UnusedDefinition, message)
+        } else if let Some(&kept) = order[..n].iter().find(|&&j| {
+            !removed[j]
+                && refdefs[j].dest == refdef.dest
+                && refdefs[j].title == refdef.title
+                && !used_as_text.contains(&normalize_label(&refdef.label))
+        }) {
+            let kept = &refdefs[kept].label;
+            aliases.insert(normalize_label(&refdef.label), kept.clone());
+            let message = format!(
+                "reference definition `{}` duplicates `{kept}`",
+                refdef.label
+            );
+            (Rule::DuplicateDefinition, message)
+        } else {
+            continue;
+        };
+        diagnostics.push(Diagnostic::new(input, rule, message, refdef.span.clone()));
+        removed[i] = true;
+    }
+
+    let mut removed = removed.into_iter();
+    refdefs.retain(|_| !removed.next().unwrap_or_default());
+    aliases
+}
+
+/// Returns the label of a reference link from its source, like `label` in
+/// `[text][label]`, or `text` in `[text][]` and `[text]`.
+pub(crate) fn link_label(source: &str, typ: LinkType) -> Option<&str> {
+    match typ {
+        LinkType::Reference | LinkType::ReferenceUnknown => {
+            let start = source.rfind('[')?;
+            source[start + 1..].strip_suffix(']')
+        }
+        // The source of collapsed links may not include their `[]`.
+        LinkType::Collapsed
+        | LinkType::CollapsedUnknown
+        | LinkType::Shortcut
+        | LinkType::ShortcutUnknown => {
+            let source = source.strip_suffix("[]").unwrap_or(source);
+            source.strip_prefix('[')?.strip_suffix(']')
+        }
+        _ => None,
+    }
+}
+
+/// Returns the label of a reference link from its source without the markers
+/// of the `depth` blockquotes around its continuation lines.
+pub(crate) fn strip_markers(label: &str, depth: usize) -> String {
+    let mut lines = label.split('\n');
+    let mut out = lines.next().unwrap_or_default().to_string();
+    for line in lines {
+        let mut line = line.trim_start();
+        for _ in 0..depth {
+            line = line.strip_prefix('>').unwrap_or(line).trim_start();
+        }
+        out.push('\n');
+        out.push_str(line);
+    }
+    out
+}
+
+/// Returns the label with its case folded and its whitespace collapsed, as
+/// labels are matched.
+pub(crate) fn normalize_label(label: &str) -> String {
+    let label = label.split_whitespace().collect::<Vec<_>>().join(" ");
+    UniCase::new(label).to_folded_case()
+}
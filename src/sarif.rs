@@ -0,0 +1,85 @@
+//! Conversion of diagnostics to SARIF, for code-scanning dashboards.
+
+use serde_json::{json, Value};
+
+use crate::{Diagnostic, Rule};
+
+/// Returns a SARIF 2.1.0 log, as JSON, of the diagnostics reported for each
+/// file. Files are identified by their URI, usually a path relative to the
+/// repository root.
+///
+/// Example:
+///
+/// ```
+/// use cmarkfmt::{EmptyLinks, Formatter};
+///
+/// let cmfmt = Formatter::default().with_empty_links(EmptyLinks::Diagnose);
+/// let diagnostics = cmfmt.lint("Read [the docs]().");
+/// let log = cmarkfmt::sarif_log([("README.md", &diagnostics[..])]);
+/// assert!(log.contains(r#""ruleId":"empty-link""#));
+/// ```
+pub fn sarif_log<'a, I>(files: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, &'a [Diagnostic])>,
+{
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut results = Vec::new();
+    for (uri, diagnostics) in files {
+        for diagnostic in diagnostics {
+            let index = match rules.iter().position(|rule| *rule == diagnostic.rule) {
+                Some(index) => index,
+                None => {
+                    rules.push(diagnostic.rule);
+                    rules.len() - 1
+                }
+            };
+            results.push(result(uri, diagnostic, index));
+        }
+    }
+
+    let rules = rules
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id(),
+                "shortDescription": { "text": rule.description() },
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "cmarkfmt",
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+    .to_string()
+}
+
+fn result(uri: &str, diagnostic: &Diagnostic, rule_index: usize) -> Value {
+    json!({
+        "ruleId": diagnostic.rule.id(),
+        "ruleIndex": rule_index,
+        "level": "warning",
+        "message": { "text": diagnostic.message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": uri },
+                "region": {
+                    "startLine": diagnostic.line,
+                    "startColumn": diagnostic.column,
+                    "byteOffset": diagnostic.range.start,
+                    "byteLength": diagnostic.range.len(),
+                },
+            },
+        }],
+    })
+}
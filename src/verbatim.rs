@@ -0,0 +1,330 @@
+//! Detection of source regions that are copied through without formatting.
+
+use std::ops::{Range, RangeInclusive};
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::blocks::{blocks, line_range, BlockInfo};
+use crate::parser_options;
+
+/// Returns the regions of the input outside of the top-level blocks selected
+/// by the filter. Leading and trailing blank lines are excluded from each
+/// region.
+pub(crate) fn unselected_regions(
+    input: &str,
+    filter: impl Fn(&BlockInfo) -> bool,
+) -> Vec<Range<usize>> {
+    regions(input, filter, false)
+}
+
+/// Returns the regions of the input outside of the top-level blocks
+/// overlapping any of the 1-based line ranges. The blank lines separating a
+/// region from the formatted blocks around it are included, so that they're
+/// copied through unchanged.
+pub(crate) fn unselected_lines(input: &str, lines: &[RangeInclusive<usize>]) -> Vec<Range<usize>> {
+    let newlines = input
+        .match_indices('\n')
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let line = |offset: usize| newlines.partition_point(|&i| i < offset) + 1;
+    let filter = |block: &BlockInfo| {
+        let start = line(block.range.start);
+        let end = line(block.range.end.saturating_sub(1).max(block.range.start));
+        lines
+            .iter()
+            .any(|lines| *lines.start() <= end && start <= *lines.end())
+    };
+    regions(input, filter, true)
+}
+
+fn regions(
+    input: &str,
+    filter: impl Fn(&BlockInfo) -> bool,
+    separators: bool,
+) -> Vec<Range<usize>> {
+    let mut regions = Vec::new();
+    let mut start = 0;
+    for block in blocks(input) {
+        if filter(&block) {
+            push_trimmed(&mut regions, input, start..block.range.start, separators);
+            start = block.range.end;
+        }
+    }
+    push_trimmed(&mut regions, input, start..input.len(), separators);
+    regions
+}
+
+// Pushes the region without its leading and trailing blank lines, or only
+// without those at the start or end of the input if the separators are kept.
+// Regions that are only blank lines are dropped.
+fn push_trimmed(
+    regions: &mut Vec<Range<usize>>,
+    input: &str,
+    range: Range<usize>,
+    separators: bool,
+) {
+    let mut start = range.start;
+    for line in input[range.clone()].split_inclusive('\n') {
+        if !line.trim().is_empty() {
+            break;
+        }
+        start += line.len();
+    }
+    if start >= range.end {
+        return;
+    }
+    if separators && range.start > 0 {
+        start = range.start;
+    }
+    if separators && range.end < input.len() {
+        regions.push(start..range.end);
+    } else {
+        regions.push(line_range(input, start..range.end));
+    }
+}
+
+/// Sorts the regions, merging any that overlap.
+pub(crate) fn merge(mut regions: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    regions.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(regions.len());
+    for region in regions {
+        match merged.last_mut() {
+            Some(last) if region.start <= last.end => last.end = last.end.max(region.end),
+            _ => merged.push(region),
+        }
+    }
+    merged
+}
+
+/// Returns the sorted, non-overlapping byte ranges of README decorations:
+/// HTML blocks with an `align` attribute (through their closing tag),
+/// paragraphs consisting only of images, and tables containing badge images.
+pub(crate) fn readme_regions(input: &str) -> Vec<Range<usize>> {
+    let mut regions: Vec<Range<usize>> = Vec::new();
+    let mut block: Option<Block> = None;
+    let mut depth = 0usize;
+
+    for (event, range) in Parser::new_ext(input, parser_options()).into_offset_iter() {
+        if regions.last().is_some_and(|r| range.start < r.end) {
+            continue;
+        }
+
+        match event {
+            Event::Start(tag) => {
+                if depth == 0 {
+                    block = match tag {
+                        Tag::Paragraph => Some(Block::new(BlockKind::Paragraph)),
+                        Tag::Table(_) => Some(Block::new(BlockKind::Table)),
+                        _ => None,
+                    };
+                } else if let Some(block) = block.as_mut() {
+                    block.start(tag);
+                }
+                depth += 1;
+            }
+            Event::End(tag) => {
+                depth -= 1;
+                if depth == 0 {
+                    if block.take().is_some_and(|b| b.is_verbatim()) {
+                        regions.push(range);
+                    }
+                } else if let Some(block) = block.as_mut() {
+                    block.end(tag);
+                }
+            }
+            Event::Html(html) if depth == 0 => {
+                if let Some(name) = aligned_tag_name(&html) {
+                    if let Some(end) = closing_tag_end(input, range.start, &name) {
+                        regions.push(range.start..end);
+                    }
+                }
+            }
+            Event::Text(text) => {
+                if let Some(block) = block.as_mut() {
+                    block.text(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {}
+            _ => {
+                if let Some(block) = block.as_mut() {
+                    block.other = true;
+                }
+            }
+        }
+    }
+
+    regions
+}
+
+enum BlockKind {
+    Paragraph,
+    Table,
+}
+
+/// Summary of the inline content of a top-level block.
+struct Block {
+    kind: BlockKind,
+    image_depth: usize,
+    images: usize,
+    badges: usize,
+    other: bool,
+}
+
+impl Block {
+    fn new(kind: BlockKind) -> Self {
+        Block {
+            kind,
+            image_depth: 0,
+            images: 0,
+            badges: 0,
+            other: false,
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Image(_, dest, _) => {
+                self.image_depth += 1;
+                self.images += 1;
+                if is_badge_url(&dest) {
+                    self.badges += 1;
+                }
+            }
+            Tag::Link(..) => {}
+            _ => self.other |= self.image_depth == 0,
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        if let Tag::Image(..) = tag {
+            self.image_depth -= 1;
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.image_depth == 0 && !text.trim().is_empty() {
+            self.other = true;
+        }
+    }
+
+    fn is_verbatim(&self) -> bool {
+        match self.kind {
+            BlockKind::Paragraph => self.images > 0 && !self.other,
+            BlockKind::Table => self.badges > 0,
+        }
+    }
+}
+
+/// Hosts serving only badges.
+const BADGE_HOSTS: [&str; 10] = [
+    "badge.fury.io",
+    "badgen.net",
+    "ci.appveyor.com",
+    "circleci.com",
+    "codecov.io",
+    "coveralls.io",
+    "deps.rs",
+    "shields.io",
+    "travis-ci.com",
+    "travis-ci.org",
+];
+
+/// Returns true if `dest` is the URL of a badge: an image served by a badge
+/// service, or a `badge.svg` image, like those of GitHub workflows and
+/// docs.rs.
+fn is_badge_url(dest: &str) -> bool {
+    let dest = dest.to_ascii_lowercase();
+    let Some((_, rest)) = dest.split_once("://") else {
+        return false;
+    };
+    let (host, path) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    let host = host.rsplit('@').next().unwrap_or(host);
+    let host = host.split(':').next().unwrap_or(host);
+    let path = &path[..path.find(['?', '#']).unwrap_or(path.len())];
+    BADGE_HOSTS
+        .iter()
+        .any(|badge| host == *badge || host.ends_with(&format!(".{badge}")))
+        || path.ends_with("/badge.svg")
+}
+
+/// Returns the lowercase tag name if `html` opens an element with an `align`
+/// attribute, e.g. `<p align="center">`.
+fn aligned_tag_name(html: &str) -> Option<String> {
+    let tag = html.trim_start().strip_prefix('<')?;
+    let tag = &tag[..tag.find('>')?];
+    let name_len = tag
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(tag.len());
+    if name_len == 0 || !has_attribute(&tag[name_len..], "align") {
+        return None;
+    }
+    Some(tag[..name_len].to_ascii_lowercase())
+}
+
+/// Returns true if the attributes of a tag include one named `name`, e.g.
+/// `align` in ` valign=top align = "center"`.
+fn has_attribute(attrs: &str, name: &str) -> bool {
+    let mut rest = attrs;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        if rest.is_empty() {
+            return false;
+        }
+        let len = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len())
+            .max(1);
+        if rest[..len].eq_ignore_ascii_case(name) {
+            return true;
+        }
+        rest = rest[len..].trim_start();
+        let Some(value) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        rest = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => match value[1..].find(quote) {
+                Some(end) => &value[end + 2..],
+                None => "",
+            },
+            _ => {
+                let end = value
+                    .find(|c: char| c.is_ascii_whitespace())
+                    .unwrap_or(value.len());
+                &value[end..]
+            }
+        };
+    }
+}
+
+/// Returns the offset just past the line containing the tag closing the
+/// element named `name` that opens at `start`.
+fn closing_tag_end(input: &str, start: usize, name: &str) -> Option<usize> {
+    let lower = input[start..].to_ascii_lowercase();
+    let open = format!("<{name}");
+    let close = format!("</{name}");
+    let mut depth = 0usize;
+    let mut pos = 0;
+    while let Some(i) = lower[pos..].find('<') {
+        let at = pos + i;
+        let rest = &lower[at..];
+        if rest.starts_with(&close) && is_name_end(rest, close.len()) {
+            depth = depth.saturating_sub(1);
+            if depth == 0 {
+                let end = start + at;
+                return Some(match input[end..].find('\n') {
+                    Some(nl) => end + nl + 1,
+                    None => input.len(),
+                });
+            }
+        } else if rest.starts_with(&open) && is_name_end(rest, open.len()) {
+            depth += 1;
+        }
+        pos = at + 1;
+    }
+    None
+}
+
+fn is_name_end(s: &str, idx: usize) -> bool {
+    !matches!(s[idx..].chars().next(), Some(c) if c.is_ascii_alphanumeric())
+}
@@ -0,0 +1,138 @@
+//! Reflowing of inline text to a maximum line width.
+
+use std::ops::Range;
+
+/// Reflows the text so that lines are at most `width` characters, where
+/// possible. Text is only broken at spaces outside of the `unbreakable` byte
+/// ranges, never before a word that would start a new block if it began a
+/// line, and never after a backslash that would then be a hard line break.
+/// Existing line breaks are kept.
+pub(crate) fn reflow(text: &str, unbreakable: &[Range<usize>], width: usize) -> String {
+    let mut out = String::with_capacity(text.len() + 16);
+    let mut offset = 0;
+    for (i, segment) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let mut len = 0;
+        let mut escape = false;
+        for word in words(segment, offset, unbreakable) {
+            let word_len = word.chars().count();
+            if len > 0 {
+                if len + 1 + word_len > width && can_start_line(word) && !escape {
+                    out.push('\n');
+                    len = 0;
+                } else {
+                    out.push(' ');
+                    len += 1;
+                }
+            }
+            out.push_str(word);
+            len += word_len;
+            let backslashes = word.len() - word.trim_end_matches('\\').len();
+            escape = backslashes % 2 == 1;
+        }
+        // Trailing spaces may be a hard line break.
+        out.push_str(&segment[segment.trim_end_matches(' ').len()..]);
+        offset += segment.len() + 1;
+    }
+    out
+}
+
+/// Wraps the text of a table cell to `width` characters with line break `tag`s.
+/// Existing `<br>` tags, which are among the `unbreakable` byte ranges, are
+/// kept as line breaks.
+pub(crate) fn wrap_cell(
+    text: &str,
+    unbreakable: &[Range<usize>],
+    width: usize,
+    tag: &str,
+) -> String {
+    let mut out = String::with_capacity(text.len() + 16);
+    let mut start = 0;
+    let breaks = unbreakable
+        .iter()
+        .filter(|r| is_line_break(&text[(*r).clone()]))
+        .cloned()
+        .chain(std::iter::once(text.len()..text.len()));
+    for br in breaks {
+        let ranges = unbreakable
+            .iter()
+            .filter(|r| start <= r.start && r.end <= br.start)
+            .map(|r| r.start - start..r.end - start)
+            .collect::<Vec<_>>();
+        let segment = reflow(&text[start..br.start], &ranges, width);
+        out.push_str(&segment.replace('\n', tag));
+        out.push_str(&text[br.clone()]);
+        start = br.end;
+    }
+    out
+}
+
+/// Returns true if the HTML is a line break tag, like `<br>` or `<br/>`.
+pub(crate) fn is_line_break(html: &str) -> bool {
+    let tag = html.to_ascii_lowercase();
+    matches!(tag.as_str(), "<br>" | "<br/>" | "<br />")
+}
+
+/// Splits the text of a table cell into its lines, separated by line break
+/// tags.
+pub(crate) fn split_line_breaks(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(text);
+    std::iter::from_fn(move || {
+        let text = rest?;
+        let mut start = 0;
+        while let Some(i) = text[start..].find('<') {
+            let tag_start = start + i;
+            let tag_end = text[tag_start..].find('>').map(|j| tag_start + j + 1);
+            if let Some(end) = tag_end.filter(|&end| is_line_break(&text[tag_start..end])) {
+                rest = Some(&text[end..]);
+                return Some(&text[..tag_start]);
+            }
+            start = tag_start + 1;
+        }
+        rest = None;
+        Some(text)
+    })
+}
+
+/// Splits the segment, starting at `offset` in the text, into words separated
+/// by spaces outside of the unbreakable ranges.
+fn words<'a>(
+    segment: &'a str,
+    offset: usize,
+    unbreakable: &'a [Range<usize>],
+) -> impl Iterator<Item = &'a str> {
+    let mut start = 0;
+    let mut breaks = segment
+        .match_indices(' ')
+        .map(|(i, _)| i)
+        .filter(move |&i| !unbreakable.iter().any(|r| r.contains(&(offset + i))))
+        .chain(std::iter::once(segment.len()));
+    std::iter::from_fn(move || loop {
+        let end = breaks.next()?;
+        let word = &segment[start..end];
+        start = end + 1;
+        if !word.is_empty() {
+            return Some(word);
+        }
+    })
+}
+
+/// Returns false if the word, at the start of a line, could be parsed as the
+/// start of a block, like a list item, heading, or blockquote.
+pub(crate) fn can_start_line(word: &str) -> bool {
+    let Some(first) = word.chars().next() else {
+        return true;
+    };
+    match first {
+        '>' | '<' | '|' => false,
+        '#' | '-' | '+' | '*' | '=' | '_' => !word.chars().all(|c| c == first),
+        '`' | '~' => !word.starts_with("```") && !word.starts_with("~~~"),
+        '0'..='9' => {
+            let digits = word.chars().take_while(char::is_ascii_digit).count();
+            !(digits <= 9 && matches!(&word[digits..], "." | ")"))
+        }
+        _ => true,
+    }
+}
@@ -0,0 +1,467 @@
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use cmarkfmt::Config;
+
+fn cmarkfmt(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cmarkfmt"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn cmarkfmt_env(args: &[&str], env: &[(&str, &str)]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cmarkfmt"))
+        .args(args)
+        .envs(env.iter().copied())
+        .output()
+        .unwrap()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cmarkfmt-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_cli_stdin() {
+    let out = cmarkfmt(&["--unordered-list", "*"], "- one\n- two");
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "* one\n* two\n");
+}
+
+#[test]
+fn test_cli_in_place() {
+    let dir = temp_dir("in-place");
+    let path = dir.join("doc.md");
+    fs::write(&path, "# Title\nSome *text*.").unwrap();
+
+    let out = cmarkfmt(&["-i", path.to_str().unwrap()], "");
+    assert!(out.status.success());
+    assert!(out.stdout.is_empty());
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "# Title\n\nSome _text_.\n"
+    );
+}
+
+#[test]
+fn test_cli_check() {
+    let dir = temp_dir("check");
+    let formatted = dir.join("formatted.md");
+    let unformatted = dir.join("unformatted.md");
+    fs::write(&formatted, "# Title\n").unwrap();
+    fs::write(&unformatted, "# Title\n* item\n").unwrap();
+
+    let out = cmarkfmt(&["--check", formatted.to_str().unwrap()], "");
+    assert_eq!(out.status.code(), Some(0));
+    assert!(out.stdout.is_empty());
+
+    let out = cmarkfmt(
+        &[
+            "--check",
+            formatted.to_str().unwrap(),
+            unformatted.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert_eq!(out.status.code(), Some(1));
+    let name = unformatted.display();
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        format!(
+            "--- {name}\n+++ {name} (formatted)\n@@ -1,2 +1,3 @@\n # Title\n-* item\n+\n+- item\n"
+        )
+    );
+    assert_eq!(
+        fs::read_to_string(&unformatted).unwrap(),
+        "# Title\n* item\n"
+    );
+}
+
+#[test]
+fn test_cli_directory() {
+    let dir = temp_dir("directory");
+    fs::create_dir_all(dir.join("docs/nested")).unwrap();
+    fs::create_dir_all(dir.join(".hidden")).unwrap();
+    fs::write(dir.join("README.md"), "* one").unwrap();
+    fs::write(dir.join("docs/nested/guide.markdown"), "* two").unwrap();
+    fs::write(dir.join("docs/notes.txt"), "* three").unwrap();
+    fs::write(dir.join(".hidden/skipped.md"), "* four").unwrap();
+
+    let out = cmarkfmt(&["--write", dir.to_str().unwrap()], "");
+    assert!(out.status.success());
+    let read = |path: &str| fs::read_to_string(dir.join(path)).unwrap();
+    assert_eq!(read("README.md"), "- one\n");
+    assert_eq!(read("docs/nested/guide.markdown"), "- two\n");
+    assert_eq!(read("docs/notes.txt"), "* three");
+    assert_eq!(read(".hidden/skipped.md"), "* four");
+}
+
+#[test]
+fn test_cli_directory_presets() {
+    let dir = temp_dir("directory-presets");
+    fs::write(
+        dir.join(".cmarkfmt.toml"),
+        "[[presets]]\npattern = \"*.mdx\"\npreset = \"default\"\n",
+    )
+    .unwrap();
+    fs::write(dir.join("page.mdx"), "* one").unwrap();
+    fs::write(dir.join("notes.txt"), "* two").unwrap();
+
+    let out = cmarkfmt(&["--write", dir.to_str().unwrap()], "");
+    assert!(out.status.success());
+    let read = |path: &str| fs::read_to_string(dir.join(path)).unwrap();
+    assert_eq!(read("page.mdx"), "- one\n");
+    assert_eq!(read("notes.txt"), "* two");
+}
+
+#[test]
+fn test_cli_stdin_filepath() {
+    let out = cmarkfmt(
+        &["--comments", "--stdin-filepath", "src/lib.rs"],
+        "/// Some *text*.\nfn main() {}\n",
+    );
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "/// Some _text_.\nfn main() {}\n"
+    );
+
+    // Only doc comments are formatted by default.
+    let input = "/// Some *text*.\n// See [docs].\n//   Keep   spacing.\nint main() {}\n";
+    let out = cmarkfmt(&["--comments", "--stdin-filepath", "src/main.c"], input);
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "/// Some _text_.\n// See [docs].\n//   Keep   spacing.\nint main() {}\n"
+    );
+
+    let out = cmarkfmt(
+        &["--check", "--stdin-filepath", "docs/guide.md"],
+        "* item\n",
+    );
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stdout).starts_with("--- docs/guide.md\n"));
+
+    let out = cmarkfmt(&["--comments", "--stdin-filepath", "notes.txt"], "");
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).starts_with("cmarkfmt: notes.txt: "));
+}
+
+#[test]
+fn test_cli_ignore_file() {
+    let dir = temp_dir("ignore-file");
+    fs::create_dir_all(dir.join("docs/vendor")).unwrap();
+    fs::write(dir.join(".cmarkfmtignore"), "CHANGELOG.md\n/docs/vendor/\n").unwrap();
+    fs::write(dir.join("docs/.cmarkfmtignore"), "*.gen.md\n").unwrap();
+    fs::write(dir.join("CHANGELOG.md"), "* one").unwrap();
+    fs::write(dir.join("docs/vendor/lib.md"), "* two").unwrap();
+    fs::write(dir.join("docs/api.gen.md"), "* three").unwrap();
+    fs::write(dir.join("docs/guide.md"), "* four").unwrap();
+
+    let out = cmarkfmt(&["--write", dir.join("docs").to_str().unwrap()], "");
+    assert!(out.status.success());
+    let out = cmarkfmt(&["--write", dir.join("CHANGELOG.md").to_str().unwrap()], "");
+    assert!(out.status.success());
+    let read = |path: &str| fs::read_to_string(dir.join(path)).unwrap();
+    assert_eq!(read("CHANGELOG.md"), "* one");
+    assert_eq!(read("docs/vendor/lib.md"), "* two");
+    assert_eq!(read("docs/api.gen.md"), "* three");
+    assert_eq!(read("docs/guide.md"), "- four\n");
+}
+
+#[test]
+fn test_cli_check_json() {
+    let out = cmarkfmt(
+        &[
+            "--check",
+            "--output-format",
+            "json",
+            "--empty-links",
+            "diagnose",
+            "--stdin-filepath",
+            "doc.md",
+        ],
+        "# Title\n* [docs]()\n",
+    );
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        concat!(
+            r#"{"files":[{"changes":[{"end":19,"end_line":2,"line":2,"#,
+            r#""replacement":"\n- [docs]()\n","start":8}],"#,
+            r#""diagnostics":[{"column":3,"end":18,"line":2,"#,
+            r#""message":"link has an empty destination","rule":"empty-link","start":10}],"#,
+            r#""formatted":false,"path":"doc.md"}]}"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn test_cli_check_github() {
+    let out = cmarkfmt(
+        &[
+            "--check",
+            "--output-format",
+            "github",
+            "--empty-links",
+            "diagnose",
+            "--stdin-filepath",
+            "docs/a,b.md",
+        ],
+        "# Title\n* [docs]()\n",
+    );
+    assert_eq!(out.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        concat!(
+            "::error file=docs/a%2Cb.md,line=2,endLine=2,title=cmarkfmt::File is not formatted\n",
+            "::error file=docs/a%2Cb.md,line=2,col=3,title=empty-link::link has an empty destination\n",
+        )
+    );
+}
+
+#[test]
+fn test_cli_check_links() {
+    let dir = temp_dir("check-links");
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(
+        dir.join("sub/other.md"),
+        "# Top\n\n## Dup\n\n## Dup\n\n## Custom {#my-id}\n",
+    )
+    .unwrap();
+    fs::write(dir.join("sub/a b.png"), "").unwrap();
+    let path = dir.join("doc.md");
+    fs::write(
+        &path,
+        "# Intro\n\n[a](sub/other.md#dup-1) [b](sub/other.md#dup-2) [c](missing.md)\n\
+         [d](#intro) [e](#nope) ![f](sub/a%20b.png) [g](https://example.com/x)\n\
+         [h](sub/other.md#my-id) [i](sub/)\n",
+    )
+    .unwrap();
+
+    let out = cmarkfmt(
+        &[
+            "--check",
+            "--check-links",
+            "--output-format",
+            "github",
+            path.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert_eq!(out.status.code(), Some(0));
+    let name = path.display();
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        format!(
+            "::error file={name},line=3,col=25,title=broken-link::heading `#dup-2` doesn't exist in `sub/other.md`\n\
+             ::error file={name},line=3,col=49,title=broken-link::link target `missing.md` doesn't exist\n\
+             ::error file={name},line=4,col=13,title=broken-link::heading `#nope` doesn't exist\n"
+        )
+    );
+}
+
+#[test]
+#[cfg(feature = "sarif")]
+fn test_cli_check_sarif() {
+    let out = cmarkfmt(
+        &[
+            "--check",
+            "--output-format",
+            "sarif",
+            "--empty-links",
+            "diagnose",
+            "--stdin-filepath",
+            "doc.md",
+        ],
+        "# Title\n* [docs]()\n",
+    );
+    assert_eq!(out.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains(r#""version":"2.1.0""#));
+    assert!(stdout.contains(r#""rules":[{"id":"unformatted","#));
+    assert!(stdout.contains(r#""ruleId":"empty-link","ruleIndex":1"#));
+    assert!(stdout
+        .contains(r#""region":{"byteLength":8,"byteOffset":10,"startColumn":3,"startLine":2}"#));
+}
+
+#[test]
+fn test_cli_config() {
+    let dir = temp_dir("config");
+    fs::create_dir_all(dir.join("docs/api")).unwrap();
+    fs::write(
+        dir.join(Config::FILE_NAME),
+        "emphasis = \"*\"\nunordered_list = \"+\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("docs/api/.cmarkfmt.toml"),
+        "unordered_list = \"*\"\n",
+    )
+    .unwrap();
+    fs::write(dir.join("docs/guide.md"), "- _one_").unwrap();
+    fs::write(dir.join("docs/api/index.md"), "- _two_").unwrap();
+
+    let out = cmarkfmt(&["--write", dir.join("docs").to_str().unwrap()], "");
+    assert!(out.status.success());
+    let read = |path: &str| fs::read_to_string(dir.join(path)).unwrap();
+    assert_eq!(read("docs/guide.md"), "+ *one*\n");
+    assert_eq!(read("docs/api/index.md"), "* _two_\n");
+
+    let path = dir.join("docs/guide.md");
+    let out = cmarkfmt(&["--unordered-list", "-", path.to_str().unwrap()], "");
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "- *one*\n");
+
+    fs::write(dir.join(Config::FILE_NAME), "emphasis = 1\n").unwrap();
+    let out = cmarkfmt(&[path.to_str().unwrap()], "");
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("invalid type"));
+}
+
+#[test]
+fn test_cli_git_diff() {
+    let dir = temp_dir("git-diff");
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    let path = dir.join("doc.md");
+    fs::write(&path, "Some *text*.\n\n* one\n* two\n\nMore *text*.\n").unwrap();
+    git(&["init", "-q"]);
+    git(&["add", "doc.md"]);
+    git(&["commit", "-q", "-m", "init"]);
+
+    fs::write(
+        &path,
+        "Some *text*.\n\n* one\n* two\n* three\n\nMore *text*.\n",
+    )
+    .unwrap();
+    let out = cmarkfmt(&["--git-diff", "--write", path.to_str().unwrap()], "");
+    assert!(out.status.success());
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "Some *text*.\n\n- one\n- two\n- three\n\nMore *text*.\n"
+    );
+}
+
+#[test]
+fn test_cli_files_from() {
+    let dir = temp_dir("files-from");
+    fs::write(dir.join("one.md"), "* one").unwrap();
+    fs::write(dir.join("with space.md"), "* two").unwrap();
+    fs::write(dir.join("skipped.md"), "* three").unwrap();
+    let list = format!(
+        "{}\0{}\0",
+        dir.join("one.md").display(),
+        dir.join("with space.md").display()
+    );
+
+    let out = cmarkfmt(&["--write", "--files-from", "-"], &list);
+    assert!(out.status.success());
+    let read = |path: &str| fs::read_to_string(dir.join(path)).unwrap();
+    assert_eq!(read("one.md"), "- one\n");
+    assert_eq!(read("with space.md"), "- two\n");
+    assert_eq!(read("skipped.md"), "* three");
+
+    let out = cmarkfmt(&["--write", "--files-from", "-"], "");
+    assert!(out.status.success());
+}
+
+#[test]
+fn test_cli_config_precedence() {
+    let dir = temp_dir("config-precedence");
+    fs::write(dir.join(Config::FILE_NAME), "emphasis = \"*\"\n").unwrap();
+    fs::write(dir.join("other.toml"), "unordered_list = \"+\"\n").unwrap();
+    let path = dir.join("doc.md");
+    fs::write(&path, "- _one_").unwrap();
+    let path = path.to_str().unwrap();
+    let other = dir.join("other.toml");
+    let other = other.to_str().unwrap();
+
+    let out = cmarkfmt_env(&[path], &[("CMARKFMT_UNORDERED_LIST", "*")]);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "* *one*\n");
+
+    let out = cmarkfmt_env(
+        &["--unordered-list", "-", path],
+        &[("CMARKFMT_UNORDERED_LIST", "*")],
+    );
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "- *one*\n");
+
+    let out = cmarkfmt_env(&["--config", other, path], &[]);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "+ _one_\n");
+
+    let out = cmarkfmt_env(&[path], &[("CMARKFMT_CONFIG", other)]);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "+ _one_\n");
+
+    // Variables that aren't settings are ignored.
+    let out = cmarkfmt_env(&[path], &[("CMARKFMT_LOG", "debug")]);
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "- *one*\n");
+
+    let out = cmarkfmt_env(&[path], &[("CMARKFMT_BOLD_ITALIC", "sideways")]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("CMARKFMT_BOLD_ITALIC"));
+}
+
+#[test]
+fn test_cli_line_width() {
+    let dir = temp_dir("line-width");
+    fs::write(dir.join(".editorconfig"), "[*.md]\nmax_line_length = 10\n").unwrap();
+    let path = dir.join("doc.md");
+    fs::write(&path, "Some text to\nreflow.").unwrap();
+    let path = path.to_str().unwrap();
+
+    let out = cmarkfmt(&[path], "");
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "Some text\nto reflow.\n"
+    );
+
+    let out = cmarkfmt(&["--line-width", "0", path], "");
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "Some text to\nreflow.\n"
+    );
+}
+
+#[cfg(feature = "markdownlint")]
+#[test]
+fn test_cli_markdownlint_jsonc() {
+    let dir = temp_dir("markdownlint-jsonc");
+    fs::write(
+        dir.join(".markdownlint.jsonc"),
+        "{\n  // Lists use plus signs.\n  \"ul-style\": { \"style\": \"plus\" },\n}\n",
+    )
+    .unwrap();
+    let path = dir.join("doc.md");
+    fs::write(&path, "- one").unwrap();
+
+    let out = cmarkfmt_env(&[path.to_str().unwrap()], &[]);
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "+ one\n");
+}
@@ -3,6 +3,10 @@ use cmarkfmt::Formatter;
 mod suite;
 
 pub fn test_cmark(input: &str, expected: &str) {
-    let out = Formatter::default().format_cmark(input);
+    test_cmark_with(&Formatter::default(), input, expected);
+}
+
+pub fn test_cmark_with(cmfmt: &Formatter, input: &str, expected: &str) {
+    let out = cmfmt.format_cmark(input);
     assert_eq!(expected, &out);
 }
@@ -0,0 +1,100 @@
+#![cfg(feature = "lsp")]
+
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use serde_json::{json, Value};
+
+/// Runs the server over the messages, returning every message it writes.
+fn cmarkfmt_lsp(messages: &[Value]) -> Vec<Value> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cmarkfmt-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for msg in messages {
+        let body = msg.to_string();
+        write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    }
+    drop(stdin);
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+
+    let mut stdout = String::from_utf8(out.stdout).unwrap();
+    let mut responses = Vec::new();
+    while let Some((header, rest)) = stdout.split_once("\r\n\r\n") {
+        let len: usize = header["Content-Length: ".len()..].parse().unwrap();
+        responses.push(serde_json::from_str(&rest[..len]).unwrap());
+        stdout = rest[len..].to_string();
+    }
+    responses
+}
+
+fn find(responses: &[Value], id: i64) -> &Value {
+    responses.iter().find(|v| v["id"] == id).unwrap()
+}
+
+#[test]
+fn test_lsp() {
+    let dir = std::env::temp_dir().join(format!("cmarkfmt-{}-lsp", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(".cmarkfmt.toml"), "empty_links = \"diagnose\"\n").unwrap();
+    let uri = format!("file://{}", dir.join("doc.md").display());
+    let text = "# Title\nSome *text*.\n\n[empty]()\n";
+    let responses = cmarkfmt_lsp(&[
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"capabilities": {}}}),
+        json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+        json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {"uri": uri, "languageId": "markdown", "version": 1, "text": text},
+        }}),
+        json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/formatting", "params": {
+            "textDocument": {"uri": uri},
+            "options": {"tabSize": 2, "insertSpaces": true},
+        }}),
+        json!({"jsonrpc": "2.0", "id": 3, "method": "textDocument/rangeFormatting", "params": {
+            "textDocument": {"uri": uri},
+            "range": {"start": {"line": 3, "character": 0}, "end": {"line": 4, "character": 0}},
+            "options": {"tabSize": 2, "insertSpaces": true},
+        }}),
+        json!({"jsonrpc": "2.0", "id": 4, "method": "shutdown"}),
+        json!({"jsonrpc": "2.0", "method": "exit"}),
+    ]);
+
+    let capabilities = &find(&responses, 1)["result"]["capabilities"];
+    assert_eq!(capabilities["documentFormattingProvider"], true);
+    assert_eq!(capabilities["documentRangeFormattingProvider"], true);
+
+    assert_eq!(
+        find(&responses, 2)["result"],
+        json!([
+            {
+                "range": {"start": {"line": 1, "character": 0}, "end": {"line": 2, "character": 0}},
+                "newText": "",
+            },
+            {
+                "range": {"start": {"line": 2, "character": 0}, "end": {"line": 2, "character": 0}},
+                "newText": "Some _text_.\n\n",
+            },
+        ])
+    );
+    assert_eq!(find(&responses, 3)["result"], json!([]));
+
+    let diagnostics = responses
+        .iter()
+        .find(|v| v["method"] == "textDocument/publishDiagnostics")
+        .unwrap();
+    assert_eq!(diagnostics["params"]["uri"], uri);
+    assert_eq!(
+        diagnostics["params"]["diagnostics"],
+        json!([{
+            "range": {"start": {"line": 3, "character": 0}, "end": {"line": 3, "character": 9}},
+            "severity": 2,
+            "code": "empty-link",
+            "source": "cmarkfmt",
+            "message": "link has an empty destination",
+        }])
+    );
+}
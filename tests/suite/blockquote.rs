@@ -1,4 +1,6 @@
-use super::test_cmark;
+use cmarkfmt::{BlockquoteJoin, BlockquoteNesting, Formatter};
+
+use super::{test_cmark, test_cmark_with};
 
 #[test]
 fn test_blockquote1() {
@@ -71,3 +73,157 @@ fn test_blockquote5() {
 
     test_cmark(input, expected);
 }
+
+#[test]
+fn test_blockquote6() {
+    let input = r#"
+> Quoted
+>
+>     code
+> - List
+>   item"#;
+
+    let expected = r#">Quoted
+>
+>     code
+>
+>- List
+>   item
+"#;
+
+    let cmfmt = Formatter::default().with_blockquote_spacing(0);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_blockquote7() {
+    let input = r#"
+> Quoted
+>
+>     code"#;
+
+    let expected = r#">   Quoted
+>
+>     code
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_blockquote("> ")
+        .with_blockquote_spacing(3);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_blockquote8() {
+    let input = r#"
+> First quote
+
+> Second quote
+>
+> > Nested
+
+> Third quote"#;
+
+    let expected = r#"> First quote
+>
+> Second quote
+>
+> > Nested
+>
+> Third quote
+"#;
+
+    let cmfmt = Formatter::default().with_blockquote_join(BlockquoteJoin::Merge);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_blockquote9() {
+    let input = r#"
+> First paragraph
+>
+> Second paragraph
+> > Nested"#;
+
+    let expected = r#"> First paragraph
+
+> Second paragraph
+
+> > Nested
+"#;
+
+    let cmfmt = Formatter::default().with_blockquote_join(BlockquoteJoin::Split);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_blockquote10() {
+    let input = r#"
+> One
+>> Two
+>> > Three
+
+> > Four"#;
+
+    let nesting = |nesting| Formatter::default().with_blockquote_nesting(nesting);
+
+    let expected = r#"> One
+>
+> > Two
+> >
+> > > Three
+
+> > Four
+"#;
+    test_cmark_with(&nesting(BlockquoteNesting::Spaced), input, expected);
+
+    let expected = r#"> One
+>
+>> Two
+>>
+>>> Three
+
+>> Four
+"#;
+    test_cmark_with(&nesting(BlockquoteNesting::Compact), input, expected);
+
+    let expected = r#"> One
+>
+>> Two
+>>
+>> > Three
+
+> > Four
+"#;
+    test_cmark_with(&nesting(BlockquoteNesting::Preserve), input, expected);
+}
+
+#[test]
+fn test_blockquote11() {
+    let input = r#"
+> One
+lazy
+> Two
+
+- > Three
+  lazy"#;
+
+    let expected = r#"> One
+> lazy
+> Two
+
+- > Three
+  > lazy
+"#;
+    test_cmark(input, expected);
+
+    let expected = r#"> One
+lazy
+> Two
+
+- > Three
+  lazy
+"#;
+    let cmfmt = Formatter::default().with_lazy_continuation(true);
+    test_cmark_with(&cmfmt, input, expected);
+}
@@ -0,0 +1,160 @@
+use cmarkfmt::{blocks, BlockKind, Formatter, HeadingLevel};
+
+use super::test_cmark_with;
+
+#[test]
+fn test_blocks1() {
+    let input = r#"# Title
+
+Some *text*
+over two lines.
+
+<div>
+  <p>HTML</p>
+</div>
+
+> Quote
+> - list
+
+---
+
+```rust
+fn main() {}
+```
+
+- one
+- two
+
+| a | b |
+| - | - |
+| 1 | 2 |
+
+[link]: https://example.com
+"#;
+
+    let found = blocks(input)
+        .map(|b| (b.kind, &input[b.range]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        vec![
+            (BlockKind::Heading(HeadingLevel::H1), "# Title\n"),
+            (BlockKind::Paragraph, "Some *text*\nover two lines.\n"),
+            (BlockKind::Html, "<div>\n  <p>HTML</p>\n</div>\n"),
+            (BlockKind::BlockQuote, "> Quote\n> - list\n"),
+            (BlockKind::Rule, "---\n"),
+            (BlockKind::CodeBlock, "```rust\nfn main() {}\n```\n"),
+            (BlockKind::List, "- one\n- two\n"),
+            (BlockKind::Table, "| a | b |\n| - | - |\n| 1 | 2 |\n"),
+        ]
+    );
+}
+
+#[test]
+fn test_blocks2() {
+    let input = r#"Intro *text*
+with [a link][ref].
+
+[ref]: https://example.com
+
+
+|Title|Description|
+|---|---|
+|Test|This is a [test][ref]|
+* List   item
+
+```rust
+fn main() {}
+```"#;
+
+    let expected = r#"Intro *text*
+with [a link][ref].
+
+[ref]: https://example.com
+
+| Title | Description           |
+| ----- | --------------------- |
+| Test  | This is a [test][ref] |
+
+* List   item
+
+```rust
+fn main() {}
+```
+"#;
+
+    let cmfmt = Formatter::default().with_block_filter(Some(&|kind| kind == BlockKind::Table));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_blocks3() {
+    let input = r#"# Title
+Intro *text*.
+
+* Changed
+* List
+
+Outro *text*."#;
+
+    let expected = r#"# Title
+Intro *text*.
+
+- Changed
+- List
+
+Outro *text*.
+"#;
+
+    let cmfmt = Formatter::default().with_lines(Some(&[4..=4]));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_blocks4() {
+    let input = r#"<div>
+hi
+</div>
+
+*para*
+"#;
+
+    let cmfmt = Formatter::default().with_lines(Some(&[1..=1]));
+    test_cmark_with(&cmfmt, input, input);
+
+    let input = r#"*a*
+
+
+*b*
+
+
+
+*c*
+"#;
+
+    let expected = r#"_a_
+
+
+*b*
+
+
+
+*c*
+"#;
+
+    let cmfmt = Formatter::default().with_lines(Some(&[1..=1]));
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"*a*
+
+
+_b_
+
+
+
+*c*
+"#;
+
+    let cmfmt = Formatter::default().with_lines(Some(&[4..=4]));
+    test_cmark_with(&cmfmt, input, expected);
+}
@@ -0,0 +1,100 @@
+use cmarkfmt::{FenceChar, Formatter, InfoString};
+
+use super::test_cmark_with;
+
+#[test]
+fn test_code1() {
+    let input = r#"
+```rust
+fn main() {}
+```
+
+    indented"#;
+
+    let expected = r#"~~~rust
+fn main() {}
+~~~
+
+    indented
+"#;
+
+    let cmfmt = Formatter::default().with_code_fence(FenceChar::Tilde);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_code2() {
+    let input = r#"
+- ~~~~~
+  ~~~
+  ~~~~~
+
+````markdown
+```rust
+fn main() {}
+```
+````"#;
+
+    let expected = r#"- ```
+  ~~~
+  ```
+
+````markdown
+```rust
+fn main() {}
+```
+````
+"#;
+
+    test_cmark_with(&Formatter::default(), input, expected);
+
+    let expected = r#"- ~~~~
+  ~~~
+  ~~~~
+
+~~~markdown
+```rust
+fn main() {}
+```
+~~~
+"#;
+
+    let cmfmt = Formatter::default().with_code_fence(FenceChar::Tilde);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_code3() {
+    let input = r#"
+```  Rust   {.numberLines}
+fn main() {}
+```
+
+```Shell
+ls
+```"#;
+
+    let expected = r#"```rust {.numberLines}
+fn main() {}
+```
+
+```shell
+ls
+```
+"#;
+
+    let cmfmt = Formatter::default().with_info_string(InfoString::Normalize);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"```rust
+fn main() {}
+```
+
+```shell
+ls
+```
+"#;
+
+    let cmfmt = Formatter::default().with_info_string(InfoString::Language);
+    test_cmark_with(&cmfmt, input, expected);
+}
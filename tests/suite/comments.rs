@@ -0,0 +1,49 @@
+use cmarkfmt::Formatter;
+
+#[test]
+fn test_comments1() {
+    let input = r#"//! Crate *docs*.
+
+/// A function.
+///
+/// * Item 1
+/// * Item 2
+fn main() {
+    // Not a doc comment.
+}
+"#;
+
+    let expected = r#"//! Crate _docs_.
+
+/// A function.
+///
+/// - Item 1
+/// - Item 2
+fn main() {
+    // Not a doc comment.
+}
+"#;
+
+    let out = Formatter::default().format_comments(input, &["///", "//!"]);
+    assert_eq!(expected, &out);
+}
+
+#[test]
+fn test_comments2() {
+    let input = r#"def main():
+    # |Key|Value|
+    # |---|---|
+    # |a|1|
+    pass
+"#;
+
+    let expected = r#"def main():
+    # | Key | Value |
+    # | --- | ----- |
+    # | a   | 1     |
+    pass
+"#;
+
+    let out = Formatter::default().format_comments(input, &["#"]);
+    assert_eq!(expected, &out);
+}
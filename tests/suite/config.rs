@@ -0,0 +1,41 @@
+use cmarkfmt::{BoldItalic, Config, Formatter, FormatterConfig, Preset};
+
+use super::test_cmark_with;
+
+#[test]
+fn test_config1() {
+    let config = FormatterConfig {
+        preset: Some(Preset::Readme),
+        bold_italic: Some(BoldItalic::Nested),
+        unordered_list: Some("*".to_string()),
+        ..FormatterConfig::default()
+    };
+
+    let toml = toml::to_string(&config).unwrap();
+    assert_eq!(
+        toml,
+        "preset = \"readme\"\nbold_italic = \"nested\"\nunordered_list = \"*\"\n"
+    );
+    assert_eq!(toml::from_str::<FormatterConfig>(&toml).unwrap(), config);
+
+    let input = r#"
+- ***text***"#;
+
+    let expected = r#"* **_text_**
+"#;
+
+    test_cmark_with(&Formatter::from(&config), input, expected);
+}
+
+#[test]
+fn test_config2() {
+    let config = Config::from_toml(
+        "emphasis = \"*\"\n\n[[presets]]\npattern = \"*.md\"\npreset = \"readme\"\n",
+    )
+    .unwrap();
+    assert_eq!(config.formatter.emphasis.as_deref(), Some("*"));
+    assert_eq!(config.presets.len(), 1);
+
+    let err = Config::from_toml("emphasis = \"*\"\nemphasys = \"_\"\n").unwrap_err();
+    assert!(err.to_string().contains("unknown field `emphasys`"));
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use cmarkfmt::{EditorConfig, EndOfLine, FinalNewline, FormatterConfig, LineEnding};
+
+#[test]
+fn test_editorconfig1() {
+    let contents = r#"
+root = true
+
+[*]
+indent_style = tab
+indent_size = tab
+tab_width = 8
+end_of_line = crlf
+insert_final_newline = true
+max_line_length = 100
+
+[docs/**.md]
+max_line_length = off
+end_of_line = unset
+
+[*.rs]
+indent_size = 4
+"#;
+
+    let editorconfig = EditorConfig::parse(contents, Path::new("docs/api/index.md"));
+    assert_eq!(
+        editorconfig,
+        EditorConfig {
+            indent_size: Some(8),
+            end_of_line: None,
+            insert_final_newline: Some(true),
+            max_line_length: None,
+        }
+    );
+
+    let editorconfig = EditorConfig::parse(contents, Path::new("README.md"));
+    assert_eq!(editorconfig.end_of_line, Some(EndOfLine::Crlf));
+    assert_eq!(editorconfig.max_line_length, Some(100));
+}
+
+#[test]
+fn test_editorconfig2() {
+    let editorconfig = EditorConfig {
+        indent_size: Some(4),
+        end_of_line: Some(EndOfLine::Crlf),
+        insert_final_newline: Some(false),
+        max_line_length: Some(80),
+    };
+    let mut config = FormatterConfig {
+        line_width: Some(0),
+        ..FormatterConfig::default()
+    };
+    editorconfig.apply(&mut config);
+    assert_eq!(config.final_newline, Some(FinalNewline::Strip));
+    assert_eq!(config.indent_width, Some(4));
+    assert_eq!(config.line_ending, Some(LineEnding::CrLf));
+    assert_eq!(config.line_width, Some(0));
+}
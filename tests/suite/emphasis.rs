@@ -0,0 +1,83 @@
+use cmarkfmt::{BoldItalic, Formatter};
+
+use super::{test_cmark, test_cmark_with};
+
+const BOLD_ITALIC_INPUT: &str = r#"
+Both ***one***, **_two_**, _**three**_, and *__four__*.
+Only __*partially* nested__ and *some **nested** text*."#;
+
+#[test]
+fn test_emphasis1() {
+    let expected = r#"Both _**one**_, **_two_**, _**three**_, and _**four**_.
+Only **_partially_ nested** and _some **nested** text_.
+"#;
+
+    test_cmark(BOLD_ITALIC_INPUT, expected);
+}
+
+#[test]
+fn test_emphasis2() {
+    let expected = r#"Both ***one***, ***two***, ***three***, and ***four***.
+Only **_partially_ nested** and _some **nested** text_.
+"#;
+
+    let cmfmt = Formatter::default().with_bold_italic(BoldItalic::Triple);
+    test_cmark_with(&cmfmt, BOLD_ITALIC_INPUT, expected);
+}
+
+#[test]
+fn test_emphasis3() {
+    let expected = r#"Both **_one_**, **_two_**, **_three_**, and **_four_**.
+Only **_partially_ nested** and _some **nested** text_.
+"#;
+
+    let cmfmt = Formatter::default().with_bold_italic(BoldItalic::Nested);
+    test_cmark_with(&cmfmt, BOLD_ITALIC_INPUT, expected);
+}
+
+#[test]
+fn test_emphasis4() {
+    let expected = r#"Both ___one___, ___two___, ___three___, and ___four___.
+Only ___partially_ nested__ and _some __nested__ text_.
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_strong("__")
+        .with_bold_italic(BoldItalic::Triple);
+    test_cmark_with(&cmfmt, BOLD_ITALIC_INPUT, expected);
+
+    let expected = r#"Both __*one*__, __*two*__, __*three*__, and __*four*__.
+Only __*partially* nested__ and *some __nested__ text*.
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_strong("__")
+        .with_emphasis("*")
+        .with_bold_italic(BoldItalic::Nested);
+    test_cmark_with(&cmfmt, BOLD_ITALIC_INPUT, expected);
+}
+
+#[test]
+fn test_emphasis5() {
+    let input = r#"
+Some intra*word* emphasis, **strong**ly, a***b***c, snake_*case*, and *not **in** words*."#;
+
+    let expected = r#"Some intra*word* emphasis, **strong**ly, a***b***c, snake\_*case*, and _not __in__ words_.
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_strong("__")
+        .with_smart_emphasis(true);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let cmfmt = cmfmt.with_bold_italic(BoldItalic::Nested);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"Some intra*word* emphasis, **strong**ly, a***b***c, snake\_*case*, and *not **in** words*.
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_emphasis("*")
+        .with_smart_emphasis(true);
+    test_cmark_with(&cmfmt, input, expected);
+}
@@ -0,0 +1,221 @@
+use cmarkfmt::{FootnotePlacement, Formatter};
+
+use super::{test_cmark, test_cmark_with};
+
+#[test]
+fn test_footnote1() {
+    let input = r#"
+Text[^1].
+
+[^1]:   A note."#;
+
+    let expected = r#"Text[^1].
+
+[^1]: A note.
+"#;
+
+    test_cmark(input, expected);
+}
+
+#[test]
+fn test_footnote2() {
+    let input = r#"
+# Notes
+
+[^b]: Second note, see[^c].
+
+First[^a] and second[^b].
+
+[^a]: First note.
+
+- item[^c]
+
+[^c]: Third.
+
+[^unused]: Unused.
+
+Last paragraph."#;
+
+    let expected = r#"# Notes
+
+First[^a] and second[^b].
+
+- item[^c]
+
+Last paragraph.
+
+[^b]: Second note, see[^c].
+
+[^a]: First note.
+
+[^c]: Third.
+
+[^unused]: Unused.
+"#;
+
+    let cmfmt = Formatter::default().with_footnote_placement(FootnotePlacement::EndOfDocument);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"# Notes
+
+First[^a] and second[^b].
+
+[^a]: First note.
+
+[^b]: Second note, see[^c].
+
+[^c]: Third.
+
+- item[^c]
+
+Last paragraph.
+
+[^unused]: Unused.
+"#;
+
+    let cmfmt =
+        Formatter::default().with_footnote_placement(FootnotePlacement::AfterFirstReference);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_footnote3() {
+    let input = r#"
+[^1]:   Moved.
+
+[^2]:   Kept.
+
+Text[^1][^2]."#;
+
+    let expected = r#"[^2]:   Kept.
+
+Text[^1][^2].
+
+[^1]: Moved.
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_footnote_placement(FootnotePlacement::EndOfDocument)
+        .with_lines(Some(&[1..=2, 6..=6]));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_footnote4() {
+    let input = r#"
+Text[^b], more[^A] and again[^b].
+
+- Item[^2]
+
+[^2]: See[^a].
+
+[^unused]: Unused.
+
+[^a]: A note.
+
+[^b]: B note."#;
+
+    let expected = r#"Text[^1], more[^2] and again[^1].
+
+- Item[^3]
+
+[^3]: See[^2].
+
+[^4]: Unused.
+
+[^2]: A note.
+
+[^1]: B note.
+"#;
+
+    let cmfmt = Formatter::default().with_footnote_renumbering(true);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"Text[^1], more[^2] and again[^1].
+
+[^1]: B note.
+
+[^2]: A note.
+
+- Item[^3]
+
+[^3]: See[^2].
+
+[^4]: Unused.
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_footnote_placement(FootnotePlacement::AfterFirstReference)
+        .with_footnote_renumbering(true);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_footnote5() {
+    let input = r#"
+Kept[^1].
+
+Text[^b].
+
+[^b]: Renamed."#;
+
+    let expected = r#"Kept[^1].
+
+Text[^2].
+
+[^2]: Renamed.
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_footnote_renumbering(true)
+        .with_lines(Some(&[3..=6]));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_footnote6() {
+    let input = r#"
+Text[^1][^2][^3].
+
+[^1]: * a
+      * b
+
+[^2]: A note with  
+a break.
+
+[^3]: ```rust
+    let x = 1;
+        let y = 2;
+    ```"#;
+
+    let expected = r#"Text[^1][^2][^3].
+
+[^1]: - a
+      - b
+
+[^2]: A note with\
+    a break.
+
+[^3]: ```rust
+    let x = 1;
+        let y = 2;
+    ```
+"#;
+
+    test_cmark(input, expected);
+}
+
+#[test]
+fn test_footnote7() {
+    let input = r#"
+Text^[An *inline* note] and ^[x\]y].
+
+Not a note: ^\[text] and [text]."#;
+
+    let expected = r#"Text^[An _inline_ note] and ^[x\]y].
+
+Not a note: ^\[text\] and \[text\].
+"#;
+
+    test_cmark(input, expected);
+}
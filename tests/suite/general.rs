@@ -1,4 +1,9 @@
-use super::test_cmark;
+use cmarkfmt::{
+    ByteOrderMark, FinalNewline, FormatError, Formatter, HardBreak, LineEnding, Rule, Tabs,
+    TrailingWhitespace,
+};
+
+use super::{test_cmark, test_cmark_with};
 
 #[test]
 fn test_list1() {
@@ -59,3 +64,202 @@ And another line.
 
     test_cmark(input, expected);
 }
+
+#[test]
+fn test_inline_html1() {
+    let input = "A <span>b</span>`c` and <b>*d*</b>.";
+    let expected = "A <span>b</span>`c` and <b>_d_</b>.\n";
+    test_cmark(input, expected);
+}
+
+#[test]
+fn test_blank_lines1() {
+    let input = r#"
+# Title
+
+
+
+Text.
+
+
+- Item
+
+
+- Item
+
+
+
+<div>
+</div>
+
+
+
+> Quote
+>
+>
+> More"#;
+
+    let expected = r#"# Title
+
+
+Text.
+
+
+- Item
+- Item
+
+
+<div>
+</div>
+
+
+> Quote
+>
+> More
+"#;
+
+    let cmfmt = Formatter::default().with_max_blank_lines(2);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_final_newline1() {
+    let cmfmt = Formatter::default().with_final_newline(FinalNewline::Preserve);
+    test_cmark_with(&cmfmt, "# Title\n\nText.", "# Title\n\nText.");
+    test_cmark_with(&cmfmt, "# Title\n\nText.\n\n\n", "# Title\n\nText.\n");
+
+    let cmfmt = Formatter::default().with_final_newline(FinalNewline::Strip);
+    test_cmark_with(&cmfmt, "- Item\n\n<br>\n", "- Item\n\n<br>");
+    test_cmark_with(&cmfmt, "", "");
+
+    // Output ending with an HTML block is unchanged when formatted again.
+    let input = "<details>\n<summary>Summary</summary>\n\nText.\n\n</details>\n";
+    let expected = "<details>\n<summary>Summary</summary>\n\nText.\n\n</details>";
+    test_cmark_with(&cmfmt, input, expected);
+    test_cmark_with(&cmfmt, expected, expected);
+    test_cmark_with(&cmfmt, "> <div>\n> hi", "> <div>\n> hi");
+}
+
+#[test]
+fn test_line_ending1() {
+    let input = "# Title\r\n\r\nSome\ntext.\r\n\r\n```\r\ncode\n```\r\n";
+
+    let cmfmt = Formatter::default();
+    test_cmark_with(&cmfmt, input, "# Title\n\nSome\ntext.\n\n```\ncode\n```\n");
+
+    let expected = "# Title\r\n\r\nSome\r\ntext.\r\n\r\n```\r\ncode\r\n```\r\n";
+    let cmfmt = Formatter::default().with_line_ending(LineEnding::Preserve);
+    test_cmark_with(&cmfmt, input, expected);
+    let cmfmt = Formatter::default().with_line_ending(LineEnding::CrLf);
+    test_cmark_with(&cmfmt, "# Title\n", "# Title\r\n");
+}
+
+#[test]
+fn test_hard_break1() {
+    let input = r#"
+Line\
+break and [link\
+text](/url)."#;
+
+    let expected = "Line  \nbreak and [link\\\ntext](/url).\n";
+
+    let cmfmt = Formatter::default().with_hard_break(HardBreak::TwoSpaces);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_max_depth1() {
+    let input = r#"
+> Quote
+>
+> * item
+>
+>   > nested
+>   >
+>   > * deeper"#;
+
+    let cmfmt = Formatter::default().with_max_depth(Some(3));
+    let err = cmfmt.try_format_cmark(input).unwrap_err();
+    assert!(matches!(err, FormatError::MaxDepth(8)));
+    assert_eq!(cmfmt.format_cmark(input), input);
+
+    let mut out = String::new();
+    let err = cmfmt.try_format_cmark_writer(input, &mut out).unwrap_err();
+    assert!(matches!(err, FormatError::MaxDepth(8)));
+
+    let diagnostics = cmfmt.lint(input);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].rule, Rule::MaxDepth);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (8, 1));
+    assert_eq!(&input[diagnostics[0].range.clone()], ">   > * deeper");
+
+    let expected = r#"> Quote
+>
+> - item
+>   > nested
+>   >
+>   > - deeper
+"#;
+
+    let cmfmt = Formatter::default().with_max_depth(Some(4));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_trailing_whitespace1() {
+    let input = "> Line  \n> break\n>\n> ```\n> code  \n>\n> ```\n\n<div>  \n</div>\n";
+
+    let expected = "> Line  \n> break\n>\n> ```\n> code\n>\n> ```\n\n<div>\n</div>\n";
+    let cmfmt = Formatter::default().with_hard_break(HardBreak::TwoSpaces);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = "> Line  \n> break\n>\n> ```\n> code  \n>\n> ```\n\n<div>  \n</div>\n";
+    let cmfmt = cmfmt.with_trailing_whitespace(TrailingWhitespace::KeepInBlocks);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = "> Line\\\n> break\n>\n> ```\n> code\n>\n> ```\n\n<div>\n</div>\n";
+    let cmfmt = cmfmt.with_trailing_whitespace(TrailingWhitespace::TrimAll);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_tabs1() {
+    let input = "a\tb `\t`\n\n| x\ty | z |\n| - | - |\n\n```\n\tcode\n```\n";
+
+    let expected = "a   b `\t`\n\n| x   y | z   |\n| ----- | --- |\n\n```\n\tcode\n```\n";
+    let cmfmt = Formatter::default().with_tabs(Tabs::Expand);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = "a b `\t`\n\n| x y | z   |\n| --- | --- |\n\n```\n\tcode\n```\n";
+    let cmfmt = cmfmt.with_tab_width(2);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let cmfmt = Formatter::default().with_tabs(Tabs::Diagnose);
+    let lines = cmfmt
+        .lint(input)
+        .iter()
+        .map(|d| (d.rule, d.line))
+        .collect::<Vec<_>>();
+    assert_eq!(lines, vec![(Rule::HardTab, 1), (Rule::HardTab, 3)]);
+}
+
+#[test]
+fn test_byte_order_mark1() {
+    let input = "\u{feff}# Title\n\n* item\n";
+
+    let expected = "\u{feff}# Title\n\n- item\n";
+    test_cmark(input, expected);
+
+    let cmfmt = Formatter::default().with_byte_order_mark(ByteOrderMark::Strip);
+    test_cmark_with(&cmfmt, input, "# Title\n\n- item\n");
+
+    let cmfmt = Formatter::default().with_byte_order_mark(ByteOrderMark::Ensure);
+    test_cmark_with(&cmfmt, "# Title\n", "\u{feff}# Title\n");
+    test_cmark_with(&cmfmt, "\u{feff}# Title\n", "\u{feff}# Title\n");
+
+    let input = "\u{feff}a\tb\n";
+    let cmfmt = Formatter::default().with_tabs(Tabs::Diagnose);
+    let diagnostics = cmfmt.lint(input);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(&input[diagnostics[0].range.clone()], "a\tb");
+}
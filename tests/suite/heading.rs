@@ -0,0 +1,268 @@
+use cmarkfmt::{
+    DuplicateHeadings, Formatter, HeadingAttributeOrder, HeadingAttributes, HeadingLevel,
+    HeadingStyle, Rule,
+};
+
+use super::{test_cmark, test_cmark_with};
+
+#[test]
+fn test_heading1() {
+    let input = r#"
+**Introduction**
+
+Some text.
+**Not a heading**
+
+**Note:**
+
+**Bold** but not a heading.
+
+__Usage with `code` and **nested** text__
+
+- **Item**"#;
+
+    let expected = r#"## Introduction
+
+Some text.
+**Not a heading**
+
+**Note:**
+
+**Bold** but not a heading.
+
+## Usage with `code` and **nested** text
+
+- **Item**
+"#;
+
+    let cmfmt = Formatter::default().with_bold_headings(Some(HeadingLevel::H2));
+    test_cmark_with(&cmfmt, input, expected);
+
+    let rules = cmfmt.lint(input).iter().map(|d| d.rule).collect::<Vec<_>>();
+    assert_eq!(rules, vec![Rule::BoldHeading, Rule::BoldHeading]);
+
+    // Links are resolved with the definitions of the document.
+    let input = "**[a**][r]**\n\n[r]: https://example.com\n";
+    let expected = "## [a\\*\\*][r]\n\n[r]: https://example.com\n";
+    test_cmark_with(&cmfmt, input, expected);
+    test_cmark_with(&cmfmt, expected, expected);
+}
+
+#[test]
+fn test_heading2() {
+    let input = r#"
+# Title
+
+Section {#section}
+---
+
+###   Subsection
+
+#
+
+# 1. Not a list
+
+> ## Quoted"#;
+
+    let expected = r#"Title
+=====
+
+Section { #section }
+--------------------
+
+### Subsection
+
+#
+
+# 1. Not a list
+
+> Quoted
+> ------
+"#;
+
+    let cmfmt = Formatter::default().with_heading_style(HeadingStyle::Setext);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_heading3() {
+    let input = r#"
+# Title
+Intro.
+## Section
+### Subsection
+> ## Quoted
+---"#;
+
+    let expected = r#"# Title
+
+
+Intro.
+
+
+
+## Section
+
+
+
+### Subsection
+
+
+> ## Quoted
+
+---
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_blank_lines_before_headings(3)
+        .with_blank_lines_after_headings(2);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_heading4() {
+    let input = r#"
+# Title {.intro #title .main}
+
+Setext {#setext}
+======"#;
+
+    let expected = r#"# Title { #title .intro .main }
+
+# Setext { #setext }
+"#;
+
+    test_cmark(input, expected);
+
+    let expected = r#"# Title {.intro #title .main}
+
+# Setext {#setext}
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_heading_attributes(HeadingAttributes::Compact)
+        .with_heading_attribute_order(HeadingAttributeOrder::Preserve);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"# Title
+
+# Setext
+"#;
+
+    let cmfmt = Formatter::default().with_heading_attributes(HeadingAttributes::Remove);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_heading5() {
+    let input = r#"
+# Title
+
+## Section
+
+###### Deepest"#;
+
+    let expected = r#"### Title
+
+#### Section
+
+###### Deepest
+"#;
+
+    let cmfmt = Formatter::default().with_heading_shift(2);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"Title
+=====
+
+Section
+=======
+
+##### Deepest
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_heading_shift(-1)
+        .with_heading_style(HeadingStyle::Setext);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_heading6() {
+    let input = r#"
+# Getting Started With `cmarkfmt`
+
+Setext Heading
+--------------
+
+### Already sentence case"#;
+
+    let expected = r#"Getting started with `cmarkfmt`
+===============================
+
+Setext heading
+--------------
+
+### Already sentence case
+"#;
+
+    let transform = |_: HeadingLevel, text: &str| {
+        let mut words = text.split(' ');
+        let first = words.next()?.to_string();
+        let rest = words.map(|word| word.to_lowercase()).collect::<Vec<_>>();
+        Some(
+            [first]
+                .into_iter()
+                .chain(rest)
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    };
+    let cmfmt = Formatter::default()
+        .with_heading_style(HeadingStyle::Setext)
+        .with_heading_transform(Some(&transform));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_heading7() {
+    let input = r#"
+# Getting Started
+
+## Getting started!
+
+## Install {#getting-started}
+
+## Usage {#usage}
+
+## Usage"#;
+
+    let expected = r#"# Getting Started
+
+## Getting started!
+
+## Install { #getting-started-2 }
+
+## Usage { #usage }
+
+## Usage
+"#;
+
+    let cmfmt = Formatter::default().with_duplicate_headings(DuplicateHeadings::Deduplicate);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let cmfmt = Formatter::default().with_duplicate_headings(DuplicateHeadings::Diagnose);
+    let lines = cmfmt
+        .lint(input)
+        .iter()
+        .map(|d| (d.rule, d.line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            (Rule::DuplicateHeading, 4),
+            (Rule::DuplicateHeading, 6),
+            (Rule::DuplicateHeading, 10)
+        ]
+    );
+}
@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use cmarkfmt::Ignore;
+
+#[test]
+fn test_ignore1() {
+    let ignore = Ignore::new(
+        r#"
+# Generated files.
+docs/**/generated/
+/CHANGELOG.md
+notes-[0-9].md
+"#,
+    );
+
+    let ignored = |path: &str, is_dir| ignore.is_ignored(Path::new(path), is_dir);
+    assert!(ignored("docs/generated", true));
+    assert!(ignored("docs/api/v1/generated/index.md", false));
+    assert!(!ignored("docs/generated", false));
+    assert!(ignored("CHANGELOG.md", false));
+    assert!(!ignored("sub/CHANGELOG.md", false));
+    assert!(ignored("sub/notes-1.md", false));
+    assert!(!ignored("sub/notes-a.md", false));
+}
+
+#[test]
+fn test_ignore2() {
+    let ignore = Ignore::new("*a*a*a*a*a*a*a*a*a*a*b\n/x/**/**/**/**/**/**/y\n");
+
+    // Patterns with many wildcards are matched without backtracking.
+    let path = "a".repeat(200);
+    assert!(!ignore.is_ignored(Path::new(&path), false));
+    let path = format!("x/{}z", "d/".repeat(100));
+    assert!(!ignore.is_ignored(Path::new(&path), false));
+    assert!(ignore.is_ignored(Path::new("x/d/e/y"), false));
+    assert!(ignore.is_ignored(Path::new(&format!("{path}/{}b", "a".repeat(10))), false));
+}
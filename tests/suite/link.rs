@@ -1,4 +1,9 @@
-use super::test_cmark;
+use cmarkfmt::{
+    extract_images, extract_links, BareUrls, EmailLinks, EmptyLinks, Formatter, LabelCase,
+    LinkType, RefDefPolicy, ReferenceLinks, Rule, TitleQuote,
+};
+
+use super::{test_cmark, test_cmark_with};
 
 #[test]
 fn test_link1() {
@@ -112,3 +117,654 @@ Here's an <autolink>.
 
     test_cmark(input, expected);
 }
+
+#[test]
+fn test_link9() {
+    let input = r#"
+A [link]() without a destination, [](https://example.com) without text,
+an ![image]() without a source, and [](<>) nothing at all.
+"#;
+
+    let expected = r#"A link without a destination, https://example.com without text,
+an image without a source, and nothing at all.
+"#;
+
+    let cmfmt = Formatter::default().with_empty_links(EmptyLinks::PlainText);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let input = "[](<>) Start, [ ]() middle [](<>)\n[]() end.";
+    let expected = "Start, middle\nend.\n";
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link10() {
+    let input = r#"
+A [link]() and [a
+multi-line link]() and ![image]()."#;
+
+    let expected = r#"A [link]() and [a
+multi-line link]() and ![image]().
+"#;
+
+    let cmfmt = Formatter::default().with_empty_links(EmptyLinks::Diagnose);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let diagnostics = cmfmt.lint(input);
+    let found = diagnostics
+        .iter()
+        .map(|d| (d.rule, d.line, d.column))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        vec![
+            (Rule::EmptyLink, 2, 3),
+            (Rule::EmptyLink, 2, 16),
+            (Rule::EmptyImage, 3, 24)
+        ]
+    );
+}
+
+#[test]
+fn test_link11() {
+    let input = r#"
+# One
+
+See [b] and [z].
+
+[z]: /z "Zed"
+[b]: /b
+
+# Two
+
+> See [a].
+>
+> [a]: /a
+
+- An [item].
+
+  [item]: /item"#;
+
+    let expected = r#"# One
+
+See [b] and [z].
+
+[z]: /z "Zed"
+[b]: /b
+
+# Two
+
+> See [a].
+>
+> [a]: /a
+
+- An [item].
+
+  [item]: /item
+"#;
+
+    let cmfmt = Formatter::default().with_refdef_policy(RefDefPolicy::KeepInPlace);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"# One
+
+See [b] and [z].
+
+# Two
+
+> See [a].
+
+- An [item].
+
+[z]: /z "Zed"
+[b]: /b
+[a]: /a
+[item]: /item
+"#;
+
+    let cmfmt = Formatter::default().with_refdef_policy(RefDefPolicy::PreserveOrderAtEnd);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link12() {
+    let input = r#"
+# One
+
+See [b] and [a][].
+
+- A list with [b] and [Multi  Word].
+
+No references.
+
+[a]: /a
+[b]: /b
+[unused]: /unused
+[multi word]: /multi"#;
+
+    let expected = r#"# One
+
+See [b] and [a][].
+
+[b]: /b
+[a]: /a
+
+- A list with [b] and [Multi  Word].
+
+[multi word]: /multi
+
+No references.
+
+[unused]: /unused
+"#;
+
+    let cmfmt = Formatter::default().with_refdef_policy(RefDefPolicy::AfterFirstUse);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link13() {
+    let input = r#"
+See [one], [text][two], [more][Two] and [four].
+
+[one]: /same "Title"
+[two]: /same "Title"
+[three]: /three
+[four]: /same "Title""#;
+
+    let expected = r#"See [one], [text][one], [more][one] and [four].
+
+[four]: /same "Title"
+[one]: /same "Title"
+"#;
+
+    let cmfmt = Formatter::default().with_prune_refdefs(true);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let found = cmfmt
+        .lint(input)
+        .into_iter()
+        .map(|d| (d.rule, d.line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        vec![(Rule::DuplicateDefinition, 5), (Rule::UnusedDefinition, 6)]
+    );
+
+    // Labels are matched with Unicode case folding.
+    let input = "[ẞ]\n\n[SS]: /url\n";
+    test_cmark_with(&cmfmt, input, input);
+}
+
+#[test]
+fn test_link14() {
+    let input = r#"
+See [the docs][Docs], [the guide][guide], and [more][DOCS].
+
+[docs]: https://example.com
+[guide]: https://example.com"#;
+
+    let expected = r#"See [the docs][Docs], [the guide][guide], and [more][DOCS].
+
+[docs]: https://example.com
+[guide]: https://example.com
+"#;
+
+    test_cmark(input, expected);
+}
+
+#[test]
+fn test_link15() {
+    let input = r#"
+Read [the **guide**](https://example.com/a/long/path "T") or [this](/short).
+
+Again [the guide](https://example.com/a/long/path "T"), [x](https://example.com/a/long/other) and [The Guide](https://example.com/a/long/third).
+
+[the-guide]: /existing"#;
+
+    let expected = r#"Read [the **guide**][the-guide-2] or [this](/short).
+
+Again [the guide][the-guide-2], [x][x] and [The Guide][the-guide-3].
+
+[the-guide]: /existing
+[the-guide-2]: https://example.com/a/long/path "T"
+[the-guide-3]: https://example.com/a/long/third
+[x]: https://example.com/a/long/other
+"#;
+
+    let cmfmt = Formatter::default().with_max_inline_link_length(Some(20));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link16() {
+    let input = r#"
+See [the docs][Docs], [home], [x][] and ![img][home].
+
+[docs]: https://example.com "The (docs)"
+[home]: /
+[x]: /a
+[unused]: /u"#;
+
+    let expected = r#"See [the docs](https://example.com "The (docs)"), [home](/), [x](/a) and ![img](/).
+"#;
+
+    let cmfmt = Formatter::default().with_inline_links(true);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link17() {
+    let input = r#"
+See [a](https://github.com/o/r/x), <https://github.com/o/r/y> and [b][home], ![i](https://github.com/o/r/i.png).
+
+[home]: https://github.com/o/r/README.md"#;
+
+    let expected = r#"See [a](https://x.io/x), <https://x.io/y> and [b][home], ![i](https://github.com/o/r/i.png).
+
+[home]: https://x.io/README.md
+"#;
+
+    let rewrite = |_, dest: &str| {
+        dest.strip_prefix("https://github.com/o/r/")
+            .map(|path| format!("https://x.io/{path}"))
+    };
+    let cmfmt = Formatter::default().with_link_rewriter(Some(&rewrite));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link18() {
+    let input = r#"
+An ![*Logo*](images/logo.png "Logo") and ![badge][b], [not](images/page.md).
+
+[b]: images/b.svg"#;
+
+    let expected = r#"An ![_Logo_](/_Logo_/logo.png "Logo") and ![badge](/badge/b.svg), [not](images/page.md).
+
+[b]: images/b.svg
+"#;
+
+    let rewrite = |alt: &str, src: &str| {
+        src.strip_prefix("images/")
+            .map(|path| format!("/{alt}/{path}"))
+    };
+    let cmfmt = Formatter::default().with_image_rewriter(Some(&rewrite));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link19() {
+    let input = r#"
+See [a](<a b> "T"), [b](<c(d>), [c](e(f)g), [d](<h)>), ![i](<x y.png>) and [r].
+
+[r]: <s t>
+[u]: <>
+[v]: <(w>"#;
+
+    let expected = r#"See [a](<a b> "T"), [b](<c(d>), [c](e(f)g), [d](<h)>), ![i](<x y.png>) and [r].
+
+[r]: <s t>
+[u]: <>
+[v]: <(w>
+"#;
+
+    test_cmark(input, expected);
+
+    let cmfmt = Formatter::default().with_inline_links(true);
+    test_cmark_with(&cmfmt, "[r]\n\n[r]: <s t>", "[r](<s t>)\n");
+}
+
+#[test]
+fn test_link20() {
+    let input = r#"
+See https://a.com/x_y_z. and (https://b.com/q?a=1&amp;b=2), (https://c.com/w(1)).
+
+Not [https://c.com](https://c.com), `https://d.com`, <https://e.com>, xhttps://f.com, or https://.
+
+<a href="x">https://g.com</a>
+
+    https://h.com"#;
+
+    let expected = r#"See <https://a.com/x_y_z>. and (<https://b.com/q?a=1&b=2>), (<https://c.com/w(1)>).
+
+Not [https://c.com](https://c.com), `https://d.com`, <https://e.com>, xhttps://f.com, or https://.
+
+<a href="x">https://g.com</a>
+
+    https://h.com
+"#;
+
+    let cmfmt = Formatter::default().with_bare_urls(BareUrls::Autolink);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"See [https://a.com/x\_y\_z](https://a.com/x_y_z). and ([https://b.com/q?a=1&b=2](https://b.com/q?a=1&b=2)), ([https://c.com/w(1)](https://c.com/w(1))).
+
+Not [https://c.com](https://c.com), `https://d.com`, <https://e.com>, xhttps://f.com, or https://.
+
+<a href="x">https://g.com</a>
+
+    https://h.com
+"#;
+
+    let cmfmt = Formatter::default().with_bare_urls(BareUrls::Inline);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link21() {
+    let input = r#"
+See [a](/a "Say \"hi\" (now)"), [b](/b 'it\'s'), [c](/c (x\\y\\)) and [r].
+
+[r]: /r "C:\\dir\\""#;
+
+    let expected = r#"See [a](/a "Say \"hi\" (now)"), [b](/b "it's"), [c](/c "x\y\\") and [r].
+
+[r]: /r "C:\dir\\"
+"#;
+
+    test_cmark(input, expected);
+
+    let expected = r#"See [a](/a 'Say "hi" (now)'), [b](/b 'it\'s'), [c](/c 'x\y\\') and [r].
+
+[r]: /r 'C:\dir\\'
+"#;
+
+    let cmfmt = Formatter::default().with_title_quote(TitleQuote::Single);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"See [a](/a (Say "hi" \(now\))), [b](/b (it's)), [c](/c (x\y\\)) and [r].
+
+[r]: /r (C:\dir\\)
+"#;
+
+    let cmfmt = Formatter::default().with_title_quote(TitleQuote::Parentheses);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link22() {
+    let input = r#"
+See [docs][docs], [Docs][], [the guide][guide], [guide][](x), [*b*] and [docs].
+
+[docs]: /d
+[guide]: /g
+[*b*]: /b"#;
+
+    let expected = r#"See [docs][docs], [Docs][], [the guide][guide], [guide][](x), [*b*] and [docs].
+
+[*b*]: /b
+[docs]: /d
+[guide]: /g
+"#;
+
+    test_cmark(input, expected);
+
+    let expected = r#"See [docs][docs], [Docs][Docs], [the guide][guide], [guide][guide](x), [_b_][*b*] and [docs][docs].
+
+[*b*]: /b
+[docs]: /d
+[guide]: /g
+"#;
+
+    let cmfmt = Formatter::default().with_reference_links(ReferenceLinks::Full);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"See [docs][], [Docs][], [the guide][guide], [guide][](x), [_b_][*b*] and [docs][].
+
+[*b*]: /b
+[docs]: /d
+[guide]: /g
+"#;
+
+    let cmfmt = Formatter::default().with_reference_links(ReferenceLinks::Collapsed);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"See [docs], [Docs], [the guide][guide], [guide][](x), [_b_][*b*] and [docs].
+
+[*b*]: /b
+[docs]: /d
+[guide]: /g
+"#;
+
+    let cmfmt = Formatter::default().with_reference_links(ReferenceLinks::Shortcut);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link23() {
+    let input = r#"A [b][] c [d][e] ![i][] [*x* `y`
+z](/u "t") <https://a.com>.
+
+> [`f`][f]
+
+[b]: /b
+[e]: /e
+[f]: /f
+[i]: /i
+"#;
+
+    let found = extract_links(input)
+        .into_iter()
+        .map(|l| (l.kind, l.text, l.dest, l.title, &input[l.range]))
+        .collect::<Vec<_>>();
+    let link = |kind, text: &str, dest: &str, title: &str, source| {
+        (
+            kind,
+            text.to_string(),
+            dest.to_string(),
+            title.to_string(),
+            source,
+        )
+    };
+    assert_eq!(
+        found,
+        vec![
+            link(LinkType::Collapsed, "b", "/b", "", "[b][]"),
+            link(LinkType::Reference, "d", "/e", "", "[d][e]"),
+            link(
+                LinkType::Inline,
+                "x y z",
+                "/u",
+                "t",
+                "[*x* `y`\nz](/u \"t\")"
+            ),
+            link(
+                LinkType::Autolink,
+                "https://a.com",
+                "https://a.com",
+                "",
+                "<https://a.com>"
+            ),
+            link(LinkType::Reference, "f", "/f", "", "[`f`][f]"),
+        ]
+    );
+}
+
+#[test]
+fn test_link24() {
+    let input = r#"![A *logo*](logo.png "Logo") and [![Badge][b]](https://example.com)
+
+- ![outer ![inner](in.png)](out.png)
+
+`![code](c.png)` <img src="html.png">
+
+[b]: badge.svg
+"#;
+
+    let found = extract_images(input)
+        .into_iter()
+        .map(|i| (i.alt, i.dest, i.title, &input[i.range]))
+        .collect::<Vec<_>>();
+    let image = |alt: &str, dest: &str, title: &str, source| {
+        (alt.to_string(), dest.to_string(), title.to_string(), source)
+    };
+    assert_eq!(
+        found,
+        vec![
+            image(
+                "A logo",
+                "logo.png",
+                "Logo",
+                "![A *logo*](logo.png \"Logo\")"
+            ),
+            image("Badge", "badge.svg", "", "![Badge][b]"),
+            image(
+                "outer inner",
+                "out.png",
+                "",
+                "![outer ![inner](in.png)](out.png)"
+            ),
+            image("inner", "in.png", "", "![inner](in.png)"),
+        ]
+    );
+}
+
+#[test]
+fn test_link25() {
+    let input = r#"
+![](logo.png) and ![ ](photo.jpg) and ![Chart](chart.svg)
+
+[![](badge.svg)](https://example.com)"#;
+
+    let expected = r#"![The \*new\* logo](logo.png) and ![ ](photo.jpg) and ![Chart](chart.svg)
+
+[![](badge.svg)](https://example.com)
+"#;
+
+    let alt_text = |src: &str| (src == "logo.png").then(|| "The *new* logo".to_string());
+    let cmfmt = Formatter::default()
+        .with_alt_text(Some(&alt_text))
+        .with_require_alt_text(true);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let found = cmfmt
+        .lint(input)
+        .into_iter()
+        .map(|d| (d.rule, d.line, d.column))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        vec![(Rule::MissingAltText, 2, 19), (Rule::MissingAltText, 4, 2)]
+    );
+}
+
+#[test]
+fn test_link26() {
+    let input = r#"
+Mail [me@example.com](mailto:me@example.com), <mailto:us@example.com>, or
+[the *team*](mailto:team@example.com). Also <you@example.com>.
+
+```
+<them@example.com>
+```"#;
+
+    let expected = r#"Mail <me@example.com>, <us@example.com>, or
+[the _team_](mailto:team@example.com). Also <you@example.com>.
+
+```
+<them@example.com>
+```
+"#;
+    let cmfmt = Formatter::default().with_email_links(EmailLinks::Autolink);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"Mail [me@example.com](mailto:me@example.com), [us@example.com](mailto:us@example.com), or
+[the _team_](mailto:team@example.com). Also [you@example.com](mailto:you@example.com).
+
+```
+<them@example.com>
+```
+"#;
+    let cmfmt = Formatter::default().with_email_links(EmailLinks::Explicit);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_link27() {
+    let input = r#"
+See [[Page Name]] and [[Page|alias]], but not \[[Escaped]], `[[code]]`, or [[Broken
+Page]]. A long line ending with [[Another Page]] is wrapped around the wikilink."#;
+
+    let expected = r#"See [[Page Name]] and [[Page|alias]], but not \[\[Escaped\]\],
+`[[code]]`, or \[\[Broken Page\]\]. A long line ending with
+[[Another Page]] is wrapped around the wikilink.
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_wikilinks(true)
+        .with_line_width(Some(66));
+    test_cmark_with(&cmfmt, input, expected);
+
+    // The content of a wikilink can be a reference link.
+    let input = "[[*foo* bar]]\n\n[*foo* bar]: /u\n";
+    let expected = "\\[[_foo_ bar][*foo* bar]\\]\n\n[*foo* bar]: /u\n";
+    let cmfmt = Formatter::default()
+        .with_wikilinks(true)
+        .with_reference_links(ReferenceLinks::Full);
+    test_cmark_with(&cmfmt, input, expected);
+    test_cmark_with(&cmfmt, expected, expected);
+}
+
+#[test]
+fn test_link28() {
+    let input = r#"
+See [the docs][Docs], [Foo][], [FOO], and [more][foo].
+
+[foo]: /foo
+[DOCS]: /docs"#;
+
+    let expected = r#"See [the docs][docs], [Foo][], [FOO], and [more][foo].
+
+[docs]: /docs
+[foo]: /foo
+"#;
+    let cmfmt = Formatter::default().with_label_case(LabelCase::Lowercase);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"See [the docs][Docs], [Foo][], [FOO], and [more][foo].
+
+[DOCS]: /docs
+[foo]: /foo
+"#;
+    test_cmark_with(&Formatter::default(), input, expected);
+}
+
+#[test]
+fn test_link29() {
+    let input = r#"
+> [foo   bar]: /url
+>
+> [foo
+> bar], [text][foo
+> bar] and [foo
+> bar][]"#;
+
+    let expected = r#"> [foo
+> bar], [text][foo
+> bar] and [foo
+> bar][]
+
+[foo bar]: /url
+"#;
+
+    test_cmark(input, expected);
+    test_cmark_with(
+        &Formatter::default().with_prune_refdefs(true),
+        input,
+        expected,
+    );
+
+    let expected = r#"> [foo
+> bar][foo
+> bar], [text][foo
+> bar] and [foo
+> bar][foo
+> bar]
+
+[foo bar]: /url
+"#;
+
+    let cmfmt = Formatter::default().with_reference_links(ReferenceLinks::Full);
+    test_cmark_with(&cmfmt, input, expected);
+}
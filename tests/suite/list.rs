@@ -1,4 +1,9 @@
-use super::test_cmark;
+use cmarkfmt::{
+    BulletStyle, ContinuationIndent, Formatter, ListMarkerAlignment, ListSpacing,
+    OrderedListDelimiter, OrderedListNumbering,
+};
+
+use super::{test_cmark, test_cmark_with};
 
 #[test]
 fn test_list1() {
@@ -66,4 +71,373 @@ fn test_list4() {
 "#;
 
     test_cmark(input, expected);
+
+    let expected = r#"- > blockquote
+  > inside
+  > list
+- Next item
+"#;
+    let cmfmt = Formatter::default().with_blank_line_after_block_items(false);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_list5() {
+    let input = r#"
+1. One
+1. Two
+5. Three
+
+Text
+
+0. Zero
+7. One"#;
+
+    let numbering = |numbering| Formatter::default().with_ordered_list_numbering(numbering);
+
+    let expected = r#"1. One
+1. Two
+1. Three
+
+Text
+
+0. Zero
+0. One
+"#;
+    test_cmark_with(&numbering(OrderedListNumbering::Start), input, expected);
+
+    let expected = r#"1. One
+2. Two
+3. Three
+
+Text
+
+0. Zero
+1. One
+"#;
+    test_cmark_with(
+        &numbering(OrderedListNumbering::Sequential),
+        input,
+        expected,
+    );
+
+    let expected = r#"1. One
+1. Two
+1. Three
+
+Text
+
+0. Zero
+1. One
+"#;
+    test_cmark_with(&numbering(OrderedListNumbering::AllOnes), input, expected);
+
+    let expected = r#"1. One
+1. Two
+5. Three
+
+Text
+
+0. Zero
+7. One
+"#;
+    test_cmark_with(&numbering(OrderedListNumbering::Preserve), input, expected);
+}
+
+#[test]
+fn test_list6() {
+    let input = r#"
+1) One
+2) Two
+
+1. Adjacent
+
+3) Three"#;
+
+    let delimiter = |delimiter| Formatter::default().with_ordered_list_delimiter(delimiter);
+
+    let expected = r#"1. One
+1. Two
+
+1) Adjacent
+
+3. Three
+"#;
+    test_cmark_with(&delimiter(OrderedListDelimiter::Period), input, expected);
+
+    let expected = r#"1) One
+1) Two
+
+1. Adjacent
+
+3) Three
+"#;
+    test_cmark_with(&delimiter(OrderedListDelimiter::Paren), input, expected);
+    test_cmark_with(&delimiter(OrderedListDelimiter::Preserve), input, expected);
+}
+
+#[test]
+fn test_list7() {
+    let input = r#"
+8. Eight
+9. Nine
+   continued
+
+   - nested
+10. Ten"#;
+
+    let align = |alignment| {
+        Formatter::default()
+            .with_ordered_list_numbering(OrderedListNumbering::Sequential)
+            .with_list_marker_alignment(alignment)
+    };
+
+    let expected = r#"8. Eight
+9. Nine
+   continued
+
+   - nested
+10. Ten
+"#;
+    test_cmark_with(&align(ListMarkerAlignment::None), input, expected);
+
+    let expected = r#"8.  Eight
+9.  Nine
+    continued
+
+    - nested
+10. Ten
+"#;
+    test_cmark_with(&align(ListMarkerAlignment::Left), input, expected);
+
+    let expected = r#" 8. Eight
+ 9. Nine
+    continued
+
+    - nested
+10. Ten
+"#;
+    test_cmark_with(&align(ListMarkerAlignment::Right), input, expected);
+}
+
+#[test]
+fn test_list8() {
+    let input = r#"
+- One
+  - Two
+- Three
+      continued
+-     code
+- Four"#;
+
+    let expected = r#"-   One
+    -   Two
+-   Three
+    continued
+-     code
+
+-   Four
+"#;
+    test_cmark_with(
+        &Formatter::default().with_indent_width(Some(4)),
+        input,
+        expected,
+    );
+}
+
+#[test]
+fn test_list9() {
+    let input = r#"
+* One
+  + Nested
+
+- Two
+
++ Three"#;
+
+    let expected = r#"* One
+  + Nested
+
+- Two
+
++ Three
+"#;
+    let cmfmt = Formatter::default().with_bullet_style(BulletStyle::Preserve);
+    test_cmark_with(&cmfmt, input, expected);
+    test_cmark_with(&cmfmt.with_prettier(true), input, expected);
+}
+
+#[test]
+fn test_list10() {
+    let input = r#"
+* One
+  * Two
+    1. Three
+       * Four
+* Five"#;
+
+    let expected = r#"- One
+  * Two
+    1. Three
+       - Four
+- Five
+"#;
+    let cmfmt = Formatter::default().with_bullet_style(BulletStyle::Depth);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"+ One
+  - Two
+    1. Three
+       + Four
++ Five
+"#;
+    test_cmark_with(&cmfmt.with_unordered_list("+"), input, expected);
+}
+
+#[test]
+fn test_list11() {
+    let input = r#"
+- One
+
+- Two
+- Three
+
+1. Four
+2. Five"#;
+
+    let spacing = |spacing| Formatter::default().with_list_spacing(spacing);
+
+    let expected = r#"- One
+- Two
+- Three
+
+1. Four
+1. Five
+"#;
+    test_cmark_with(&spacing(ListSpacing::Tight), input, expected);
+
+    let expected = r#"- One
+
+- Two
+
+- Three
+
+1. Four
+
+1. Five
+"#;
+    test_cmark_with(&spacing(ListSpacing::Loose), input, expected);
+
+    let expected = r#"- One
+
+- Two
+
+- Three
+
+1. Four
+1. Five
+"#;
+    test_cmark_with(&spacing(ListSpacing::Preserve), input, expected);
+
+    // A loose list nested in a tight one.
+    let input = "- a\n  - b\n\n    c\n- d\n";
+    test_cmark_with(&spacing(ListSpacing::Preserve), input, input);
+
+    // Nested lists are separated from the paragraphs of loose items.
+    let input = "- a\n- b\n  - c\n- d\n";
+    let expected = "- a\n\n- b\n\n  - c\n\n- d\n";
+    test_cmark_with(&spacing(ListSpacing::Loose), input, expected);
+    test_cmark_with(&spacing(ListSpacing::Loose), expected, expected);
+
+    let input = "> - b\n>\n>   - c\n";
+    test_cmark_with(&spacing(ListSpacing::Preserve), input, input);
+}
+
+#[test]
+fn test_list12() {
+    let input = r#"
+- One
+  continued
+
+  Two
+
+      code
+- Three
+  - Nested
+
+  Four"#;
+
+    let expected = r#"- One
+  continued
+
+  Two
+
+      code
+
+- Three
+
+  - Nested
+
+  Four
+"#;
+    test_cmark(input, expected);
+
+    let expected = r#"- One
+  continued
+
+    Two
+
+      code
+
+- Three
+
+    - Nested
+
+    Four
+"#;
+    let cmfmt = Formatter::default().with_continuation_indent(ContinuationIndent::Four);
+    test_cmark_with(&cmfmt, input, expected);
+
+    // Blocks after the text of tight items are continuation blocks.
+    let input = "* x\n* y:\n\n  ```sh\n  code\n  ```\n* a\n  > b\n";
+    let expected = "- x\n- y:\n    ```sh\n    code\n    ```\n\n- a\n    > b\n";
+    test_cmark_with(&cmfmt, input, expected);
+    test_cmark_with(&cmfmt, expected, expected);
+
+    let expected = "- x\n- y:\n  ```sh\n  code\n  ```\n\n- a\n  > b\n";
+    test_cmark(input, expected);
+}
+
+#[test]
+fn test_list13() {
+    let input = r#"
+- [X] One
+- [x] Two
+- [ ] Three"#;
+
+    let expected = r#"- [x] One
+- [x] Two
+- [ ] Three
+"#;
+    test_cmark(input, expected);
+
+    let expected = r#"- [X] One
+- [x] Two
+- [ ] Three
+"#;
+    test_cmark_with(
+        &Formatter::default().with_task_checked(None),
+        input,
+        expected,
+    );
+
+    let expected = r#"- [X] One
+- [X] Two
+- [ ] Three
+"#;
+    test_cmark_with(
+        &Formatter::default().with_task_checked(Some('X')),
+        input,
+        expected,
+    );
 }
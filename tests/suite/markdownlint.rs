@@ -0,0 +1,111 @@
+use cmarkfmt::{
+    BlockquoteJoin, BulletStyle, EmptyLinks, FormatterConfig, HeadingStyle, Markdownlint,
+};
+
+#[test]
+fn test_markdownlint1() {
+    let markdownlint = Markdownlint::from_yaml(
+        r#"
+default: true
+ul-style:
+  style: plus
+MD028: false
+no-empty-links: false
+strong-style:
+  style: underscore
+"#,
+    )
+    .unwrap();
+
+    let mut config = FormatterConfig {
+        blockquote_spacing: Some(2),
+        ..FormatterConfig::default()
+    };
+    markdownlint.apply(&mut config);
+
+    assert_eq!(config.unordered_list.as_deref(), Some("+"));
+    assert_eq!(config.blockquote_spacing, Some(2));
+    assert_eq!(config.blockquote_join, None);
+    assert_eq!(config.empty_links, None);
+    assert_eq!(config.emphasis, None);
+    assert_eq!(config.strong.as_deref(), Some("__"));
+}
+
+#[test]
+fn test_markdownlint2() {
+    let markdownlint = Markdownlint::from_json(r#"{ "default": false, "md042": true }"#).unwrap();
+    assert!(markdownlint.is_enabled(&["MD042", "no-empty-links"]));
+    assert!(!markdownlint.is_enabled(&["MD028", "no-blanks-blockquote"]));
+
+    let mut config = FormatterConfig::default();
+    markdownlint.apply(&mut config);
+    assert_eq!(config.empty_links, Some(EmptyLinks::Diagnose));
+    assert_ne!(config.blockquote_join, Some(BlockquoteJoin::Merge));
+
+    assert!(Markdownlint::from_json("[1]").is_err());
+
+    // Enabling MD028 doesn't join blockquotes.
+    let mut config = FormatterConfig::default();
+    Markdownlint::from_json(r#"{ "MD028": true }"#)
+        .unwrap()
+        .apply(&mut config);
+    assert_eq!(config.blockquote_join, None);
+}
+
+#[test]
+fn test_markdownlint3() {
+    let mut config = FormatterConfig::default();
+    Markdownlint::from_json("{}").unwrap().apply(&mut config);
+    assert_eq!(config.line_width, None);
+
+    let markdownlint = Markdownlint::from_json(r#"{ "line-length": { "line_length": 100 } }"#);
+    markdownlint.unwrap().apply(&mut config);
+    assert_eq!(config.line_width, Some(100));
+}
+
+#[test]
+fn test_markdownlint4() {
+    let markdownlint = Markdownlint::from_json(r#"{ "MD003": { "style": "setext_with_atx" } }"#);
+    let mut config = FormatterConfig::default();
+    markdownlint.unwrap().apply(&mut config);
+    assert_eq!(config.heading_style, Some(HeadingStyle::Setext));
+
+    let markdownlint = Markdownlint::from_json(r#"{ "MD004": { "style": "sublist" } }"#);
+    let mut config = FormatterConfig::default();
+    markdownlint.unwrap().apply(&mut config);
+    assert_eq!(config.bullet_style, Some(BulletStyle::Depth));
+    assert_eq!(config.unordered_list, None);
+}
+
+#[test]
+fn test_markdownlint5() {
+    let markdownlint =
+        Markdownlint::from_json(r#"{ "MD022": { "lines_above": 2, "lines_below": -1 } }"#);
+    let mut config = FormatterConfig::default();
+    markdownlint.unwrap().apply(&mut config);
+    assert_eq!(config.blank_lines_before_headings, Some(2));
+    assert_eq!(config.blank_lines_after_headings, None);
+}
+
+#[test]
+fn test_markdownlint6() {
+    let markdownlint = Markdownlint::from_json(
+        r#"{
+    // Lists use asterisks.
+    "ul-style": { "style": "asterisk", },
+    /* "MD049": { "style": "underscore" }, */
+    "emphasis-style": { "style": "asterisk" /* not "a // b" */ },
+}"#,
+    )
+    .unwrap();
+    let mut config = FormatterConfig::default();
+    markdownlint.apply(&mut config);
+    assert_eq!(config.unordered_list.as_deref(), Some("*"));
+    assert_eq!(config.emphasis.as_deref(), Some("*"));
+
+    let markdownlint = Markdownlint::from_json(r#"{ "x": "a // b, ]" }"#).unwrap();
+    assert_eq!(
+        markdownlint,
+        Markdownlint::from_yaml("x: 'a // b, ]'").unwrap()
+    );
+}
@@ -1,7 +1,23 @@
-pub use super::test_cmark;
+pub use super::{test_cmark, test_cmark_with};
 
 mod blockquote;
+mod blocks;
+mod code;
+mod comments;
+#[cfg(feature = "config")]
+mod config;
+#[cfg(feature = "config")]
+mod editorconfig;
+mod emphasis;
+mod footnote;
 mod general;
+mod heading;
+mod ignore;
 mod link;
 mod list;
+#[cfg(feature = "markdownlint")]
+mod markdownlint;
+mod preset;
+mod prettier;
 mod table;
+mod wrap;
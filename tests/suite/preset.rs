@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use cmarkfmt::{Formatter, Preset, PresetMap};
+
+use super::test_cmark_with;
+
+#[test]
+fn test_preset_readme1() {
+    let input = r#"<p align="center">
+  <img src="logo.png">
+
+    <b>Logo</b>
+</p>
+
+[![CI](https://img.shields.io/ci.svg)](https://ci)  ![License](https://img.shields.io/license.svg)
+# Project
+Some *text*."#;
+
+    let expected = r#"<p align="center">
+  <img src="logo.png">
+
+    <b>Logo</b>
+</p>
+
+[![CI](https://img.shields.io/ci.svg)](https://ci)  ![License](https://img.shields.io/license.svg)
+
+# Project
+
+Some _text_.
+"#;
+
+    test_cmark_with(&Formatter::preset(Preset::Readme), input, expected);
+}
+
+#[test]
+fn test_preset_readme2() {
+    let input = r#"
+|Crate|Status|
+|---|---|
+|core|[![ci](https://img.shields.io/a.svg)](https://ci)|
+
+|Title|Description|
+|---|---|
+|Test|This is a test|"#;
+
+    let expected = r#"|Crate|Status|
+|---|---|
+|core|[![ci](https://img.shields.io/a.svg)](https://ci)|
+
+| Title | Description    |
+| ----- | -------------- |
+| Test  | This is a test |
+"#;
+
+    test_cmark_with(&Formatter::preset(Preset::Readme), input, expected);
+}
+
+#[test]
+fn test_preset_readme3() {
+    let input = r#"<p valign="top">
+
+Some *text*.
+</p>
+
+<p align = "center">
+
+Some *text*.
+</p>
+
+|A|B|
+|-|-|
+|[![x](https://example.com/badge-guide.png)](https://x)|b|
+
+|A|B|
+|-|-|
+|![x](https://github.com/o/r/actions/workflows/ci.yml/badge.svg?branch=main)|b|"#;
+
+    let expected = r#"<p valign="top">
+
+Some _text_.
+
+</p>
+<p align = "center">
+
+Some *text*.
+</p>
+
+| A                                                      | B   |
+| ------------------------------------------------------ | --- |
+| [![x](https://example.com/badge-guide.png)](https://x) | b   |
+
+|A|B|
+|-|-|
+|![x](https://github.com/o/r/actions/workflows/ci.yml/badge.svg?branch=main)|b|
+"#;
+
+    test_cmark_with(&Formatter::preset(Preset::Readme), input, expected);
+}
+
+#[test]
+fn test_preset_github1() {
+    let input = r#"
+> [!Warning]
+> Don't *do* this.
+
+> [!NOTE] and [!TIP]
+
+[!NOTE]"#;
+
+    let expected = r#"> [!Warning]
+> Don't _do_ this.
+
+> \[!NOTE\] and \[!TIP\]
+
+\[!NOTE\]
+"#;
+
+    test_cmark_with(&Formatter::preset(Preset::Github), input, expected);
+}
+
+#[test]
+fn test_preset_commonmark_strict1() {
+    let input = r#"
+| a | b |
+|---|---|
+| 1 | 2 |
+
+Some ~~struck~~ _text_.
+
+- one
+- two"#;
+
+    let expected = r#"| a | b |
+|---|---|
+| 1 | 2 |
+
+Some ~~struck~~ *text*.
+
+* one
+* two
+"#;
+
+    test_cmark_with(
+        &Formatter::preset(Preset::CommonmarkStrict),
+        input,
+        expected,
+    );
+}
+
+#[test]
+fn test_preset_prettier1() {
+    let input = r#"
+***Both*** and *emphasis*.
+
+* item"#;
+
+    let expected = r#"**_Both_** and _emphasis_.
+
+- item
+"#;
+
+    test_cmark_with(&Formatter::preset(Preset::Prettier), input, expected);
+}
+
+#[test]
+fn test_preset_map1() {
+    let map = PresetMap::default()
+        .with("*.mdx", Preset::Readme)
+        .with("README*", Preset::Readme);
+
+    assert_eq!(
+        map.resolve(Path::new("a/b/guide.md")),
+        Some(Preset::Default)
+    );
+    assert_eq!(
+        map.resolve(Path::new("notes.markdown")),
+        Some(Preset::Default)
+    );
+    assert_eq!(map.resolve(Path::new("page.mdx")), Some(Preset::Readme));
+    assert_eq!(map.resolve(Path::new("README.md")), Some(Preset::Readme));
+    assert_eq!(map.resolve(Path::new("readme.txt")), None);
+    assert_eq!(PresetMap::empty().resolve(Path::new("a.md")), None);
+
+    // `?` matches a single character, not a single byte.
+    let map = PresetMap::empty().with("r?sum?.md", Preset::Readme);
+    assert_eq!(map.resolve(Path::new("résumé.md")), Some(Preset::Readme));
+}
+
+#[test]
+fn test_preset_parse1() {
+    assert_eq!("readme".parse(), Ok(Preset::Readme));
+    assert_eq!("Default".parse(), Ok(Preset::Default));
+    assert_eq!("commonmark-strict".parse(), Ok(Preset::CommonmarkStrict));
+    assert_eq!(Preset::Mdbook.to_string(), "mdbook");
+    assert!("pandoc".parse::<Preset>().is_err());
+}
@@ -0,0 +1,112 @@
+use cmarkfmt::{Formatter, Preset};
+
+use super::test_cmark_with;
+
+fn prettier() -> Formatter<'static> {
+    Formatter::preset(Preset::Prettier)
+}
+
+#[test]
+fn test_prettier1() {
+    let input = r#"
+3. Three
+3. Four
+3. Five
+
+# Ones
+
+1. One
+1. One
+1. One
+
+# Zero
+
+0. Zero
+1. One
+1. One"#;
+
+    let expected = r#"3. Three
+4. Four
+5. Five
+
+# Ones
+
+1. One
+1. One
+1. One
+
+# Zero
+
+0. Zero
+1. One
+1. One
+"#;
+
+    test_cmark_with(&prettier(), input, expected);
+}
+
+#[test]
+fn test_prettier2() {
+    let input = r#"
+* One
+* Two
+
++ Three
+
+- Four
+
+1. Five
+
+1) Six
+
+Text
+
++ Seven"#;
+
+    let expected = r#"- One
+- Two
+
+* Three
+
+- Four
+
+1. Five
+
+1) Six
+
+Text
+
+- Seven
+"#;
+
+    test_cmark_with(&prettier(), input, expected);
+}
+
+#[test]
+fn test_prettier3() {
+    let input = r#"
+| Left | Center | Right |
+|------|:------:|------:|
+| a | b | c |
+| longer | longest cell | 1 |"#;
+
+    let expected = r#"| Left   |    Center    | Right |
+| ------ | :----------: | ----: |
+| a      |      b       |     c |
+| longer | longest cell |     1 |
+"#;
+
+    test_cmark_with(&prettier(), input, expected);
+}
+
+#[test]
+fn test_prettier4() {
+    let input = r#"
+Spaces  
+Backslash\
+End"#;
+
+    let expected = "Spaces  \nBackslash\\\nEnd\n";
+
+    test_cmark_with(&prettier(), input, expected);
+}
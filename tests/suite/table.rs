@@ -1,4 +1,6 @@
-use super::test_cmark;
+use cmarkfmt::{Formatter, RaggedRows, Rule, TableLineBreak, TableStyle, WidthMode};
+
+use super::{test_cmark, test_cmark_with};
 
 #[test]
 fn test_table1() {
@@ -138,3 +140,530 @@ fn test_table9() {
 
     test_cmark(input, expected);
 }
+
+#[test]
+fn test_table10() {
+    let input = r#"
+|Name|Emoji|
+|---|---|
+|日本語|🎉|
+|abc|x|"#;
+
+    let expected = r#"| Name | Emoji |
+| ---- | ----- |
+| 日本語  | 🎉     |
+| abc  | x     |
+"#;
+
+    test_cmark(input, expected);
+
+    if cfg!(feature = "unicode-width") {
+        let expected = r#"| Name   | Emoji |
+| ------ | ----- |
+| 日本語 | 🎉    |
+| abc    | x     |
+"#;
+
+        let cmfmt = Formatter::default().with_table_width_mode(WidthMode::Display);
+        test_cmark_with(&cmfmt, input, expected);
+    }
+}
+
+#[test]
+fn test_table11() {
+    let input = r#"
+Name | Value
+:--- | ---:
+a    |     1
+
+> - |a|b|
+>   |-|-|
+>   |1|2|
+
+|a|b|
+|-|-|
+|1|2|3|"#;
+
+    let expected = r#"Name | Value
+:--- | ---:
+a    |     1
+
+> - |a|b|
+>   |-|-|
+>   |1|2|
+
+| a   | b   |
+| --- | --- |
+| 1   | 2   |
+"#;
+
+    let cmfmt = Formatter::default().with_preserve_tables(true);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_table12() {
+    let input = r#"
+|Name|Description|
+|---|:---:|
+|a|A cell that is far too long to be padded|
+|b|Short|
+|c|`Code that is never broken`|"#;
+
+    let expected = r#"| Name | Description |
+| ---- | :---------: |
+| a    | A cell that is far too long to be padded |
+| b    | Short       |
+| c    | `Code that is never broken` |
+"#;
+
+    let cmfmt = Formatter::default().with_table_cell_width(Some(11));
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"| Name | Description |
+| ---- | :---------: |
+| a    | A cell that<br>is far too<br>long to be<br>padded |
+| b    | Short       |
+| c    | `Code that is never broken` |
+"#;
+
+    let cmfmt = cmfmt.with_table_cell_wrap(true);
+    test_cmark_with(&cmfmt, input, expected);
+    test_cmark_with(&cmfmt, expected, expected);
+
+    // Lines may start with code spans, and links and emphasis aren't broken.
+    let input = r#"
+| When `serde_json` depends on `serde` |
+| - |
+| [link text here](/url) and *a b c d* |"#;
+
+    let expected = r#"| When<br>`serde_json`<br>depends on<br>`serde` |
+| ----------- |
+| [link text here](/url)<br>and<br>_a b c d_ |
+"#;
+
+    test_cmark_with(&cmfmt, input, expected);
+    test_cmark_with(&cmfmt, expected, expected);
+}
+
+#[test]
+fn test_table13() {
+    let input = r#"
+|a|b|
+|-|-|
+|1|
+|2|3|
+|4|5|6 \| 7|8|"#;
+
+    let expected = r#"| a   | b   |
+| --- | --- |
+| 1   |     |
+| 2   | 3   |
+| 4   | 5   |
+"#;
+
+    test_cmark(input, expected);
+
+    let expected = r#"| a   | b   |        |     |
+| --- | --- | ------ | --- |
+| 1   |     |        |     |
+| 2   | 3   |        |     |
+| 4   | 5   | 6 \| 7 | 8   |
+"#;
+
+    let cmfmt = Formatter::default().with_ragged_rows(RaggedRows::Extend);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let diagnostics = cmfmt
+        .lint(input)
+        .into_iter()
+        .map(|d| (d.rule, d.line, d.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        vec![
+            (
+                Rule::RaggedTableRow,
+                4,
+                "expected 2 table cells, found 1".to_string()
+            ),
+            (
+                Rule::RaggedTableRow,
+                6,
+                "expected 2 table cells, found 4".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_table14() {
+    let input = r#"
+| Name | Value |
+|:-----|------:|
+| a    | 1     |
+
+Name | Value
+-----|------
+b    | 2
+
+| Name | Value |
+|------|-------|
+|      | 3     |"#;
+
+    let expected = r#"Name | Value
+:--- | ----:
+a    | 1
+
+Name | Value
+---- | -----
+b    | 2
+
+| Name | Value |
+| ---- | ----- |
+|      | 3     |
+"#;
+
+    let cmfmt = Formatter::default().with_table_style(TableStyle::Unpiped);
+    test_cmark_with(&cmfmt, input, expected);
+    test_cmark_with(&cmfmt, expected, expected);
+
+    let expected = r#"| Name | Value |
+| :--- | ----: |
+| a    | 1     |
+
+Name | Value
+---- | -----
+b    | 2
+
+| Name | Value |
+| ---- | ----- |
+|      | 3     |
+"#;
+
+    let cmfmt = Formatter::default().with_table_style(TableStyle::Preserve);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_table15() {
+    let input = r#"
+|Item|Change|Share|Code|Note|
+|---|---|:-:|---|---|
+|a|-1,234.5|50%|007|1|
+|b|+2||1.2.3||
+|c|3|25%|4|x|"#;
+
+    let expected = r#"| Item |    Change | Share | Code  | Note |
+| ---- | --------: | :---: | ----- | ---- |
+| a    | \-1,234.5 |  50%  | 007   | 1    |
+| b    |       \+2 |       | 1.2.3 |      |
+| c    |         3 |  25%  | 4     | x    |
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_align_numeric_columns(true)
+        .with_prettier(true);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_table16() {
+    let input = r#"
+| name | price |
+|------|-------|
+| tea  | 1.5 |
+| cake | 12 |"#;
+
+    let expected = r#"| NAME | PRICE |
+| ---- | ----- |
+| tea  | 1.50  |
+| cake | 12.00 |
+"#;
+
+    let format = |column: usize, text: &str| match text.parse::<f64>() {
+        Ok(price) if column == 1 => Some(format!("{price:.2}")),
+        Err(_) if text == "name" || text == "price" => Some(text.to_uppercase()),
+        _ => None,
+    };
+    let cmfmt = Formatter::default().with_table_cell_fmt(Some(&format));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_table17() {
+    let input = r#"
+> |Name|Description|
+> |---|---|
+> |a|Short|
+> |b|A much longer description|
+> |c|
+
+After."#;
+
+    let expected = r#"> | Name | Description |
+> | ---- | ----------- |
+> | a    | Short       |
+> | b    | A much longer description |
+> | c    |             |
+
+After.
+"#;
+
+    let cmfmt = Formatter::default().with_table_streaming(true);
+    test_cmark_with(&cmfmt, input, expected);
+
+    let expected = r#"> | Name     | Description |
+> | -------- | ----------- |
+> | a        | Short       |
+> | b        | A much longer description |
+> | c        |             |
+
+After.
+"#;
+
+    let cmfmt = cmfmt.with_table_cell_width(Some(8));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_table18() {
+    let input = r#"
+<!-- cmarkfmt: sort-by=2 -->
+|Name|Size|
+|---|---|
+|delta|1,000|
+|Bravo|-5|
+|alpha|20|
+|charlie|3.5|
+
+|Name|Size|
+|---|---|
+|b|2|
+|a|1|"#;
+
+    let expected = r#"<!-- cmarkfmt: sort-by=2 -->
+
+| Name    | Size  |
+| ------- | ----- |
+| Bravo   | \-5   |
+| charlie | 3.5   |
+| alpha   | 20    |
+| delta   | 1,000 |
+
+| Name | Size |
+| ---- | ---- |
+| b    | 2    |
+| a    | 1    |
+"#;
+
+    test_cmark(input, expected);
+
+    let expected = r#"<!-- cmarkfmt: sort-by=2 -->
+
+| Name    | Size  |
+| ------- | ----- |
+| Bravo   | \-5   |
+| charlie | 3.5   |
+| alpha   | 20    |
+| delta   | 1,000 |
+
+| Name | Size |
+| ---- | ---- |
+| a    | 1    |
+| b    | 2    |
+"#;
+
+    let cmfmt = Formatter::default().with_table_sort(Some(&|_| Some(0)));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_table19() {
+    let input = r#"
+|Name|Pattern|
+|:-:|---|
+|**Bold**|`a \| b`|
+|Plain|[link](/url)|
+|x|\*y\*|"#;
+
+    let expected = r#"| Name  | Pattern |
+| :---: | ------- |
+| **Bold**  | `a \| b`  |
+| Plain | [link](/url)    |
+|   x   | \*y\*     |
+"#;
+
+    let cmfmt = Formatter::default()
+        .with_prettier(true)
+        .with_table_rendered_width(true);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_table20() {
+    let input = r#"
+|a|b|
+|-|-|
+|1|2|
+Table: A caption
+
+|a|b|
+|-|-|
+|1|2|
+[Another caption]
+
+|a|b|
+|-|-|
+[not a caption]
+|1|2|"#;
+
+    let expected = r#"| a   | b   |
+| --- | --- |
+| 1   | 2   |
+Table: A caption
+
+| a   | b   |
+| --- | --- |
+| 1   | 2   |
+[Another caption]
+
+| a                 | b   |
+| ----------------- | --- |
+| \[not a caption\] |     |
+| 1                 | 2   |
+"#;
+
+    test_cmark(input, expected);
+}
+
+#[test]
+fn test_table21() {
+    let input = r#"
+<table>
+<tr><th>A</th><th>B</th></tr>
+<tr><td colspan="2">Spanned</td></tr>
+</table>
+
+<table>
+  <thead>
+    <tr><td>Name</td><td align="right">Size</td></tr>
+  </thead>
+  <tbody>
+    <tr><td><code>a|b</code> &amp; c</td><td>1</td></tr>
+    <tr><td>
+      two
+      lines
+    </td><td>22</td></tr>
+  </tbody>
+</table>"#;
+
+    let expected = r#"<table>
+<tr><th>A</th><th>B</th></tr>
+<tr><td colspan="2">Spanned</td></tr>
+</table>
+
+| Name                  | Size |
+| --------------------- | ---: |
+| <code>a\|b</code> & c | 1    |
+| two lines             | 22   |
+"#;
+
+    let cmfmt = Formatter::default().with_convert_html_tables(true);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_table22() {
+    let input = r#"
+|Name|Notes|
+|-|-|
+|a|first line<br/>second<BR />third|
+|b|short|"#;
+
+    let expected = r#"| Name | Notes                         |
+| ---- | ----------------------------- |
+| a    | first line<br>second<br>third |
+| b    | short                         |
+"#;
+
+    let cmfmt = Formatter::default().with_table_line_break(TableLineBreak::Br);
+    test_cmark_with(&cmfmt, input, expected);
+
+    // Cells with line breaks are aligned by their full text.
+    test_cmark("|a<br>b|\n|-|", "| a<br>b |\n| ------ |\n");
+}
+
+#[test]
+fn test_table23() {
+    let input = r#"
+<!-- cmarkfmt: table=compact -->
+|Name|Size|
+|:-:|-:|
+|a|1|
+
+<!-- cmarkfmt: table=preserve, sort-by=1 -->
+|Name|Size|
+|:-:|-:|
+|b|2|
+|a|1|
+
+<!-- cmarkfmt: no-align -->
+|Name|Size|
+|:-:|-:|
+|a|1|"#;
+
+    let expected = r#"<!-- cmarkfmt: table=compact -->
+
+| Name | Size |
+| :-: | --: |
+| a | 1 |
+
+<!-- cmarkfmt: table=preserve, sort-by=1 -->
+
+| Name | Size |
+| :--: | ---: |
+|  a   |    1 |
+|  b   |    2 |
+
+<!-- cmarkfmt: no-align -->
+
+| Name | Size |
+| :--: | ---: |
+| a    | 1    |
+"#;
+
+    let cmfmt = Formatter::default().with_prettier(true);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_table24() {
+    let input = r#"
+||b|
+|-|-|
+|||
+| x |"#;
+
+    let expected = r#"|     | b   |
+| --- | --- |
+|     |     |
+| x   |     |
+"#;
+
+    test_cmark(input, expected);
+
+    let diagnostics = Formatter::default()
+        .lint(input)
+        .into_iter()
+        .map(|d| (d.rule, d.line, d.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        vec![(
+            Rule::RaggedTableRow,
+            5,
+            "expected 2 table cells, found 1".to_string()
+        )]
+    );
+}
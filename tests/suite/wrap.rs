@@ -0,0 +1,156 @@
+use cmarkfmt::{Formatter, LineBreakStyle};
+
+use super::test_cmark_with;
+
+#[test]
+fn test_wrap1() {
+    let input = r#"
+Some text that goes on and on,
+with a soft break in the middle of it.
+
+Hard break\
+then more text that is long enough to wrap."#;
+
+    let expected = r#"Some text that goes on and on,
+with a soft break in the
+middle of it.
+
+Hard break\
+then more text that is long
+enough to wrap.
+"#;
+
+    let cmfmt = Formatter::default().with_line_width(Some(30));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_wrap2() {
+    let input = r#"
+- A list item with a lot of text in it
+  1. A nested ordered item with plenty of text
+
+> A quote with text that wraps nicely over lines."#;
+
+    let expected = r#"- A list item with a
+  lot of text in it
+  1. A nested
+     ordered item
+     with plenty of
+     text
+
+> A quote with text
+> that wraps nicely
+> over lines.
+"#;
+
+    let cmfmt = Formatter::default().with_line_width(Some(20));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_wrap3() {
+    let input = r#"
+Text with `a code span` and <span class="a b">html</span> kept
+together, and 1. - # + > characters that mustn't start lines."#;
+
+    let expected = r#"Text with
+`a code span`
+and <span class="a b">html</span>
+kept together,
+and 1. - # + >
+characters that
+mustn't start
+lines.
+"#;
+
+    let cmfmt = Formatter::default().with_line_width(Some(16));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_wrap4() {
+    let input = r#"
+# A long heading that isn't wrapped
+
+| Column | Another column |
+| --- | --- |
+| a long cell | b |
+
+```
+a long line of code that isn't wrapped
+```"#;
+
+    let expected = r#"# A long heading that isn't wrapped
+
+| Column      | Another column |
+| ----------- | -------------- |
+| a long cell | b              |
+
+```
+a long line of code that isn't wrapped
+```
+"#;
+
+    let cmfmt = Formatter::default().with_line_width(Some(10));
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_wrap5() {
+    let input = r#"
+One paragraph,
+one line.
+
+> Quoted [link
+> text](https://example.com)
+> and `code
+> span`.
+
+Hard break\
+kept."#;
+
+    let expected = r#"One paragraph, one line.
+
+> Quoted [link text](https://example.com) and `code span`.
+
+Hard break\
+kept.
+"#;
+
+    let cmfmt = Formatter::default().with_line_break_style(LineBreakStyle::Join);
+    test_cmark_with(&cmfmt, input, expected);
+}
+
+#[test]
+fn test_wrap6() {
+    let input = r#"
+Some [link](</my uri>) and ![image](<x y.png>) text.
+
+> A quoted [link](/url 'foo bar') here.
+
+- Items
+  - [ ] barbaz
+
+Some \a\ \3 text."#;
+
+    let expected = r#"Some
+[link](</my uri>)
+and
+![image](<x y.png>)
+text.
+
+> A quoted
+> [link](/url "foo bar")
+> here.
+
+- Items
+  - [ ] barbaz
+
+Some \a\ \3
+text.
+"#;
+
+    let cmfmt = Formatter::default().with_line_width(Some(10));
+    test_cmark_with(&cmfmt, input, expected);
+}